@@ -0,0 +1,29 @@
+use std::{env, fs, path::Path};
+
+fn main() {
+    versions();
+
+    #[cfg(feature = "grpc")]
+    {
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path().unwrap());
+        tonic_prost_build::compile_protos("proto/meexprox.proto").unwrap();
+    }
+}
+
+/// Generates table of release versions by protocol version, included by protocol module
+fn versions() {
+    println!("cargo:rerun-if-changed=proto/versions.txt");
+
+    let data = fs::read_to_string("proto/versions.txt").unwrap();
+    let mut table = String::from("&[\n");
+    for line in data.lines().map(str::trim).filter(|o| !o.is_empty() && !o.starts_with('#')) {
+        let mut fields = line.split_whitespace();
+        let protocol: u16 = fields.next().unwrap().parse().unwrap();
+        let names: Vec<String> = fields.map(|o| format!("{:?}", o)).collect();
+        assert!(!names.is_empty(), "protocol version {} has no release versions", protocol);
+        table += &format!("    ({}, &[{}]),\n", protocol, names.join(", "));
+    }
+    table += "]\n";
+
+    fs::write(Path::new(&env::var("OUT_DIR").unwrap()).join("versions.rs"), table).unwrap();
+}
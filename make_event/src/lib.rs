@@ -1,28 +1,37 @@
 
 use proc_macro::TokenStream;
 use quote::{quote, format_ident};
-use syn::{parse_macro_input, DeriveInput, Data, Fields};
+use syn::{parse_macro_input, punctuated::Punctuated, DeriveInput, Data, Fields, Ident, Token};
 
-#[proc_macro_derive(MakeEvent, attributes(MakeEvent, setter))]
+/// Implements `Event` with getters, setters and `new()` taking every field except `cancelled`.
+/// With `#[MakeEvent("name", builder)]` also generates typed builder, where fields without `#[optional]`
+/// have to be set before `build()` can be called and `#[optional]` fields start with default value
+#[proc_macro_derive(MakeEvent, attributes(MakeEvent, setter, mutable, optional))]
 pub fn make_event_derive(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let struct_name = &input.ident;
-    let event_name = input.attrs.iter()
+    let args = input.attrs.iter()
         .find_map(|attr| {
             if attr.path().is_ident("MakeEvent") {
-                attr.parse_args::<syn::LitStr>().ok()
+                attr.parse_args_with(Punctuated::<syn::Expr, Token![,]>::parse_terminated).ok()
             } else {
                 None
             }
         })
-        .expect("Expected MakeEvent attribute with a name argument")
-        .value();
+        .expect("Expected MakeEvent attribute with a name argument");
+    let event_name = match args.first() {
+        Some(syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(name), .. })) => name.value(),
+        _ => panic!("Expected MakeEvent attribute with a name argument")
+    };
+    let with_builder = args.iter().skip(1).any(|o| matches!(o, syn::Expr::Path(path) if path.path.is_ident("builder")));
 
     let mut getters = Vec::new();
     let mut setters = Vec::new();
     let mut new_args = Vec::new();
     let mut new_inits = Vec::new();
     let mut has_cancelled_field = false;
+    let mut required_fields = Vec::new();
+    let mut optional_fields = Vec::new();
 
     if let Data::Struct(data) = input.data {
         if let Fields::Named(fields) = data.fields {
@@ -35,6 +44,12 @@ pub fn make_event_derive(input: TokenStream) -> TokenStream {
                 } else {
                     new_args.push(quote! { #field_name: #field_ty });
                     new_inits.push(quote! { #field_name });
+
+                    if field.attrs.iter().any(|attr| attr.path().is_ident("optional")) {
+                        optional_fields.push((field_name.clone().unwrap(), field_ty.clone()));
+                    } else {
+                        required_fields.push((field_name.clone().unwrap(), field_ty.clone()));
+                    }
                 }
 
                 getters.push(quote! {
@@ -51,6 +66,15 @@ pub fn make_event_derive(input: TokenStream) -> TokenStream {
                         }
                     });
                 }
+
+                if field.attrs.iter().any(|attr| attr.path().is_ident("mutable")) {
+                    let getter_mut_name = format_ident!("{}_mut", field_name.as_ref().unwrap());
+                    setters.push(quote! {
+                        pub fn #getter_mut_name(&mut self) -> &mut #field_ty {
+                            &mut self.#field_name
+                        }
+                    });
+                }
             }
         }
     } else {
@@ -90,8 +114,18 @@ pub fn make_event_derive(input: TokenStream) -> TokenStream {
         }
     };
 
+    let builder = if with_builder {
+        make_builder(struct_name, &required_fields, &optional_fields, has_cancelled_field)
+    } else {
+        quote! {}
+    };
+
     let expanded = quote! {
+        #builder
+
         impl #struct_name {
+            pub const NAME: &'static str = #event_name;
+
             #(#getters)*
             #(#setters)*
             #new_method
@@ -101,10 +135,340 @@ pub fn make_event_derive(input: TokenStream) -> TokenStream {
             #cancel_methods
 
             fn name(&self) -> String {
-                #event_name.to_string()
+                Self::NAME.to_string()
             }
         }
     };
 
     TokenStream::from(expanded)
 }
+
+/// Builder with type parameter for every required field, `()` until field is set
+fn make_builder(
+    struct_name: &Ident,
+    required: &[(Ident, syn::Type)],
+    optional: &[(Ident, syn::Type)],
+    has_cancelled_field: bool
+) -> proc_macro2::TokenStream {
+    let builder_name = format_ident!("{}Builder", struct_name);
+    let generics: Vec<Ident> = (0..required.len()).map(|i| format_ident!("F{}", i)).collect();
+    let required_names: Vec<&Ident> = required.iter().map(|o| &o.0).collect();
+    let required_types: Vec<&syn::Type> = required.iter().map(|o| &o.1).collect();
+    let optional_names: Vec<&Ident> = optional.iter().map(|o| &o.0).collect();
+    let optional_types: Vec<&syn::Type> = optional.iter().map(|o| &o.1).collect();
+    let unset: Vec<proc_macro2::TokenStream> = required.iter().map(|_| quote! { () }).collect();
+
+    let required_setters = required.iter().enumerate().map(|(i, (name, ty))| {
+        let output: Vec<proc_macro2::TokenStream> = generics.iter().enumerate()
+            .map(|(j, o)| if i == j { quote! { #ty } } else { quote! { #o } })
+            .collect();
+        let moved: Vec<proc_macro2::TokenStream> = required_names.iter()
+            .map(|o| if *o == name { quote! { #o: value } } else { quote! { #o: self.#o } })
+            .collect();
+        quote! {
+            pub fn #name(self, value: #ty) -> #builder_name<#(#output),*> {
+                #builder_name {
+                    #(#moved,)*
+                    #(#optional_names: self.#optional_names),*
+                }
+            }
+        }
+    });
+
+    let cancelled = if has_cancelled_field {
+        quote! { cancelled: false, }
+    } else {
+        quote! {}
+    };
+
+    quote! {
+        pub struct #builder_name<#(#generics),*> {
+            #(#required_names: #generics,)*
+            #(#optional_names: #optional_types),*
+        }
+
+        impl #struct_name {
+            pub fn builder() -> #builder_name<#(#unset),*> {
+                #builder_name {
+                    #(#required_names: (),)*
+                    #(#optional_names: Default::default()),*
+                }
+            }
+        }
+
+        impl<#(#generics),*> #builder_name<#(#generics),*> {
+            #(#required_setters)*
+
+            #(
+                pub fn #optional_names(mut self, value: #optional_types) -> Self {
+                    self.#optional_names = value;
+                    self
+                }
+            )*
+        }
+
+        impl #builder_name<#(#required_types),*> {
+            pub fn build(self) -> #struct_name {
+                #struct_name {
+                    #cancelled
+                    #(#required_names: self.#required_names,)*
+                    #(#optional_names: self.#optional_names),*
+                }
+            }
+        }
+    }
+}
+
+/// Generates registration of methods marked with `#[event_handler]` in impl block,
+/// every handler takes `&mut self` or `&self` and `&mut` event, and returns nothing or `Result<(), ProxyError>`.
+/// Handlers can be ordered with `#[priority(High)]` and `#[order(before = "Listener::method", after = "...")]`
+#[proc_macro_attribute]
+pub fn event_listener(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut input = parse_macro_input!(item as syn::ItemImpl);
+    let self_ty = input.self_ty.clone();
+    let self_name = match &*self_ty {
+        syn::Type::Path(path) => path.path.segments.last().unwrap().ident.clone(),
+        _ => panic!("event_listener can only be used on impl of named type")
+    };
+    let mut listener_impls = Vec::new();
+    let mut registrations = Vec::new();
+
+    for item in input.items.iter_mut() {
+        let syn::ImplItem::Fn(method) = item else { continue };
+
+        let len = method.attrs.len();
+        method.attrs.retain(|attr| !attr.path().is_ident("event_handler"));
+        if method.attrs.len() == len {
+            continue;
+        }
+
+        let method_name = &method.sig.ident;
+        let label = format!("{}::{}", self_name, method_name);
+        let handler_name = format_ident!("{}_{}_handler", self_name, method_name);
+
+        let mut priority = format_ident!("Normal");
+        let mut before = Vec::new();
+        let mut after = Vec::new();
+
+        for attr in method.attrs.iter() {
+            if attr.path().is_ident("priority") {
+                priority = attr.parse_args::<Ident>().expect("Expected priority like #[priority(High)]");
+            } else if attr.path().is_ident("order") {
+                attr.parse_nested_meta(|meta| {
+                    let value = meta.value()?.parse::<syn::LitStr>()?;
+                    if meta.path.is_ident("before") {
+                        before.push(value);
+                    } else if meta.path.is_ident("after") {
+                        after.push(value);
+                    } else {
+                        return Err(meta.error("expected before or after"));
+                    }
+                    Ok(())
+                }).expect("Expected order like #[order(before = \"Listener::method\")]");
+            }
+        }
+        method.attrs.retain(|attr| !attr.path().is_ident("priority") && !attr.path().is_ident("order"));
+
+        let event_ty = match method.sig.inputs.iter().nth(1) {
+            Some(syn::FnArg::Typed(arg)) => match &*arg.ty {
+                syn::Type::Reference(reference) if reference.mutability.is_some() => reference.elem.clone(),
+                _ => panic!("event handler {} has to take event as &mut", method_name)
+            },
+            _ => panic!("event handler {} has to take self and event", method_name)
+        };
+
+        let call = match method.sig.output {
+            syn::ReturnType::Default => quote! {
+                self.0.lock().unwrap_or_else(|o| o.into_inner()).#method_name(event);
+                Ok(())
+            },
+            _ => quote! {
+                self.0.lock().unwrap_or_else(|o| o.into_inner()).#method_name(event)
+            }
+        };
+
+        listener_impls.push(quote! {
+            #[allow(non_camel_case_types)]
+            struct #handler_name(std::sync::Arc<std::sync::Mutex<#self_ty>>);
+
+            impl EventListener<#event_ty> for #handler_name {
+                fn on_event(&self, event: &mut #event_ty) -> Result<(), ProxyError> {
+                    #call
+                }
+
+                fn order(&self) -> ListenerOrder {
+                    ListenerOrder {
+                        priority: EventPriority::#priority,
+                        label: Some(#label.to_string()),
+                        before: vec![#(#before.to_string()),*],
+                        after: vec![#(#after.to_string()),*]
+                    }
+                }
+            }
+        });
+
+        registrations.push(quote! {
+            meexprox.add_event_listener::<#event_ty>(Box::new(#handler_name(listener.clone())));
+        });
+    }
+
+    let expanded = quote! {
+        #input
+
+        #(#listener_impls)*
+
+        impl EventHandlers for #self_ty {
+            fn register(self, meexprox: &mut MeexProx) {
+                let listener = std::sync::Arc::new(std::sync::Mutex::new(self));
+                #(#registrations)*
+            }
+        }
+    };
+
+    TokenStream::from(expanded)
+}
+
+/// Marks event handler method, only valid inside impl block with `#[event_listener]`
+#[proc_macro_attribute]
+pub fn event_handler(_attr: TokenStream, _item: TokenStream) -> TokenStream {
+    syn::Error::new(
+        proc_macro::Span::call_site().into(),
+        "#[event_handler] has to be inside impl block with #[event_listener]"
+    ).to_compile_error().into()
+}
+
+/// Generates `AnyEvent` enum with variant for every listed event type (named without `Event` suffix)
+/// and `EVENTS` registry of event names and type ids, events have to derive `MakeEvent`.
+/// `Event`, `MeexProx` and `ProxyError` have to be in scope
+#[proc_macro]
+pub fn event_registry(input: TokenStream) -> TokenStream {
+    let events = parse_macro_input!(input with Punctuated::<Ident, Token![,]>::parse_terminated);
+
+    let variants: Vec<Ident> = events.iter()
+        .map(|o| {
+            let name = o.to_string();
+            format_ident!("{}", name.strip_suffix("Event").unwrap_or(&name))
+        })
+        .collect();
+    let events: Vec<&Ident> = events.iter().collect();
+
+    let expanded = quote! {
+        /// Event of any registered type
+        pub enum AnyEvent {
+            #(#variants(#events)),*
+        }
+
+        impl AnyEvent {
+            pub fn name(&self) -> &'static str {
+                match self {
+                    #(AnyEvent::#variants(_) => #events::NAME),*
+                }
+            }
+
+            pub fn as_event(&self) -> &dyn Event {
+                match self {
+                    #(AnyEvent::#variants(event) => event),*
+                }
+            }
+
+            pub fn as_event_mut(&mut self) -> &mut dyn Event {
+                match self {
+                    #(AnyEvent::#variants(event) => event),*
+                }
+            }
+
+            /// Calls listeners of event inside
+            pub fn trigger(&mut self, meexprox: &MeexProx) -> Result<(), ProxyError> {
+                match self {
+                    #(AnyEvent::#variants(event) => meexprox.trigger_event(event)),*
+                }
+            }
+
+            /// Type id of event inside
+            pub fn event_type_id(&self) -> std::any::TypeId {
+                match self {
+                    #(AnyEvent::#variants(_) => std::any::TypeId::of::<#events>()),*
+                }
+            }
+        }
+
+        #(
+            impl From<#events> for AnyEvent {
+                fn from(event: #events) -> AnyEvent {
+                    AnyEvent::#variants(event)
+                }
+            }
+        )*
+
+        /// Names and type ids of every registered event
+        pub const EVENTS: &[(&str, fn() -> std::any::TypeId)] = &[
+            #((#events::NAME, std::any::TypeId::of::<#events>)),*
+        ];
+
+        /// Type id of registered event with this name
+        pub fn event_type_id(name: &str) -> Option<std::any::TypeId> {
+            EVENTS.iter().find(|o| o.0 == name).map(|o| (o.1)())
+        }
+    };
+
+    TokenStream::from(expanded)
+}
+
+/// Generates `NAMES` of every variant of record enum and `name()` of record, names are variant names
+/// in snake case, the same as serde `rename_all = "snake_case"` gives. Variants copied from event
+/// are marked with `#[event(SomeEvent)]`, `event_type_id()` of record returns type id of that event
+#[proc_macro_derive(EventRecord, attributes(event))]
+pub fn event_record_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let enum_name = &input.ident;
+
+    let Data::Enum(data) = input.data else {
+        return syn::Error::new(enum_name.span(), "EventRecord can only be derived for enums")
+            .to_compile_error()
+            .into();
+    };
+
+    let variants: Vec<&Ident> = data.variants.iter().map(|o| &o.ident).collect();
+    let names: Vec<String> = variants.iter().map(|o| snake_case(&o.to_string())).collect();
+    let events: Vec<proc_macro2::TokenStream> = data.variants.iter()
+        .map(|o| match o.attrs.iter().find(|o| o.path().is_ident("event")).map(|o| o.parse_args::<syn::Path>()) {
+            Some(Ok(event)) => quote! { Some(std::any::TypeId::of::<#event>()) },
+            Some(Err(err)) => err.to_compile_error(),
+            None => quote! { None }
+        })
+        .collect();
+
+    let expanded = quote! {
+        impl #enum_name {
+            /// Names of every record type
+            pub const NAMES: &'static [&'static str] = &[#(#names),*];
+
+            /// Name of record type, the same as `type` field in json
+            pub fn name(&self) -> &'static str {
+                match self {
+                    #(#enum_name::#variants { .. } => #names),*
+                }
+            }
+
+            /// Type id of event this record is copied from, if record has its own event
+            pub fn event_type_id(&self) -> Option<std::any::TypeId> {
+                match self {
+                    #(#enum_name::#variants { .. } => #events),*
+                }
+            }
+        }
+    };
+
+    TokenStream::from(expanded)
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
@@ -0,0 +1,165 @@
+use std::{env, fs, path::Path, time::Duration};
+
+use meexprox::{config::ProxyConfig, health::ServerHealth, migrate};
+
+const USAGE: &str = "usage: meexprox [init [--systemd] [--force] | check [config.yml] | migrate <bungeecord|velocity> <config> [--force]]";
+
+/// Runs subcommand of binary, returns exit code or None if proxy has to be started
+pub fn run(args: &[String]) -> Option<i32> {
+    let (command, args) = args.split_first()?;
+
+    Some(match command.as_str() {
+        "init" => init(args),
+        "check" => check(args),
+        "migrate" => migrate(args),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            0
+        }
+        _ => {
+            eprintln!("unknown command {}\n{}", command, USAGE);
+            2
+        }
+    })
+}
+
+/// Writes file unless it exists and force is not set
+fn write_file(path: &str, data: &[u8], force: bool) -> bool {
+    if Path::new(path).exists() && !force {
+        eprintln!("{} already exists, use --force to overwrite it", path);
+        return false;
+    }
+
+    match fs::write(path, data) {
+        Ok(_) => {
+            println!("{} written", path);
+            true
+        }
+        Err(e) => {
+            eprintln!("{} write error: {}", path, e);
+            false
+        }
+    }
+}
+
+/// systemd unit running binary in current directory
+fn systemd_unit() -> Option<String> {
+    let exe = env::current_exe().ok()?;
+    let dir = env::current_dir().ok()?;
+
+    Some(format!(
+        "[Unit]\n\
+        Description=meexprox minecraft proxy\n\
+        After=network-online.target\n\
+        Wants=network-online.target\n\
+        \n\
+        [Service]\n\
+        WorkingDirectory={}\n\
+        ExecStart={}\n\
+        Restart=on-failure\n\
+        \n\
+        [Install]\n\
+        WantedBy=multi-user.target\n",
+        dir.display(),
+        exe.display()
+    ))
+}
+
+fn init(args: &[String]) -> i32 {
+    let force = args.iter().any(|o| o == "--force");
+    let systemd = args.iter().any(|o| o == "--systemd");
+
+    let mut ok = write_file("config.yml", include_bytes!("../config.yml"), force);
+    ok &= write_file("permissions.yml", include_bytes!("../permissions.yml"), force);
+
+    if systemd {
+        ok &= match systemd_unit() {
+            Some(unit) => write_file("meexprox.service", unit.as_bytes(), force),
+            None => {
+                eprintln!("can't find path of meexprox binary");
+                false
+            }
+        };
+    }
+
+    if ok { 0 } else { 1 }
+}
+
+fn check(args: &[String]) -> i32 {
+    let path = args.first().map(|o| o.as_str()).unwrap_or("config.yml");
+
+    let Some(config) = ProxyConfig::load(path) else {
+        eprintln!("{}: config parse error", path);
+        return 1;
+    };
+
+    let mut problems = config.validate();
+
+    for server in &config.servers {
+        let health = ServerHealth::check(server, Duration::from_secs(3));
+        match health.latency {
+            Some(latency) => println!("server {} ({}) is reachable in {} ms", server.name, server.host, latency.as_millis()),
+            None => problems.push(format!("server {} ({}) is unreachable", server.name, server.host))
+        }
+    }
+
+    for problem in &problems {
+        eprintln!("{}: {}", path, problem);
+    }
+
+    if problems.is_empty() {
+        println!("{}: config is valid", path);
+        0
+    } else {
+        1
+    }
+}
+
+/// Converts other proxy config at path
+type Converter = fn(&Path) -> Result<migrate::Migration, String>;
+
+fn migrate(args: &[String]) -> i32 {
+    let force = args.iter().any(|o| o == "--force");
+    let args: Vec<&str> = args.iter().map(|o| o.as_str()).filter(|o| !o.starts_with("--")).collect();
+
+    let (source, convert): (&str, Converter) = match args.first() {
+        Some(&"bungeecord") => ("BungeeCord", |path| {
+            migrate::from_bungeecord(&fs::read_to_string(path).map_err(|e| e.to_string())?)
+        }),
+        Some(&"velocity") => ("Velocity", |path| {
+            let dir = path.parent().unwrap_or(Path::new("."));
+            migrate::from_velocity(&fs::read_to_string(path).map_err(|e| e.to_string())?, dir)
+        }),
+        _ => {
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    };
+
+    let Some(path) = args.get(1) else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+
+    let migration = match convert(Path::new(path)) {
+        Ok(migration) => migration,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return 1;
+        }
+    };
+
+    for warning in &migration.warnings {
+        eprintln!("warning: {}", warning);
+    }
+
+    let ok = match migration.to_yml(source) {
+        Ok(config) => write_file("config.yml", config.as_bytes(), force),
+        Err(e) => {
+            eprintln!("config write error: {}", e);
+            false
+        }
+    };
+
+    if ok { 0 } else { 1 }
+}
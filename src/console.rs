@@ -0,0 +1,66 @@
+use std::sync::Arc;
+
+use ignore_result::Ignore;
+use log::error;
+use meexprox::{command::CommandSender, MeexProx};
+use rustyline::{
+    completion::Completer, error::ReadlineError, highlight::Highlighter, hint::Hinter,
+    history::DefaultHistory, validate::Validator, Context, Editor, Helper,
+};
+
+const HISTORY_FILE: &str = "console_history.txt";
+
+struct ConsoleHelper {
+    meexprox: Arc<MeexProx>
+}
+
+impl Completer for ConsoleHelper {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.meexprox.commands().complete(&self.meexprox, &line[..pos]))
+    }
+}
+
+impl Hinter for ConsoleHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ConsoleHelper {}
+
+impl Validator for ConsoleHelper {}
+
+impl Helper for ConsoleHelper {}
+
+/// Executes commands typed in console until it is closed
+pub fn run(meexprox: Arc<MeexProx>) {
+    let mut editor = match Editor::<ConsoleHelper, DefaultHistory>::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            error!("console error: {}", e);
+            return;
+        }
+    };
+
+    editor.set_helper(Some(ConsoleHelper { meexprox: meexprox.clone() }));
+    editor.load_history(HISTORY_FILE).ignore();
+
+    loop {
+        let line = match editor.readline("> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(_) => break
+        };
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        editor.add_history_entry(line.as_str()).ignore();
+        editor.save_history(HISTORY_FILE).ignore();
+
+        for message in meexprox.execute_command(&CommandSender::Console, &line) {
+            println!("{}", message);
+        }
+    }
+}
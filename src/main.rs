@@ -1,25 +1,43 @@
-use std::{fs::{self, File}, path::Path};
+use std::{env, fs::{self, File}, path::Path, process, sync::Mutex, thread};
 
-use log::LevelFilter;
 use meexprox::{config::ProxyConfig, MeexProx};
-use simplelog::{ColorChoice, CombinedLogger, Config, TermLogger, TerminalMode, WriteLogger};
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, Layer};
 
 
+mod cli;
+mod console;
+
 pub fn main() {
-    CombinedLogger::init(vec![
-        TermLogger::new(
-            LevelFilter::Debug,
-            Config::default(),
-            TerminalMode::Mixed,
-            ColorChoice::Auto,
-        ),
-        WriteLogger::new(
-            LevelFilter::Info,
-            Config::default(),
-            File::create("latest.log").unwrap(),
-        ),
-    ])
-    .unwrap();
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Some(code) = cli::run(&args) {
+        process::exit(code);
+    }
+
+    #[cfg(feature = "otel")]
+    let telemetry = meexprox::otel::Telemetry::from_env();
+    #[cfg(feature = "otel")]
+    let otel_layer = telemetry.as_ref().map(|o| {
+        tracing_opentelemetry::layer()
+            .with_tracer(o.tracer())
+            .with_filter(LevelFilter::INFO)
+    });
+    #[cfg(not(feature = "otel"))]
+    let otel_layer: Option<tracing_subscriber::layer::Identity> = None;
+
+    tracing_subscriber::registry()
+        .with(otel_layer)
+        .with(
+            fmt::layer()
+                .with_filter(LevelFilter::DEBUG)
+        )
+        .with(
+            fmt::layer()
+                .with_ansi(false)
+                .with_writer(Mutex::new(File::create("latest.log").unwrap()))
+                .with_filter(LevelFilter::INFO)
+        )
+        .init();
 
     let config_path = Path::new("config.yml");
 
@@ -28,9 +46,31 @@ pub fn main() {
             .expect("config write error");
     }
 
+    let permissions_path = Path::new("permissions.yml");
+
+    if !permissions_path.exists() {
+        fs::write(permissions_path, include_bytes!("../permissions.yml"))
+            .expect("permissions write error");
+    }
+
     let config = ProxyConfig::load(config_path).expect("config parse error");
 
     let meexprox = MeexProx::new(config);
     // meexprox.add_event_listener(Box::new(MyEventListener {}));
-    meexprox.start();
+    // meexprox.add_event_handlers(MyEventHandlers {});
+    // meexprox.add_plugin(Box::new(MyPlugin {}));
+
+    let meexprox = meexprox.init();
+
+    #[cfg(feature = "otel")]
+    if let Some(telemetry) = telemetry {
+        telemetry.register_metrics(&meexprox);
+        meexprox.provide(telemetry);
+    }
+
+    let console = meexprox.clone();
+    thread::spawn(move || console::run(console));
+
+    meexprox.listen();
 }
+
@@ -0,0 +1,378 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use ignore_result::Ignore;
+use log::{error, info};
+use serde_json::{json, Map, Value};
+
+use super::{config::ApiServer, record::{EventFilter, TimedRecord}, websocket, MeexProx};
+
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub query: HashMap<String, String>,
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>
+}
+
+impl HttpRequest {
+    pub fn read(stream: &mut TcpStream) -> io::Result<HttpRequest> {
+        let mut reader = BufReader::new(stream);
+
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+
+        let mut parts = line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let target = parts.next().unwrap_or_default();
+
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path.to_string(), parse_query(query)),
+            None => (target.to_string(), HashMap::new())
+        };
+
+        let mut headers = HashMap::new();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 { break }
+            let line = line.trim_end();
+            if line.is_empty() { break }
+            if let Some((key, value)) = line.split_once(':') {
+                headers.insert(key.trim().to_lowercase(), value.trim().to_string());
+            }
+        }
+
+        let length = headers.get("content-length")
+            .and_then(|o| o.parse::<usize>().ok())
+            .unwrap_or(0)
+            .min(65536);
+
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+
+        Ok(HttpRequest { method, path, query, headers, body })
+    }
+
+    /// Token from `Authorization: Bearer` header or `token` query parameter
+    pub fn token(&self) -> Option<&str> {
+        self.headers.get("authorization")
+            .and_then(|o| o.strip_prefix("Bearer "))
+            .or(self.query.get("token").map(|o| o.as_str()))
+    }
+}
+
+pub struct HttpResponse {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>
+}
+
+impl HttpResponse {
+    pub fn text(status: u16, text: &str) -> HttpResponse {
+        HttpResponse {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: text.as_bytes().to_vec()
+        }
+    }
+
+    pub fn html(html: &str) -> HttpResponse {
+        HttpResponse {
+            status: 200,
+            content_type: "text/html; charset=utf-8",
+            body: html.as_bytes().to_vec()
+        }
+    }
+
+    pub fn json(status: u16, json: Value) -> HttpResponse {
+        HttpResponse {
+            status,
+            content_type: "application/json",
+            body: json.to_string().into_bytes()
+        }
+    }
+
+    pub fn write(&self, stream: &mut TcpStream) -> io::Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            404 => "Not Found",
+            500 => "Internal Server Error",
+            503 => "Service Unavailable",
+            _ => "Unknown"
+        };
+
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.status, reason, self.content_type, self.body.len()
+        )?;
+        stream.write_all(&self.body)?;
+        stream.flush()
+    }
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query.split('&')
+        .filter_map(|o| o.split_once('=').or(Some((o, ""))))
+        .filter(|o| !o.0.is_empty())
+        .map(|(k, v)| (percent_decode(k), percent_decode(v)))
+        .collect()
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok()
+                    .and_then(|o| u8::from_str_radix(o, 16).ok());
+                match hex {
+                    Some(byte) => {
+                        out.push(byte);
+                        i += 2;
+                    }
+                    None => out.push(b'%')
+                }
+            }
+            byte => out.push(byte)
+        }
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// Starts admin http server in new thread
+pub fn start(meexprox: Arc<MeexProx>, admin: ApiServer) {
+    let listener = match TcpListener::bind(&admin.host) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("admin server bind error: {}", e);
+            return;
+        }
+    };
+
+    info!("admin server started on {}", &admin.host);
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let meexprox = meexprox.clone();
+            let admin = admin.clone();
+            thread::spawn(move || {
+                handle_connection(&meexprox, &admin, stream).ignore();
+            });
+        }
+    });
+}
+
+fn handle_connection(meexprox: &Arc<MeexProx>, admin: &ApiServer, mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+    let request = HttpRequest::read(&mut stream)?;
+
+    let response = if request.path == "/healthz" || request.path == "/readyz" {
+        health(meexprox, request.path == "/readyz")
+    } else if admin.token.is_some() && request.token() != admin.token.as_deref() {
+        HttpResponse::text(401, "unauthorized")
+    } else if request.path == "/events" && websocket::is_upgrade(&request) {
+        return websocket::serve_events(meexprox, &request, stream);
+    } else {
+        route(meexprox, &request)
+    };
+
+    response.write(&mut stream)
+}
+
+#[allow(unused_variables)]
+fn route(meexprox: &Arc<MeexProx>, request: &HttpRequest) -> HttpResponse {
+    #[cfg(feature = "dashboard")]
+    if let Some(response) = super::dashboard::route(meexprox, request) {
+        return response;
+    }
+
+    if request.path == "/metrics" {
+        return HttpResponse::text(200, &metrics(meexprox));
+    }
+
+    if request.path == "/api/status" {
+        return HttpResponse::json(200, status(meexprox));
+    }
+
+    if request.path == "/api/events" {
+        return events(meexprox, request);
+    }
+
+    if request.path == "/api/player" {
+        return player(meexprox, request);
+    }
+
+    HttpResponse::text(404, "not found")
+}
+
+/// Liveness or readiness probe, answered without token.
+/// Proxy is alive while it listens, and ready if also at least one server is reachable or none was checked yet
+fn health(meexprox: &Arc<MeexProx>, ready: bool) -> HttpResponse {
+    let listeners = meexprox.listening();
+    let players = meexprox.online_count();
+
+    let servers = meexprox.config().servers.clone();
+    let health: Vec<Option<bool>> = servers.iter()
+        .map(|o| meexprox.server_health(&o.name).map(|o| o.reachable))
+        .collect();
+    let reachable = health.iter().filter(|o| **o == Some(true)).count();
+    let unreachable = health.iter().filter(|o| **o == Some(false)).count();
+
+    let mut ok = !listeners.is_empty();
+    if ready {
+        ok &= reachable > 0 || unreachable == 0;
+    }
+
+    HttpResponse::json(if ok { 200 } else { 503 }, json!({
+        "status": if ok { "ok" } else { "unavailable" },
+        "listeners": listeners,
+        "players": players,
+        "servers": {
+            "total": servers.len(),
+            "reachable": reachable,
+            "unreachable": unreachable,
+            "unknown": servers.len() - reachable - unreachable
+        }
+    }))
+}
+
+/// Servers with their health and players, for server status widgets
+fn status(meexprox: &Arc<MeexProx>) -> Value {
+    let players = meexprox.get_players();
+    let players: Vec<_> = players.iter().filter(|o| o.is_connected()).collect();
+
+    let servers: Vec<Value> = meexprox.config().servers.iter()
+        .map(|server| {
+            let health = meexprox.server_health(&server.name);
+            let names: Vec<&str> = players.iter()
+                .filter(|o| o.server().is_some_and(|o| o.name == server.name))
+                .map(|o| o.name.as_str())
+                .collect();
+
+            json!({
+                "name": server.name,
+                "online": health.as_ref().map(|o| o.reachable),
+                "latency": health.and_then(|o| o.latency).map(|o| o.as_millis() as u64),
+                "player_count": names.len(),
+                "players": names
+            })
+        })
+        .collect();
+
+    json!({
+        "uptime": meexprox.stats().uptime().as_secs(),
+        "player_count": players.len(),
+        "unique_players_today": meexprox.unique_players_today(),
+        "servers": servers
+    })
+}
+
+/// Last events from history, `last` is count (100 by default) and `events` is comma separated types
+fn events(meexprox: &Arc<MeexProx>, request: &HttpRequest) -> HttpResponse {
+    let Ok(last) = request.query.get("last").map(|o| o.parse::<usize>()).unwrap_or(Ok(100)) else {
+        return HttpResponse::text(400, "invalid last");
+    };
+    let types = request.query.get("events").map(|o| EventFilter::new(o.split(',')));
+
+    let events: Vec<TimedRecord> = meexprox.recent_events(usize::MAX)
+        .into_iter()
+        .filter(|o| types.as_ref().is_none_or(|t| t.accepts(&o.record)))
+        .collect();
+    let events = &events[events.len().saturating_sub(last)..];
+
+    HttpResponse::json(200, json!(events))
+}
+
+/// Stored stats of player by `name`, times are unix seconds and durations are seconds
+fn player(meexprox: &Arc<MeexProx>, request: &HttpRequest) -> HttpResponse {
+    let Some(name) = request.query.get("name") else {
+        return HttpResponse::text(400, "name is required");
+    };
+    let Some(storage) = meexprox.storage() else {
+        return HttpResponse::text(503, "storage is not enabled");
+    };
+    let record = match storage.get_player(name) {
+        Ok(Some(record)) => record,
+        Ok(None) => return HttpResponse::text(404, "player has never joined"),
+        Err(e) => return HttpResponse::text(500, &format!("storage error: {}", e))
+    };
+
+    let online = meexprox.get_players().iter()
+        .find(|o| o.uuid == record.uuid && o.is_connected())
+        .and_then(|o| o.server().map(|o| o.name));
+    let servers: Map<String, Value> = record.server_time.iter()
+        .map(|(server, time)| (server.clone(), Value::from(*time)))
+        .collect();
+
+    HttpResponse::json(200, json!({
+        "uuid": record.uuid,
+        "name": record.name,
+        "online": online.is_some(),
+        "server": online,
+        "first_seen": record.first_seen,
+        "last_seen": record.last_seen,
+        "play_time": record.play_time,
+        "server_time": servers
+    }))
+}
+
+/// Counters in prometheus text format
+fn metrics(meexprox: &Arc<MeexProx>) -> String {
+    let stats = meexprox.stats();
+    let players = meexprox.online_count();
+
+    let mut out = String::new();
+
+    out.push_str("# TYPE meexprox_players gauge\n");
+    out.push_str(&format!("meexprox_players {}\n", players));
+    out.push_str("# TYPE meexprox_connections_total counter\n");
+    out.push_str(&format!("meexprox_connections_total {}\n", stats.connections()));
+    out.push_str("# TYPE meexprox_status_requests_total counter\n");
+    out.push_str(&format!("meexprox_status_requests_total {}\n", stats.status_requests()));
+
+    out.push_str("# TYPE meexprox_packets_total counter\n");
+    out.push_str(&format!("meexprox_packets_total{{direction=\"serverbound\"}} {}\n", stats.packets_serverbound()));
+    out.push_str(&format!("meexprox_packets_total{{direction=\"clientbound\"}} {}\n", stats.packets_clientbound()));
+    out.push_str("# TYPE meexprox_bytes_total counter\n");
+    out.push_str(&format!("meexprox_bytes_total{{direction=\"serverbound\"}} {}\n", stats.bytes_serverbound()));
+    out.push_str(&format!("meexprox_bytes_total{{direction=\"clientbound\"}} {}\n", stats.bytes_clientbound()));
+
+    out.push_str("# TYPE meexprox_connect_failures_total counter\n");
+    for (server, failures) in stats.connect_failures() {
+        out.push_str(&format!("meexprox_connect_failures_total{{server=\"{}\"}} {}\n", server, failures));
+    }
+
+    let counts = stats.packet_counts();
+    out.push_str("# TYPE meexprox_packet_count_total counter\n");
+    for o in &counts {
+        out.push_str(&format!(
+            "meexprox_packet_count_total{{direction=\"{}\",state=\"{}\",id=\"0x{:02X}\"}} {}\n",
+            o.direction.name(), o.state.name(), o.id, o.count
+        ));
+    }
+    out.push_str("# TYPE meexprox_packet_bytes_total counter\n");
+    for o in &counts {
+        out.push_str(&format!(
+            "meexprox_packet_bytes_total{{direction=\"{}\",state=\"{}\",id=\"0x{:02X}\"}} {}\n",
+            o.direction.name(), o.state.name(), o.id, o.bytes
+        ));
+    }
+
+    out
+}
@@ -0,0 +1,73 @@
+use std::{collections::HashMap, sync::Arc, thread};
+
+use serde_yml::{Mapping, Value};
+
+use super::{placeholders::PlaceholderContext, MeexProx};
+
+/// Chat message sent to players on every backend when record happens
+#[derive(Clone, Debug)]
+pub struct BroadcastMessage {
+    pub template: String,
+    /// Only players with this permission see the message
+    pub permission: Option<String>
+}
+
+#[derive(Clone, Debug)]
+pub struct Broadcasts {
+    /// Message by record type, records without message are not broadcasted
+    pub messages: HashMap<String, BroadcastMessage>
+}
+
+impl Broadcasts {
+    pub fn from_data(data: &Mapping) -> Option<Broadcasts> {
+        if !data.get("enabled")?.as_bool()? {
+            return None;
+        }
+
+        let permission = data.get("permission")
+            .and_then(|o| o.as_str())
+            .map(|o| o.to_string());
+
+        Some(Broadcasts {
+            messages: data.get("messages")?.as_mapping()?
+                .iter()
+                .filter_map(|(k, v)| {
+                    let message = match v {
+                        Value::String(template) => BroadcastMessage {
+                            template: template.clone(),
+                            permission: permission.clone()
+                        },
+                        Value::Mapping(map) => BroadcastMessage {
+                            template: map.get("message")?.as_str()?.to_string(),
+                            permission: map.get("permission")
+                                .and_then(|o| o.as_str())
+                                .map(|o| o.to_string())
+                                .or(permission.clone())
+                        },
+                        _ => return None
+                    };
+                    Some((k.as_str()?.to_string(), message))
+                })
+                .collect()
+        })
+    }
+}
+
+/// Starts thread broadcasting records that have message in config
+pub fn start(meexprox: Arc<MeexProx>) {
+    let records = meexprox.subscribe();
+
+    thread::spawn(move || {
+        for record in records {
+            let Some(broadcasts) = meexprox.config().broadcasts.clone() else {
+                continue;
+            };
+
+            if let Some(message) = broadcasts.messages.get(record.name()) {
+                // proxy placeholders first, record fields like `{player}` are left for record
+                let template = meexprox.render(&message.template, &PlaceholderContext::new());
+                meexprox.broadcast(record.format(&template), message.permission.as_deref());
+            }
+        }
+    });
+}
@@ -0,0 +1,133 @@
+use std::{any::Any, sync::Arc};
+
+use super::{
+    command::Command,
+    config::{ListenerInfo, Messaging, PlayerForwarding, ProxyConfig, RouteRule, ServerInfo},
+    event::{Event, EventHandlers, EventListener},
+    plugin::Plugin,
+    MeexProx,
+};
+
+type Setup = Box<dyn FnOnce(&mut MeexProx)>;
+
+/// Builds proxy in code, without config file.
+/// Options that have no method here can be set with [`MeexProxBuilder::configure`]
+pub struct MeexProxBuilder {
+    config: ProxyConfig,
+    setup: Vec<Setup>
+}
+
+impl Default for MeexProxBuilder {
+    fn default() -> MeexProxBuilder {
+        MeexProxBuilder::new()
+    }
+}
+
+impl MeexProxBuilder {
+    /// Builder with default config, proxy is bound to `127.0.0.1:25565` and has no servers
+    pub fn new() -> MeexProxBuilder {
+        MeexProxBuilder::from_config(ProxyConfig::new(
+            "127.0.0.1:25565".to_string(),
+            Vec::new(),
+            None,
+            PlayerForwarding::None,
+            PlayerForwarding::None
+        ))
+    }
+
+    /// Builder starting from loaded or already built config
+    pub fn from_config(config: ProxyConfig) -> MeexProxBuilder {
+        MeexProxBuilder { config, setup: Vec::new() }
+    }
+
+    pub fn host(mut self, host: impl Into<String>) -> MeexProxBuilder {
+        self.config.host = host.into();
+        self
+    }
+
+    /// Adds server, server with the same name is replaced
+    pub fn server(mut self, server: ServerInfo) -> MeexProxBuilder {
+        self.config.servers.retain(|o| o.name != server.name);
+        self.config.servers.push(server);
+        self
+    }
+
+    /// Adds port listened in addition to host
+    pub fn listener(mut self, listener: ListenerInfo) -> MeexProxBuilder {
+        self.config.listeners.push(listener);
+        self
+    }
+
+    pub fn route(mut self, route: RouteRule) -> MeexProxBuilder {
+        self.config.routes.push(route);
+        self
+    }
+
+    pub fn messaging(mut self, messaging: Messaging) -> MeexProxBuilder {
+        self.config.messaging = Some(messaging);
+        self
+    }
+
+    pub fn default_forwarding(mut self, forwarding: PlayerForwarding) -> MeexProxBuilder {
+        self.config.default_forwarding = forwarding;
+        self
+    }
+
+    pub fn incoming_forwarding(mut self, forwarding: PlayerForwarding) -> MeexProxBuilder {
+        self.config.incoming_forwarding = forwarding;
+        self
+    }
+
+    /// Changes any other option of config
+    pub fn configure(mut self, configure: impl FnOnce(&mut ProxyConfig)) -> MeexProxBuilder {
+        configure(&mut self.config);
+        self
+    }
+
+    pub fn event_listener<T: Event + 'static>(
+        mut self,
+        event_listener: Box<dyn EventListener<T> + Send + Sync>
+    ) -> MeexProxBuilder {
+        self.setup.push(Box::new(move |o| o.add_event_listener(event_listener)));
+        self
+    }
+
+    pub fn event_handlers(mut self, handlers: impl EventHandlers + 'static) -> MeexProxBuilder {
+        self.setup.push(Box::new(move |o| o.add_event_handlers(handlers)));
+        self
+    }
+
+    pub fn plugin(mut self, plugin: Box<dyn Plugin>) -> MeexProxBuilder {
+        self.setup.push(Box::new(move |o| o.add_plugin(plugin)));
+        self
+    }
+
+    pub fn command(mut self, command: impl Command + 'static) -> MeexProxBuilder {
+        self.setup.push(Box::new(move |o| o.commands().register(command)));
+        self
+    }
+
+    /// Provides service that plugins and listeners get with `get_service`
+    pub fn service<T: Any + Send + Sync>(mut self, service: T) -> MeexProxBuilder {
+        self.setup.push(Box::new(move |o| o.provide(service)));
+        self
+    }
+
+    pub fn config(&self) -> &ProxyConfig {
+        &self.config
+    }
+
+    /// Proxy that is not started yet, see [`MeexProx::init`] and [`MeexProx::start`]
+    pub fn build(self) -> MeexProx {
+        let mut meexprox = MeexProx::new(self.config);
+        for setup in self.setup {
+            setup(&mut meexprox);
+        }
+        meexprox
+    }
+
+    /// Builds and initializes proxy, listening is left to caller
+    pub fn init(self) -> Arc<MeexProx> {
+        self.build().init()
+    }
+}
@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use ignore_result::Ignore;
+use rust_mc_proto::{DataBufferReader, DataBufferWriter, Packet, ProtocolError};
+use serde_yml::Mapping;
+
+use super::{split::SplitConn, MeexProx};
+
+/// Serverbound in play state, same id on every supported version
+const CHAT_ACKNOWLEDGEMENT: u8 = 0x03;
+
+/// Chat of players is sent by proxy to players on every server
+#[derive(Clone, Debug)]
+pub struct GlobalChat {
+    /// Template with `{player}`, `{server}` and `{message}` placeholders
+    pub format: String
+}
+
+impl GlobalChat {
+    pub fn from_data(data: &Mapping) -> Option<GlobalChat> {
+        if !data.get("enabled")?.as_bool()? {
+            return None;
+        }
+
+        Some(GlobalChat {
+            format: data.get("format")
+                .and_then(|o| o.as_str())
+                .unwrap_or("§7[{server}] §f{player}§7: §f{message}")
+                .to_string()
+        })
+    }
+
+    pub fn format(&self, player: &str, server: &str, message: &str) -> String {
+        self.format.replace("{player}", player)
+            .replace("{server}", server)
+            .replace("{message}", message)
+    }
+}
+
+/// Needed to send and receive staff chat
+pub const STAFF_CHAT_PERMISSION: &str = "meexprox.staffchat";
+
+/// Channel of `/sc` command, seen only by players with staff chat permission
+#[derive(Clone, Debug)]
+pub struct StaffChat {
+    /// Template with `{player}` and `{message}` placeholders
+    pub format: String
+}
+
+impl Default for StaffChat {
+    fn default() -> StaffChat {
+        StaffChat {
+            format: "§b[Staff] {player}§7: §f{message}".to_string()
+        }
+    }
+}
+
+impl StaffChat {
+    pub fn from_data(data: &Mapping) -> StaffChat {
+        StaffChat {
+            format: data.get("format")
+                .and_then(|o| o.as_str())
+                .map(|o| o.to_string())
+                .unwrap_or(StaffChat::default().format)
+        }
+    }
+
+    pub fn format(&self, player: &str, message: &str) -> String {
+        self.format.replace("{player}", player)
+            .replace("{message}", message)
+    }
+}
+
+/// Serverbound chat message, signature is skipped as proxy resends message unsigned
+pub struct ChatMessage {
+    pub message: String,
+    /// Count of messages acknowledged by client since previous chat packet
+    pub acknowledged: usize
+}
+
+impl ChatMessage {
+    pub fn read(packet: &mut Packet) -> Result<ChatMessage, ProtocolError> {
+        let message = packet.read_string()?;
+        packet.read_long()?; // timestamp
+        packet.read_long()?; // salt
+        if packet.read_boolean()? {
+            packet.read_bytes(256)?;
+        }
+        let acknowledged = packet.read_usize_varint()?;
+
+        Ok(ChatMessage { message, acknowledged })
+    }
+}
+
+/// Sends chat message of player to everyone with global chat enabled, returns false if it has to be sent to backend
+pub fn dispatch_global_chat(meexprox: &Arc<MeexProx>, name: &str, mut packet: Packet, server: &SplitConn) -> bool {
+    if meexprox.config().global_chat.is_none() {
+        return false;
+    }
+
+    let Ok(chat) = ChatMessage::read(&mut packet) else {
+        return false;
+    };
+
+    // signed chat carries acknowledgements of seen messages, backend kicks player if they go missing
+    if chat.acknowledged > 0 {
+        let ack = Packet::build(CHAT_ACKNOWLEDGEMENT, |p| p.write_usize_varint(chat.acknowledged));
+        if let Ok(ack) = ack {
+            server.write_packet(&ack).ignore();
+        }
+    }
+
+    meexprox.global_chat(name, &chat.message);
+
+    true
+}
@@ -0,0 +1,235 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::Arc,
+};
+
+use parking_lot::RwLock;
+
+use super::MeexProx;
+
+/// Who executes the command
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CommandSender {
+    Console,
+    /// Connected player with given name
+    Player(String)
+}
+
+impl CommandSender {
+    pub fn name(&self) -> &str {
+        match self {
+            CommandSender::Console => "CONSOLE",
+            CommandSender::Player(name) => name
+        }
+    }
+
+    /// Console can use every command, players the ones they have permission for
+    pub fn can_use(&self, meexprox: &MeexProx, command: &dyn Command) -> bool {
+        match self {
+            CommandSender::Console => true,
+            CommandSender::Player(name) => meexprox.has_permission(name, command.permission())
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CommandError {
+    UnknownCommand,
+    NoPermission,
+    Usage,
+    Failed(String)
+}
+
+pub struct CommandContext<'a> {
+    pub meexprox: &'a Arc<MeexProx>,
+    pub sender: &'a CommandSender,
+    pub args: Vec<String>,
+    output: Vec<String>
+}
+
+impl CommandContext<'_> {
+    /// Argument at index, usage error if missing
+    pub fn arg(&self, index: usize) -> Result<&str, CommandError> {
+        self.args.get(index)
+            .map(|o| o.as_str())
+            .ok_or(CommandError::Usage)
+    }
+
+    /// Arguments from index joined with spaces, None if there are none
+    pub fn rest(&self, index: usize) -> Option<String> {
+        self.args.get(index..)
+            .filter(|o| !o.is_empty())
+            .map(|o| o.join(" "))
+    }
+
+    /// Adds message line sent back to the sender
+    pub fn reply(&mut self, message: impl Into<String>) {
+        self.output.push(message.into());
+    }
+}
+
+pub trait Command: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn aliases(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Arguments description, like `<player> [reason]`
+    fn usage(&self) -> &str {
+        ""
+    }
+
+    fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError>;
+
+    /// Permission required to use the command, None if everyone can use it
+    fn permission(&self) -> Option<&str> {
+        None
+    }
+
+    /// Type of every argument, like `player` or `server`, used to pick completion provider
+    fn arguments(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
+    /// Suggestions for argument at index, added to the ones of argument type provider
+    fn complete(&self, _meexprox: &Arc<MeexProx>, _index: usize) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+type CompletionProvider = Arc<dyn Fn(&MeexProx) -> Vec<String> + Send + Sync>;
+
+/// Commands shared by console and other senders
+#[derive(Default)]
+pub struct CommandManager {
+    commands: RwLock<Vec<Arc<dyn Command>>>,
+    completions: RwLock<HashMap<String, CompletionProvider>>
+}
+
+impl CommandManager {
+    pub fn new() -> CommandManager {
+        let manager = CommandManager::default();
+
+        manager.register_completion("player", |meexprox| {
+            meexprox.get_players().iter()
+                .filter(|o| o.is_connected())
+                .map(|o| o.name.clone())
+                .collect()
+        });
+
+        manager.register_completion("server", |meexprox| {
+            meexprox.config().servers.iter()
+                .map(|o| o.name.clone())
+                .collect()
+        });
+
+        manager
+    }
+
+    /// Registers suggestions for every command argument of given type
+    pub fn register_completion(
+        &self,
+        argument: &str,
+        provider: impl Fn(&MeexProx) -> Vec<String> + Send + Sync + 'static
+    ) {
+        self.completions.write().insert(argument.to_string(), Arc::new(provider));
+    }
+
+    /// Registers command, replacing the command with the same name
+    pub fn register(&self, command: impl Command + 'static) {
+        let mut commands = self.commands.write();
+        commands.retain(|o| o.name() != command.name());
+        commands.push(Arc::new(command));
+    }
+
+    pub fn unregister(&self, name: &str) {
+        self.commands.write().retain(|o| o.name() != name);
+    }
+
+    /// Finds command by name or alias
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Command>> {
+        let name = name.to_lowercase();
+        self.commands.read().iter()
+            .find(|o| o.name() == name || o.aliases().contains(&name))
+            .cloned()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.commands.read().iter()
+            .map(|o| o.name().to_string())
+            .collect()
+    }
+
+    /// Suggestions for the last word of partial command line, returned with its start offset
+    pub fn complete(&self, meexprox: &Arc<MeexProx>, line: &str) -> (usize, Vec<String>) {
+        let start = line.rfind(' ').map(|o| o + 1).unwrap_or(0);
+        let word = line[start..].to_lowercase();
+        let args: Vec<&str> = line[..start].split_whitespace().collect();
+
+        let mut suggestions: Vec<String> = match args.split_first() {
+            None => self.names(),
+            Some((name, args)) => match self.get(name) {
+                Some(command) => {
+                    let mut suggestions = command.complete(meexprox, args.len());
+                    let provider = command.arguments().get(args.len())
+                        .and_then(|o| self.completions.read().get(*o).cloned());
+                    if let Some(provider) = provider {
+                        suggestions.extend(provider(meexprox));
+                    }
+                    suggestions
+                }
+                None => Vec::new()
+            }
+        };
+
+        suggestions.retain(|o| o.to_lowercase().starts_with(&word));
+        suggestions.sort();
+        suggestions.dedup();
+
+        (start, suggestions)
+    }
+
+    /// Executes command line (without leading slash) and returns lines to send back
+    pub fn execute(&self, meexprox: &Arc<MeexProx>, sender: &CommandSender, line: &str) -> Vec<String> {
+        let mut args = line.split_whitespace().map(|o| o.to_string());
+
+        let Some(name) = args.next() else {
+            return Vec::new();
+        };
+
+        let mut ctx = CommandContext {
+            meexprox,
+            sender,
+            args: args.collect(),
+            output: Vec::new()
+        };
+
+        let result = match self.get(&name) {
+            Some(command) if !sender.can_use(meexprox, command.as_ref()) => Err(CommandError::NoPermission),
+            Some(command) => command.execute(&mut ctx).map_err(|e| match e {
+                CommandError::Usage => CommandError::Failed(format!("usage: {} {}", command.name(), command.usage())),
+                e => e
+            }),
+            None => Err(CommandError::UnknownCommand)
+        };
+
+        if let Err(e) = result {
+            ctx.reply(e.to_string());
+        }
+
+        ctx.output
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::UnknownCommand => write!(f, "unknown command"),
+            CommandError::NoPermission => write!(f, "you don't have permission to use this command"),
+            CommandError::Usage => write!(f, "invalid usage"),
+            CommandError::Failed(message) => write!(f, "{}", message)
+        }
+    }
+}
@@ -0,0 +1,1060 @@
+use std::{cmp::Reverse, net::IpAddr, process, sync::Arc, time::Duration};
+
+use super::{chat, command::{Command, CommandContext, CommandError, CommandSender}, dump, protocol, record::{EventFilter, ProxyEventRecord, TimedRecord}, storage, MeexProx};
+
+pub struct ListCommand;
+
+impl Command for ListCommand {
+    fn name(&self) -> &str {
+        "list"
+    }
+
+    fn permission(&self) -> Option<&str> {
+        Some("meexprox.command.list")
+    }
+
+    fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+        let distribution = ctx.meexprox.player_distribution();
+
+        for (server, players) in &distribution {
+            ctx.reply(format!("[{}] ({}): {}", server, players.len(), players.join(", ")));
+        }
+
+        let total: usize = distribution.iter().map(|o| o.1.len()).sum();
+        ctx.reply(format!("total players online: {}", total));
+
+        Ok(())
+    }
+}
+
+const GLIST_PAGE_SIZE: usize = 10;
+
+pub struct GlistCommand;
+
+impl Command for GlistCommand {
+    fn name(&self) -> &str {
+        "glist"
+    }
+
+    fn usage(&self) -> &str {
+        "[all] [page]"
+    }
+
+    fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+        let mut args = ctx.args.iter().map(|o| o.as_str()).peekable();
+
+        let names = args.next_if_eq(&"all").is_some();
+        let page = match args.next() {
+            Some(page) => page.parse::<usize>().ok().filter(|o| *o > 0).ok_or(CommandError::Usage)?,
+            None => 1
+        };
+
+        let distribution = ctx.meexprox.player_distribution();
+        let total: usize = distribution.iter().map(|o| o.1.len()).sum();
+        let pages = distribution.len().div_ceil(GLIST_PAGE_SIZE).max(1);
+
+        if page > pages {
+            return Err(CommandError::Failed(format!("there are only {} pages", pages)));
+        }
+
+        for (server, players) in distribution.iter().skip((page - 1) * GLIST_PAGE_SIZE).take(GLIST_PAGE_SIZE) {
+            if names && !players.is_empty() {
+                ctx.reply(format!("[{}] ({}): {}", server, players.len(), players.join(", ")));
+            } else {
+                ctx.reply(format!("[{}] ({})", server, players.len()));
+            }
+        }
+
+        ctx.reply(format!("total players online: {}", total));
+
+        if pages > 1 {
+            ctx.reply(format!("page {}/{}", page, pages));
+        }
+
+        Ok(())
+    }
+
+    fn complete(&self, _meexprox: &Arc<MeexProx>, index: usize) -> Vec<String> {
+        match index {
+            0 => vec!["all".to_string()],
+            _ => Vec::new()
+        }
+    }
+}
+
+pub struct SendCommand;
+
+impl Command for SendCommand {
+    fn name(&self) -> &str {
+        "send"
+    }
+
+    fn permission(&self) -> Option<&str> {
+        Some("meexprox.command.send")
+    }
+
+    fn usage(&self) -> &str {
+        "<player> <server>"
+    }
+
+    fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+        let name = ctx.arg(0)?;
+        let config = ctx.meexprox.config();
+        let server = config.get_server_by_name(ctx.arg(1)?)
+            .ok_or(CommandError::Failed("server not found".to_string()))?;
+
+        let player = ctx.meexprox.get_players().iter()
+            .find(|o| o.name == name && o.is_connected())
+            .cloned()
+            .ok_or(CommandError::Failed("player not found".to_string()))?;
+
+        player.connect_server(&config, server.clone()).map_err(|e| CommandError::Failed(format!("connect error: {}", e)))?;
+        ctx.reply(format!("{} sent to {}", name, server.name));
+
+        Ok(())
+    }
+
+    fn arguments(&self) -> Vec<&str> {
+        vec!["player", "server"]
+    }
+}
+
+pub struct FindCommand;
+
+impl Command for FindCommand {
+    fn name(&self) -> &str {
+        "find"
+    }
+
+    fn permission(&self) -> Option<&str> {
+        Some("meexprox.command.find")
+    }
+
+    fn usage(&self) -> &str {
+        "<player>"
+    }
+
+    fn arguments(&self) -> Vec<&str> {
+        vec!["player"]
+    }
+
+    fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+        let name = ctx.arg(0)?;
+
+        let found = ctx.meexprox.get_players().iter()
+            .find(|o| o.name.eq_ignore_ascii_case(name) && o.is_connected())
+            .map(|o| (o.name.clone(), o.server().map(|o| o.name)));
+
+        match found {
+            Some((name, Some(server))) => ctx.reply(format!("{} is online at {}", name, server)),
+            Some((name, None)) => ctx.reply(format!("{} is online, but not connected to any server", name)),
+            None => return Err(CommandError::Failed("player not found".to_string()))
+        }
+
+        Ok(())
+    }
+}
+
+/// Ip with host part hidden, like `192.168.x.x`
+fn mask_ip(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, _, _] = ip.octets();
+            format!("{}.{}.x.x", a, b)
+        }
+        IpAddr::V6(ip) => {
+            let segments = ip.segments();
+            format!("{:x}:{:x}:x:x:x:x:x:x", segments[0], segments[1])
+        }
+    }
+}
+
+pub struct PlayerInfoCommand;
+
+impl Command for PlayerInfoCommand {
+    fn name(&self) -> &str {
+        "playerinfo"
+    }
+
+    fn permission(&self) -> Option<&str> {
+        Some("meexprox.command.playerinfo")
+    }
+
+    fn usage(&self) -> &str {
+        "<player>"
+    }
+
+    fn arguments(&self) -> Vec<&str> {
+        vec!["player"]
+    }
+
+    fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+        let name = ctx.arg(0)?;
+        // full ip is shown to console and players with permission only
+        let full_ip = match ctx.sender {
+            CommandSender::Console => true,
+            CommandSender::Player(sender) => ctx.meexprox.has_permission(sender, Some("meexprox.playerinfo.ip"))
+        };
+
+        let player = ctx.meexprox.get_players().iter()
+            .find(|o| o.name.eq_ignore_ascii_case(name) && o.is_connected())
+            .cloned()
+            .ok_or(CommandError::Failed("player not found".to_string()))?;
+
+        let ip = if full_ip { player.addr.ip().to_string() } else { mask_ip(player.addr.ip()) };
+        let server = player.server().map(|o| o.name);
+        let mut lines = vec![
+            format!("{} ({})", player.name, player.uuid),
+            format!("ip: {}", ip),
+            format!("version: {} ({})", protocol::raw_version_name(player.handshake().raw_protocol), player.handshake().raw_protocol),
+            format!("server: {}", server.clone().unwrap_or("none".to_string())),
+            format!("connected for: {}", storage::format_duration(player.connected_for().as_secs() as i64)),
+            format!("ping: {}", player.latency().map(|o| format!("{}ms", o.as_millis())).unwrap_or("unknown".to_string())),
+            format!("client brand: {}", player.client_brand().unwrap_or("unknown".to_string()))
+        ];
+
+        // stats of current session are stored when it ends, so they are added here
+        match ctx.meexprox.storage().map(|o| o.get_player_by_uuid(player.uuid)) {
+            Some(Ok(Some(mut record))) => {
+                let now = storage::now();
+                let session = player.connected_for().as_secs() as i64;
+                if let Some(server) = server {
+                    let time = player.server_for().as_secs() as i64;
+                    match record.server_time.iter_mut().find(|o| o.0 == server) {
+                        Some(entry) => entry.1 += time,
+                        None => record.server_time.push((server, time))
+                    }
+                    record.server_time.sort_by_key(|o| Reverse(o.1));
+                }
+                lines.push(format!("first joined {} ago", storage::format_duration(now - record.first_seen)));
+                lines.push(format!("play time: {}", storage::format_duration(record.play_time + session)));
+                if !record.server_time.is_empty() {
+                    lines.push(format!("time on servers: {}", record.format_server_time()));
+                }
+            }
+            Some(Err(e)) => lines.push(format!("storage error: {}", e)),
+            _ => {}
+        }
+
+        for line in lines {
+            ctx.reply(line);
+        }
+
+        Ok(())
+    }
+}
+
+pub struct ServerCommand;
+
+impl Command for ServerCommand {
+    fn name(&self) -> &str {
+        "server"
+    }
+
+    fn usage(&self) -> &str {
+        "[server]"
+    }
+
+    fn arguments(&self) -> Vec<&str> {
+        vec!["server"]
+    }
+
+    fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+        let config = ctx.meexprox.config();
+
+        let Some(name) = ctx.args.first() else {
+            for server in &config.servers {
+                if server.aliases.is_empty() {
+                    ctx.reply(server.name.clone());
+                } else {
+                    ctx.reply(format!("{} ({})", server.name, server.aliases.join(", ")));
+                }
+            }
+            return Ok(());
+        };
+
+        let CommandSender::Player(player) = ctx.sender else {
+            return Err(CommandError::Failed("only players can switch servers".to_string()));
+        };
+
+        let server = config.get_server_by_name(name)
+            .ok_or(CommandError::Failed("server not found".to_string()))?;
+
+        let target = ctx.meexprox.get_players().iter()
+            .find(|o| &o.name == player && o.is_connected())
+            .cloned()
+            .ok_or(CommandError::Failed("player not found".to_string()))?;
+
+        target.connect_server(&config, server.clone()).map_err(|e| CommandError::Failed(format!("connect error: {}", e)))?;
+        ctx.reply(format!("connected to {}", server.name));
+
+        Ok(())
+    }
+}
+
+pub struct KickCommand;
+
+impl Command for KickCommand {
+    fn name(&self) -> &str {
+        "kick"
+    }
+
+    fn permission(&self) -> Option<&str> {
+        Some("meexprox.command.kick")
+    }
+
+    fn usage(&self) -> &str {
+        "<player> [reason]"
+    }
+
+    fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+        let name = ctx.arg(0)?.to_string();
+        let reason = ctx.rest(1).unwrap_or("Kicked".to_string());
+
+        let result = ctx.meexprox.get_players().iter()
+            .find(|o| o.name == name && o.is_connected())
+            .map(|o| o.kick(reason))
+            .ok_or(CommandError::Failed("player not found".to_string()))?;
+
+        result.map_err(|e| CommandError::Failed(format!("kick error: {}", e)))?;
+        ctx.reply(format!("{} kicked", name));
+
+        Ok(())
+    }
+
+    fn arguments(&self) -> Vec<&str> {
+        vec!["player", "text"]
+    }
+}
+
+pub struct DebugCommand;
+
+impl Command for DebugCommand {
+    fn name(&self) -> &str {
+        "debug"
+    }
+
+    fn permission(&self) -> Option<&str> {
+        Some("meexprox.command.debug")
+    }
+
+    fn usage(&self) -> &str {
+        "<player> [on | off]"
+    }
+
+    fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+        let name = ctx.arg(0)?.to_string();
+        let players = ctx.meexprox.get_players();
+        let player = players.iter()
+            .find(|o| o.name == name && o.is_connected())
+            .ok_or(CommandError::Failed("player not found".to_string()))?;
+
+        let debug = match ctx.args.get(1).map(|o| o.as_str()) {
+            None => !player.is_debug(),
+            Some("on") => true,
+            Some("off") => false,
+            _ => return Err(CommandError::Usage)
+        };
+        player.set_debug(debug);
+        drop(players);
+
+        ctx.reply(format!("packet logging of {} {}", name, if debug { "enabled" } else { "disabled" }));
+
+        Ok(())
+    }
+
+    fn arguments(&self) -> Vec<&str> {
+        vec!["player"]
+    }
+
+    fn complete(&self, _meexprox: &Arc<MeexProx>, index: usize) -> Vec<String> {
+        match index {
+            1 => vec!["on".to_string(), "off".to_string()],
+            _ => Vec::new()
+        }
+    }
+}
+
+pub struct GlobalChatCommand;
+
+impl Command for GlobalChatCommand {
+    fn name(&self) -> &str {
+        "globalchat"
+    }
+
+    fn aliases(&self) -> Vec<String> {
+        vec!["gchat".to_string()]
+    }
+
+    fn usage(&self) -> &str {
+        "[on | off]"
+    }
+
+    fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+        if ctx.meexprox.config().global_chat.is_none() {
+            return Err(CommandError::Failed("global chat is disabled".to_string()));
+        }
+
+        let CommandSender::Player(name) = ctx.sender else {
+            return Err(CommandError::Failed("only players can toggle global chat".to_string()));
+        };
+
+        let players = ctx.meexprox.get_players();
+        let player = players.iter()
+            .find(|o| &o.name == name && o.is_connected())
+            .ok_or(CommandError::Failed("player not found".to_string()))?;
+
+        let enabled = match ctx.args.first().map(|o| o.as_str()) {
+            None => !player.is_global_chat(),
+            Some("on") => true,
+            Some("off") => false,
+            _ => return Err(CommandError::Usage)
+        };
+        player.set_global_chat(enabled);
+        drop(players);
+
+        ctx.reply(format!("global chat {}", if enabled { "enabled" } else { "disabled" }));
+
+        Ok(())
+    }
+
+    fn complete(&self, _meexprox: &Arc<MeexProx>, index: usize) -> Vec<String> {
+        match index {
+            0 => vec!["on".to_string(), "off".to_string()],
+            _ => Vec::new()
+        }
+    }
+}
+
+pub struct MsgCommand;
+
+impl Command for MsgCommand {
+    fn name(&self) -> &str {
+        "msg"
+    }
+
+    fn aliases(&self) -> Vec<String> {
+        vec!["tell".to_string(), "w".to_string()]
+    }
+
+    fn usage(&self) -> &str {
+        "<player> <message>"
+    }
+
+    fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+        let name = ctx.arg(0)?.to_string();
+        let message = ctx.rest(1).ok_or(CommandError::Usage)?;
+
+        let recipient = ctx.meexprox.private_message(ctx.sender, &name, &message)
+            .ok_or(CommandError::Failed("player not found".to_string()))?;
+        ctx.reply(format!("§7[me -> {}] §f{}", recipient, message));
+
+        Ok(())
+    }
+
+    fn arguments(&self) -> Vec<&str> {
+        vec!["player", "text"]
+    }
+}
+
+pub struct ReplyCommand;
+
+impl Command for ReplyCommand {
+    fn name(&self) -> &str {
+        "reply"
+    }
+
+    fn aliases(&self) -> Vec<String> {
+        vec!["r".to_string()]
+    }
+
+    fn usage(&self) -> &str {
+        "<message>"
+    }
+
+    fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+        let message = ctx.rest(0).ok_or(CommandError::Usage)?;
+
+        let CommandSender::Player(name) = ctx.sender else {
+            return Err(CommandError::Failed("only players can reply".to_string()));
+        };
+
+        let target = ctx.meexprox.get_players().iter()
+            .find(|o| &o.name == name)
+            .and_then(|o| o.reply_target())
+            .ok_or(CommandError::Failed("nobody to reply to".to_string()))?;
+
+        let recipient = ctx.meexprox.private_message(ctx.sender, &target, &message)
+            .ok_or(CommandError::Failed(format!("{} is offline", target)))?;
+        ctx.reply(format!("§7[me -> {}] §f{}", recipient, message));
+
+        Ok(())
+    }
+
+    fn arguments(&self) -> Vec<&str> {
+        vec!["text"]
+    }
+}
+
+pub struct AlertCommand;
+
+impl Command for AlertCommand {
+    fn name(&self) -> &str {
+        "alert"
+    }
+
+    fn permission(&self) -> Option<&str> {
+        Some("meexprox.command.alert")
+    }
+
+    fn usage(&self) -> &str {
+        "<message>"
+    }
+
+    fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+        let message = ctx.rest(0).ok_or(CommandError::Usage)?;
+        ctx.meexprox.alert(&message);
+        ctx.reply("alert sent");
+
+        Ok(())
+    }
+
+    fn arguments(&self) -> Vec<&str> {
+        vec!["text"]
+    }
+}
+
+pub struct StaffChatCommand;
+
+impl Command for StaffChatCommand {
+    fn name(&self) -> &str {
+        "sc"
+    }
+
+    fn aliases(&self) -> Vec<String> {
+        vec!["staffchat".to_string()]
+    }
+
+    fn permission(&self) -> Option<&str> {
+        Some(chat::STAFF_CHAT_PERMISSION)
+    }
+
+    fn usage(&self) -> &str {
+        "<message>"
+    }
+
+    fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+        let message = ctx.rest(0).ok_or(CommandError::Usage)?;
+        ctx.meexprox.staff_chat(ctx.sender.name(), &message);
+
+        // console is not a player, so it does not receive staff chat by itself
+        if *ctx.sender == CommandSender::Console {
+            ctx.reply(ctx.meexprox.config().staff_chat.format(ctx.sender.name(), &message));
+        }
+
+        Ok(())
+    }
+
+    fn arguments(&self) -> Vec<&str> {
+        vec!["text"]
+    }
+}
+
+const STATS_PACKETS_LIMIT: usize = 20;
+
+pub struct StatsCommand;
+
+impl Command for StatsCommand {
+    fn name(&self) -> &str {
+        "stats"
+    }
+
+    fn permission(&self) -> Option<&str> {
+        Some("meexprox.command.stats")
+    }
+
+    fn usage(&self) -> &str {
+        "[packets]"
+    }
+
+    fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+        let stats = ctx.meexprox.stats();
+
+        match ctx.args.first().map(|o| o.as_str()) {
+            None => {
+                let throughput = |window| {
+                    let o = stats.throughput(Duration::from_secs(window));
+                    format!("{:.1} packets/s, {:.1} KB/s", o.packets, o.bytes / 1024.0)
+                };
+                let lines = [
+                    format!("uptime: {}", storage::format_duration(stats.uptime().as_secs() as i64)),
+                    format!("connections: {}", stats.connections()),
+                    format!("players online: {}", ctx.meexprox.online_count()),
+                    format!("status requests: {}", stats.status_requests()),
+                    format!("serverbound: {} packets, {} bytes", stats.packets_serverbound(), stats.bytes_serverbound()),
+                    format!("clientbound: {} packets, {} bytes", stats.packets_clientbound(), stats.bytes_clientbound()),
+                    format!("throughput 10s: {}", throughput(10)),
+                    format!("throughput 1m: {}", throughput(60))
+                ];
+                for line in lines {
+                    ctx.reply(line);
+                }
+                for (server, failures) in stats.connect_failures() {
+                    ctx.reply(format!("connect failures of {}: {}", server, failures));
+                }
+            }
+            Some("packets") => {
+                let counts = stats.packet_counts();
+                for o in counts.iter().take(STATS_PACKETS_LIMIT) {
+                    ctx.reply(format!(
+                        "{} {} 0x{:02X}: {} packets, {} bytes",
+                        o.direction.name(), o.state.name(), o.id, o.count, o.bytes
+                    ));
+                }
+                if counts.len() > STATS_PACKETS_LIMIT {
+                    ctx.reply(format!("and {} more", counts.len() - STATS_PACKETS_LIMIT));
+                }
+            }
+            _ => return Err(CommandError::Usage)
+        }
+
+        Ok(())
+    }
+
+    fn complete(&self, _meexprox: &Arc<MeexProx>, index: usize) -> Vec<String> {
+        match index {
+            0 => vec!["packets".to_string()],
+            _ => Vec::new()
+        }
+    }
+}
+
+pub struct IpBlocksCommand;
+
+impl Command for IpBlocksCommand {
+    fn name(&self) -> &str {
+        "ipblocks"
+    }
+
+    fn permission(&self) -> Option<&str> {
+        Some("meexprox.command.ipblocks")
+    }
+
+    fn usage(&self) -> &str {
+        "[list | clear [ip]]"
+    }
+
+    fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+        let blocks = ctx.meexprox.ip_blocks();
+
+        match ctx.args.first().map(|o| o.as_str()) {
+            None | Some("list") => {
+                let blocked = blocks.blocked();
+                if blocked.is_empty() {
+                    ctx.reply("no blocked ips");
+                }
+                for (ip, left) in blocked {
+                    ctx.reply(format!("{} for {}", ip, storage::format_duration(left.as_secs() as i64)));
+                }
+            }
+            Some("clear") => match ctx.args.get(1) {
+                Some(ip) => {
+                    let ip: IpAddr = ip.parse().map_err(|_| CommandError::Failed(format!("invalid ip {}", ip)))?;
+                    if !blocks.unblock(ip) {
+                        return Err(CommandError::Failed(format!("{} is not blocked", ip)));
+                    }
+                    ctx.reply(format!("{} unblocked", ip));
+                }
+                None => {
+                    let count = blocks.clear();
+                    ctx.reply(format!("{} ips unblocked", count));
+                }
+            },
+            _ => return Err(CommandError::Usage)
+        }
+
+        Ok(())
+    }
+
+    fn complete(&self, meexprox: &Arc<MeexProx>, index: usize) -> Vec<String> {
+        match index {
+            0 => vec!["list".to_string(), "clear".to_string()],
+            1 => meexprox.ip_blocks().blocked().iter().map(|o| o.0.to_string()).collect(),
+            _ => Vec::new()
+        }
+    }
+}
+
+const EVENTS_DEFAULT_LAST: usize = 20;
+
+pub struct EventsCommand;
+
+impl Command for EventsCommand {
+    fn name(&self) -> &str {
+        "events"
+    }
+
+    fn permission(&self) -> Option<&str> {
+        Some("meexprox.command.events")
+    }
+
+    fn usage(&self) -> &str {
+        "[--last <count>] [type...]"
+    }
+
+    fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+        let mut last = EVENTS_DEFAULT_LAST;
+        let mut types = Vec::new();
+
+        let mut args = ctx.args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "--last" {
+                last = args.next().and_then(|o| o.parse().ok()).ok_or(CommandError::Usage)?;
+            } else if EventFilter::is_known(arg) {
+                types.push(arg.clone());
+            } else {
+                return Err(CommandError::Failed(format!("unknown event type {}, types are {}", arg, ProxyEventRecord::NAMES.join(", "))));
+            }
+        }
+
+        let filter = (!types.is_empty()).then(|| EventFilter::new(&types));
+        let events: Vec<TimedRecord> = ctx.meexprox.recent_events(usize::MAX)
+            .into_iter()
+            .filter(|o| filter.as_ref().is_none_or(|f| f.accepts(&o.record)))
+            .collect();
+        let events = &events[events.len().saturating_sub(last)..];
+
+        if events.is_empty() {
+            ctx.reply("no events");
+        }
+
+        let now = storage::now();
+        for event in events {
+            ctx.reply(format!("{} ago: {}", storage::format_duration(now - event.time), event.record.to_json()));
+        }
+
+        Ok(())
+    }
+
+    fn complete(&self, _meexprox: &Arc<MeexProx>, _index: usize) -> Vec<String> {
+        std::iter::once("--last")
+            .chain(ProxyEventRecord::NAMES.iter().copied())
+            .map(|o| o.to_string())
+            .collect()
+    }
+}
+
+pub struct DumpCommand;
+
+impl Command for DumpCommand {
+    fn name(&self) -> &str {
+        "dump"
+    }
+
+    fn permission(&self) -> Option<&str> {
+        Some("meexprox.command.dump")
+    }
+
+    fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+        let path = dump::write(ctx.meexprox)
+            .map_err(|e| CommandError::Failed(format!("dump write error: {}", e)))?;
+        ctx.reply(format!("diagnostics written to {}", path.display()));
+
+        Ok(())
+    }
+}
+
+pub struct ReloadCommand;
+
+impl Command for ReloadCommand {
+    fn name(&self) -> &str {
+        "reload"
+    }
+
+    fn permission(&self) -> Option<&str> {
+        Some("meexprox.command.reload")
+    }
+
+    fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+        ctx.meexprox.reload_config()
+            .map_err(|_| CommandError::Failed("config reload error".to_string()))?;
+        ctx.reply("config reloaded");
+
+        Ok(())
+    }
+}
+
+pub struct MaintenanceCommand;
+
+impl Command for MaintenanceCommand {
+    fn name(&self) -> &str {
+        "maintenance"
+    }
+
+    fn permission(&self) -> Option<&str> {
+        Some("meexprox.command.maintenance")
+    }
+
+    fn usage(&self) -> &str {
+        "[on [message] | off]"
+    }
+
+    fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+        match ctx.args.first().map(|o| o.as_str()) {
+            None => {
+                let state = match ctx.meexprox.config().maintenance.clone() {
+                    Some(message) => format!("on ({})", message),
+                    None => "off".to_string()
+                };
+                ctx.reply(format!("maintenance is {}", state));
+            }
+            Some("on") => {
+                let message = ctx.rest(1).unwrap_or("Server is under maintenance".to_string());
+                ctx.meexprox.set_maintenance(Some(message));
+                ctx.reply("maintenance enabled");
+            }
+            Some("off") => {
+                ctx.meexprox.set_maintenance(None);
+                ctx.reply("maintenance disabled");
+            }
+            _ => return Err(CommandError::Usage)
+        }
+
+        Ok(())
+    }
+
+    fn complete(&self, _meexprox: &Arc<MeexProx>, index: usize) -> Vec<String> {
+        match index {
+            0 => vec!["on".to_string(), "off".to_string()],
+            _ => Vec::new()
+        }
+    }
+}
+
+pub struct EndCommand;
+
+impl Command for EndCommand {
+    fn name(&self) -> &str {
+        "end"
+    }
+
+    fn permission(&self) -> Option<&str> {
+        Some("meexprox.command.end")
+    }
+
+    fn aliases(&self) -> Vec<String> {
+        vec!["stop".to_string()]
+    }
+
+    fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+        ctx.meexprox.shutdown();
+        process::exit(0);
+    }
+}
+
+pub use database::{BanCommand, SeenCommand, TempbanCommand, UnbanCommand, WhitelistCommand};
+
+mod database {
+    use std::sync::Arc;
+
+    use super::super::{
+        command::{Command, CommandContext, CommandError},
+        storage::{self, Storage, StorageError},
+        MeexProx,
+    };
+
+    fn store(meexprox: &MeexProx) -> Result<Arc<dyn Storage>, CommandError> {
+        meexprox.storage()
+            .cloned()
+            .ok_or(CommandError::Failed("storage is not enabled".to_string()))
+    }
+
+    fn storage_error(e: StorageError) -> CommandError {
+        CommandError::Failed(format!("storage error: {}", e))
+    }
+
+    pub struct BanCommand;
+
+    impl Command for BanCommand {
+        fn name(&self) -> &str {
+            "ban"
+        }
+
+        fn permission(&self) -> Option<&str> {
+            Some("meexprox.command.ban")
+        }
+
+        fn usage(&self) -> &str {
+            "<player> [reason]"
+        }
+
+        fn arguments(&self) -> Vec<&str> {
+            vec!["player", "text"]
+        }
+
+        fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+            let name = ctx.arg(0)?.to_string();
+            let reason = ctx.rest(1).unwrap_or("Banned by an operator".to_string());
+
+            ctx.meexprox.ban(&name, reason, None).map_err(storage_error)?;
+            ctx.reply(format!("{} banned", name));
+
+            Ok(())
+        }
+    }
+
+    pub struct TempbanCommand;
+
+    impl Command for TempbanCommand {
+        fn name(&self) -> &str {
+            "tempban"
+        }
+
+        fn permission(&self) -> Option<&str> {
+            Some("meexprox.command.tempban")
+        }
+
+        fn usage(&self) -> &str {
+            "<player> <duration> [reason]"
+        }
+
+        fn arguments(&self) -> Vec<&str> {
+            vec!["player", "duration", "text"]
+        }
+
+        fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+            let name = ctx.arg(0)?.to_string();
+            let duration = storage::parse_duration(ctx.arg(1)?)
+                .ok_or(CommandError::Failed("invalid duration, use format like 7d12h".to_string()))?;
+            let reason = ctx.rest(2).unwrap_or("Banned by an operator".to_string());
+
+            ctx.meexprox.ban(&name, reason, Some(duration)).map_err(storage_error)?;
+            ctx.reply(format!("{} banned for {}", name, storage::format_duration(duration)));
+
+            Ok(())
+        }
+    }
+
+    pub struct UnbanCommand;
+
+    impl Command for UnbanCommand {
+        fn name(&self) -> &str {
+            "unban"
+        }
+
+        fn permission(&self) -> Option<&str> {
+            Some("meexprox.command.unban")
+        }
+
+        fn usage(&self) -> &str {
+            "<player>"
+        }
+
+        fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+            let name = ctx.arg(0)?.to_string();
+
+            if store(ctx.meexprox)?.remove_ban(&name).map_err(storage_error)? {
+                ctx.reply(format!("{} unbanned", name));
+                Ok(())
+            } else {
+                Err(CommandError::Failed(format!("{} is not banned", name)))
+            }
+        }
+    }
+
+    pub struct WhitelistCommand;
+
+    impl Command for WhitelistCommand {
+        fn name(&self) -> &str {
+            "whitelist"
+        }
+
+        fn permission(&self) -> Option<&str> {
+            Some("meexprox.command.whitelist")
+        }
+
+        fn usage(&self) -> &str {
+            "<add | remove> <player> | list"
+        }
+
+        fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+            let store = store(ctx.meexprox)?;
+
+            match ctx.arg(0)? {
+                "add" => {
+                    let name = ctx.arg(1)?.to_string();
+                    store.whitelist_add(&name).map_err(storage_error)?;
+                    ctx.reply(format!("{} added to whitelist", name));
+                }
+                "remove" => {
+                    let name = ctx.arg(1)?.to_string();
+                    if !store.whitelist_remove(&name).map_err(storage_error)? {
+                        return Err(CommandError::Failed(format!("{} is not whitelisted", name)));
+                    }
+                    ctx.reply(format!("{} removed from whitelist", name));
+                }
+                "list" => {
+                    let names = store.whitelist().map_err(storage_error)?;
+                    ctx.reply(format!("whitelisted ({}): {}", names.len(), names.join(", ")));
+                }
+                _ => return Err(CommandError::Usage)
+            }
+
+            Ok(())
+        }
+
+        fn complete(&self, meexprox: &Arc<MeexProx>, index: usize) -> Vec<String> {
+            match index {
+                0 => vec!["add".to_string(), "remove".to_string(), "list".to_string()],
+                1 => meexprox.storage()
+                    .and_then(|o| o.whitelist().ok())
+                    .unwrap_or_default(),
+                _ => Vec::new()
+            }
+        }
+    }
+
+    pub struct SeenCommand;
+
+    impl Command for SeenCommand {
+        fn name(&self) -> &str {
+            "seen"
+        }
+
+        fn permission(&self) -> Option<&str> {
+            Some("meexprox.command.seen")
+        }
+
+        fn usage(&self) -> &str {
+            "<player>"
+        }
+
+        fn arguments(&self) -> Vec<&str> {
+            vec!["player"]
+        }
+
+        fn execute(&self, ctx: &mut CommandContext) -> Result<(), CommandError> {
+            let name = ctx.arg(0)?.to_string();
+            let record = store(ctx.meexprox)?.get_player(&name).map_err(storage_error)?
+                .ok_or(CommandError::Failed(format!("{} has never joined", name)))?;
+
+            let online = ctx.meexprox.get_players().iter()
+                .any(|o| o.uuid == record.uuid && o.is_connected());
+            let now = storage::now();
+
+            if online {
+                ctx.reply(format!("{} is online", record.name));
+            } else {
+                ctx.reply(format!("{} was last seen {} ago", record.name, storage::format_duration(now - record.last_seen)));
+            }
+
+            ctx.reply(format!("first joined {} ago", storage::format_duration(now - record.first_seen)));
+            ctx.reply(format!("play time: {}", storage::format_duration(record.play_time)));
+            if !record.server_time.is_empty() {
+                ctx.reply(format!("time on servers: {}", record.format_server_time()));
+            }
+
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use rust_mc_proto::{Packet, ProtocolError};
+use serde_json::{json, Map, Value};
+
+use super::protocol;
+
+/// Action done when component is clicked in chat
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClickEvent {
+    OpenUrl(String),
+    RunCommand(String),
+    SuggestCommand(String),
+    CopyToClipboard(String)
+}
+
+impl ClickEvent {
+    fn to_json(&self) -> Value {
+        let (action, value) = match self {
+            ClickEvent::OpenUrl(o) => ("open_url", o),
+            ClickEvent::RunCommand(o) => ("run_command", o),
+            ClickEvent::SuggestCommand(o) => ("suggest_command", o),
+            ClickEvent::CopyToClipboard(o) => ("copy_to_clipboard", o)
+        };
+        json!({ "action": action, "value": value })
+    }
+}
+
+/// Text component, serialized as json or nbt depending on protocol version
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Component {
+    pub text: String,
+    /// Color name (`red`, `dark_aqua`, ...) or hex like `#ff8800`
+    pub color: Option<String>,
+    pub bold: Option<bool>,
+    pub hover: Option<Box<Component>>,
+    pub click: Option<ClickEvent>,
+    pub extra: Vec<Component>
+}
+
+impl Component {
+    pub fn text(text: impl Into<String>) -> Component {
+        Component {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn color(mut self, color: impl Into<String>) -> Component {
+        self.color = Some(color.into());
+        self
+    }
+
+    pub fn bold(mut self, bold: bool) -> Component {
+        self.bold = Some(bold);
+        self
+    }
+
+    /// Text shown when cursor is over the component
+    pub fn hover(mut self, hover: impl Into<Component>) -> Component {
+        self.hover = Some(Box::new(hover.into()));
+        self
+    }
+
+    pub fn click(mut self, click: ClickEvent) -> Component {
+        self.click = Some(click);
+        self
+    }
+
+    /// Adds child component, children inherit style of parent
+    pub fn append(mut self, extra: impl Into<Component>) -> Component {
+        self.extra.push(extra.into());
+        self
+    }
+
+    /// Text of component and all its children without formatting
+    pub fn to_plain(&self) -> String {
+        let mut text = self.text.clone();
+        for extra in &self.extra {
+            text.push_str(&extra.to_plain());
+        }
+        text
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut map = Map::new();
+        map.insert("text".to_string(), Value::String(self.text.clone()));
+
+        if let Some(color) = &self.color {
+            map.insert("color".to_string(), Value::String(color.clone()));
+        }
+        if let Some(bold) = self.bold {
+            map.insert("bold".to_string(), Value::Bool(bold));
+        }
+        if let Some(hover) = &self.hover {
+            map.insert("hoverEvent".to_string(), json!({ "action": "show_text", "contents": hover.to_json() }));
+        }
+        if let Some(click) = &self.click {
+            map.insert("clickEvent".to_string(), click.to_json());
+        }
+        if !self.extra.is_empty() {
+            map.insert("extra".to_string(), Value::Array(self.extra.iter().map(|o| o.to_json()).collect()));
+        }
+
+        Value::Object(map)
+    }
+
+    /// Writes component as json string before 1.20.3 and as network nbt since
+    pub fn write(&self, packet: &mut Packet, protocol_version: u16) -> Result<(), ProtocolError> {
+        protocol::write_component(packet, protocol_version, &self.to_json())
+    }
+}
+
+impl From<&str> for Component {
+    fn from(text: &str) -> Component {
+        Component::text(text)
+    }
+}
+
+impl From<String> for Component {
+    fn from(text: String) -> Component {
+        Component::text(text)
+    }
+}
+
+impl From<&String> for Component {
+    fn from(text: &String) -> Component {
+        Component::text(text.clone())
+    }
+}
+
+impl std::fmt::Display for Component {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_plain())
+    }
+}
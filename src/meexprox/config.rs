@@ -1,6 +1,15 @@
+use log::error;
+use regex::Regex;
 use serde_yml::{Mapping, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::fs;
-use std::path::Path;
+use std::io;
+use std::net::{IpAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::{broadcast::Broadcasts, chat::{GlobalChat, StaffChat}, discord::DiscordNotifier, forwarding, geoip::Location, ipblock::IpBlockConfig, motd::MotdRotation, permissions::Permissions, pool::PoolConfig, protocol, sanitizer::Sanitizer, screener::ScreenerConfig, sidebar::SidebarConfig, split::WriteBatching, throttle::ThrottleConfig, status::StatusResponse, storage::StorageConfig, transfer::TransferConfig, tunnel::{self, TunnelLink, TunnelListener}, webhook::Webhook, workers::WorkerConfig};
 
 #[derive(Clone, Debug)]
 pub struct ServerInfo {
@@ -8,6 +17,20 @@ pub struct ServerInfo {
     pub host: String,
     pub domains: Vec<String>,
     pub player_forwarding: PlayerForwarding,
+    /// Other names the server can be found by
+    pub aliases: Vec<String>,
+    /// Status shown for domains of server while it is down, global one is used if None
+    pub offline_status: Option<StatusInfo>,
+    /// Other servers able to serve connections of this one, connected in parallel with it
+    pub try_servers: Vec<String>,
+    /// What to do with extra data of handshake address when connecting to server
+    pub address_extra: AddressExtra,
+    /// How server is picked among this one and its `try` servers
+    pub strategy: RoutingStrategy,
+    /// Where server is, for `nearest` strategy
+    pub location: Option<Location>,
+    /// Compressed link used instead of connecting to host, when server is another meexprox
+    pub tunnel: Option<TunnelLink>
 }
 
 impl ServerInfo {
@@ -21,7 +44,14 @@ impl ServerInfo {
             name,
             host,
             domains,
-            player_forwarding
+            player_forwarding,
+            aliases: Vec::new(),
+            offline_status: None,
+            try_servers: Vec::new(),
+            address_extra: AddressExtra::Preserve,
+            strategy: RoutingStrategy::Parallel,
+            location: None,
+            tunnel: None
         }
     }
 
@@ -30,11 +60,92 @@ impl ServerInfo {
             name: String::new(),
             host,
             domains: Vec::new(),
-            player_forwarding
+            player_forwarding,
+            aliases: Vec::new(),
+            offline_status: None,
+            try_servers: Vec::new(),
+            address_extra: AddressExtra::Preserve,
+            strategy: RoutingStrategy::Parallel,
+            location: None,
+            tunnel: None
+        }
+    }
+
+    /// Connects to server, through tunnel if it has one
+    pub fn connect(&self) -> io::Result<TcpStream> {
+        match &self.tunnel {
+            Some(link) => tunnel::connect(link),
+            None => TcpStream::connect(&self.host)
+        }
+    }
+
+    /// Whether name is the server name or one of its aliases
+    pub fn has_name(&self, name: &str) -> bool {
+        self.name == name || self.aliases.iter().any(|o| o == name)
+    }
+}
+
+/// How server is picked among members of group, formed by server and its `try` servers
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RoutingStrategy {
+    /// Connect to all, staggered by `try_stagger`, first connected wins
+    #[default]
+    Parallel,
+    /// Server nearest to GeoIP location of player first, by lowest RTT if location is unknown
+    Nearest,
+    /// Server with lowest RTT measured by health checks first
+    LowestRtt
+}
+
+impl RoutingStrategy {
+    pub fn from_name(name: &str) -> Option<RoutingStrategy> {
+        match name {
+            "parallel" => Some(RoutingStrategy::Parallel),
+            "nearest" => Some(RoutingStrategy::Nearest),
+            "lowest_rtt" => Some(RoutingStrategy::LowestRtt),
+            _ => None
         }
     }
 }
 
+/// Policy for extra data after hostname in handshake address (forge markers, proxy fields)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AddressExtra {
+    Strip,
+    Preserve,
+    /// Extra data is replaced with this
+    Rewrite(String)
+}
+
+impl AddressExtra {
+    pub fn from_data(data: &Mapping) -> Option<AddressExtra> {
+        Some(match data.get("address_extra")?.as_str()? {
+            "strip" => AddressExtra::Strip,
+            "preserve" => AddressExtra::Preserve,
+            "rewrite" => AddressExtra::Rewrite(data.get("address_extra_rewrite")?.as_str()?.to_string()),
+            _ => return None
+        })
+    }
+}
+
+/// What to do with forwarding data in handshake address of client that is not trusted proxy
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UntrustedForwarding {
+    #[default]
+    Strip,
+    Disconnect
+}
+
+impl UntrustedForwarding {
+    pub fn from_name(name: &str) -> Option<UntrustedForwarding> {
+        Some(match name {
+            "strip" => UntrustedForwarding::Strip,
+            "disconnect" => UntrustedForwarding::Disconnect,
+            _ => return None
+        })
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum PlayerForwarding {
     Velocity(String),
@@ -77,19 +188,496 @@ impl PlayerForwarding {
     }
 }
 
+/// Routes handshake addresses matching pattern to server named by target,
+/// `$1`, `${name}` in target are replaced with capture groups
+#[derive(Clone, Debug)]
+pub struct RouteRule {
+    pub pattern: Regex,
+    pub server: String
+}
+
+impl RouteRule {
+    pub fn from_data(data: &Mapping) -> Option<RouteRule> {
+        let pattern = data.get("pattern")?.as_str()?;
+        let pattern = match Regex::new(pattern) {
+            Ok(pattern) => pattern,
+            Err(e) => {
+                error!("invalid route pattern {}: {}", pattern, e);
+                return None;
+            }
+        };
+
+        Some(RouteRule {
+            pattern,
+            server: data.get("server")?.as_str()?.to_string()
+        })
+    }
+
+    /// Server name for address, if it matches the pattern
+    pub fn target(&self, address: &str) -> Option<String> {
+        let captures = self.pattern.captures(address)?;
+        let mut target = String::new();
+        captures.expand(&self.server, &mut target);
+        Some(target)
+    }
+}
+
+/// Rules of login names, checked before player is sent to server
+#[derive(Clone, Debug)]
+pub struct UsernameRules {
+    /// Replaces vanilla rule (3 to 16 of `a-z`, `A-Z`, `0-9` and `_`), for Geyser or offline mode names
+    pub pattern: Option<Regex>,
+    /// Disconnect message of invalid names
+    pub message: String
+}
+
+impl Default for UsernameRules {
+    fn default() -> UsernameRules {
+        UsernameRules {
+            pattern: None,
+            message: "Invalid username".to_string()
+        }
+    }
+}
+
+impl UsernameRules {
+    pub fn from_data(data: &Mapping) -> Option<UsernameRules> {
+        let default = UsernameRules::default();
+
+        let pattern = match data.get("pattern").and_then(|o| o.as_str()).filter(|o| !o.is_empty()) {
+            Some(pattern) => match Regex::new(pattern) {
+                Ok(pattern) => Some(pattern),
+                Err(e) => {
+                    error!("invalid username pattern {}: {}", pattern, e);
+                    return None;
+                }
+            },
+            None => None
+        };
+
+        Some(UsernameRules {
+            pattern,
+            message: data.get("message")
+                .and_then(|o| o.as_str())
+                .map(|o| o.to_string())
+                .unwrap_or(default.message)
+        })
+    }
+
+    pub fn is_valid(&self, name: &str) -> bool {
+        match &self.pattern {
+            Some(pattern) => pattern.is_match(name),
+            None => (3..=16).contains(&name.len()) && name.chars().all(|o| o.is_ascii_alphanumeric() || o == '_')
+        }
+    }
+}
+
+/// Status built by proxy itself, without asking any backend
+#[derive(Clone, Debug)]
+pub struct StatusInfo {
+    pub motd: String,
+    /// `{version}` is replaced with release versions of client protocol
+    pub version: String,
+    pub max_players: usize
+}
+
+impl StatusInfo {
+    pub fn from_data(data: &Mapping) -> Option<StatusInfo> {
+        if !data.get("enabled")?.as_bool()? {
+            return None;
+        }
+
+        Some(StatusInfo {
+            motd: data.get("motd")?.as_str()?.to_string(),
+            version: data.get("version")
+                .and_then(|o| o.as_str())
+                .unwrap_or("meexprox")
+                .to_string(),
+            max_players: data.get("max_players")
+                .and_then(|o| o.as_u64())
+                .unwrap_or(0) as usize
+        })
+    }
+
+    /// Protocol version is the one sent by client, so snapshots see status as compatible
+    pub fn to_status(&self, protocol_version: i32) -> StatusResponse {
+        StatusResponse::new(
+            self.version.replace("{version}", &protocol::raw_version_name(protocol_version)),
+            protocol_version,
+            self.max_players as i32,
+            0,
+            &self.motd
+        )
+    }
+}
+
+/// Replaces backend player count in status with proxy totals
+#[derive(Clone, Debug)]
+pub struct PlayerCount {
+    /// Max players shown, backend value is kept if None
+    pub max_players: Option<i32>,
+    /// Named groups of server names, servers in group count only players of the group
+    pub groups: HashMap<String, Vec<String>>
+}
+
+impl PlayerCount {
+    pub fn from_data(data: &Mapping) -> Option<PlayerCount> {
+        if !data.get("enabled")?.as_bool()? {
+            return None;
+        }
+
+        Some(PlayerCount {
+            max_players: data.get("max_players")
+                .and_then(|o| o.as_i64())
+                .map(|o| o as i32),
+            groups: data.get("groups")
+                .and_then(|o| o.as_mapping())
+                .map(|o| o.iter()
+                    .filter_map(|(k, v)| Some((
+                        k.as_str()?.to_string(),
+                        v.as_sequence()?
+                            .iter()
+                            .filter_map(|o| o.as_str())
+                            .map(|o| o.to_string())
+                            .collect()
+                    )))
+                    .collect())
+                .unwrap_or_default()
+        })
+    }
+
+    /// Servers counted together with server, None if all servers are counted
+    pub fn group_of(&self, server: &str) -> Option<&Vec<String>> {
+        self.groups.values().find(|o| o.iter().any(|o| o == server))
+    }
+}
+
+/// Retrying of backend connection on login
+#[derive(Clone, Debug)]
+pub struct ConnectRetry {
+    pub attempts: u32,
+    /// Delay before second attempt, doubled after every next one
+    pub delay: Duration,
+    /// Server to connect to when all attempts failed
+    pub fallback: Option<String>
+}
+
+impl ConnectRetry {
+    pub fn from_data(data: &Mapping) -> Option<ConnectRetry> {
+        if !data.get("enabled")?.as_bool()? {
+            return None;
+        }
+
+        Some(ConnectRetry {
+            attempts: data.get("attempts")
+                .and_then(|o| o.as_u64())
+                .unwrap_or(3) as u32,
+            delay: Duration::from_millis(data.get("delay")
+                .and_then(|o| o.as_u64())
+                .unwrap_or(500)),
+            fallback: data.get("fallback")
+                .and_then(|o| o.as_str())
+                .map(|o| o.to_string())
+        })
+    }
+}
+
+/// Clients with protocol versions missing from version table, like snapshots and pre-releases,
+/// are accepted and routed to one server
+#[derive(Clone, Debug)]
+pub struct SnapshotTolerance {
+    pub server: String
+}
+
+impl SnapshotTolerance {
+    pub fn from_data(data: &Mapping) -> Option<SnapshotTolerance> {
+        if !data.get("enabled")?.as_bool()? {
+            return None;
+        }
+
+        Some(SnapshotTolerance {
+            server: data.get("server")?.as_str()?.to_string()
+        })
+    }
+}
+
+/// Chat message and title sent to player after joining
+#[derive(Clone, Debug)]
+pub struct JoinMessage {
+    pub message: Option<String>,
+    pub title: Option<String>,
+    pub subtitle: Option<String>,
+    /// Title times in ticks
+    pub fade_in: i32,
+    pub stay: i32,
+    pub fade_out: i32
+}
+
+impl JoinMessage {
+    pub fn from_data(data: &Mapping) -> Option<JoinMessage> {
+        if !data.get("enabled")?.as_bool()? {
+            return None;
+        }
+
+        let text = |key: &str| data.get(key)
+            .and_then(|o| o.as_str())
+            .map(|o| o.to_string());
+        let ticks = |key: &str, default: i64| data.get(key)
+            .and_then(|o| o.as_i64())
+            .unwrap_or(default) as i32;
+
+        Some(JoinMessage {
+            message: text("message"),
+            title: text("title"),
+            subtitle: text("subtitle"),
+            fade_in: ticks("fade_in", 10),
+            stay: ticks("stay", 70),
+            fade_out: ticks("fade_out", 20)
+        })
+    }
+}
+
+/// Format of `/alert` command message
+#[derive(Clone, Debug)]
+pub struct AlertMessage {
+    /// Chat message, `{message}` is replaced with alert text
+    pub format: String,
+    /// Title shown with alert text as subtitle, no title if None
+    pub title: Option<String>
+}
+
+impl Default for AlertMessage {
+    fn default() -> AlertMessage {
+        AlertMessage {
+            format: "§4[Alert] §c{message}".to_string(),
+            title: Some("§4Alert".to_string())
+        }
+    }
+}
+
+impl AlertMessage {
+    pub fn from_data(data: &Mapping) -> AlertMessage {
+        let default = AlertMessage::default();
+
+        AlertMessage {
+            format: data.get("format")
+                .and_then(|o| o.as_str())
+                .map(|o| o.to_string())
+                .unwrap_or(default.format),
+            title: match data.get("title") {
+                Some(title) => title.as_str()
+                    .filter(|o| !o.is_empty())
+                    .map(|o| o.to_string()),
+                None => default.title
+            }
+        }
+    }
+
+    pub fn format(&self, message: &str) -> String {
+        self.format.replace("{message}", message)
+    }
+}
+
+/// What happens when player logs in while the same uuid or name is connected
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Existing session is kicked
+    #[default]
+    KickExisting,
+    /// New login is denied
+    Deny,
+    /// Both sessions stay connected
+    Allow
+}
+
+impl DuplicatePolicy {
+    pub fn from_name(name: &str) -> Option<DuplicatePolicy> {
+        match name {
+            "kick_existing" => Some(DuplicatePolicy::KickExisting),
+            "deny" => Some(DuplicatePolicy::Deny),
+            "allow" => Some(DuplicatePolicy::Allow),
+            _ => None
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DuplicateSession {
+    pub policy: DuplicatePolicy,
+    /// Kick message of existing session
+    pub kick_message: String,
+    /// Disconnect message of denied login
+    pub deny_message: String
+}
+
+impl Default for DuplicateSession {
+    fn default() -> DuplicateSession {
+        DuplicateSession {
+            policy: DuplicatePolicy::default(),
+            kick_message: "You logged in from another location".to_string(),
+            deny_message: "You are already connected to this server".to_string()
+        }
+    }
+}
+
+impl DuplicateSession {
+    pub fn from_data(data: &Mapping) -> Option<DuplicateSession> {
+        let default = DuplicateSession::default();
+
+        Some(DuplicateSession {
+            policy: match data.get("policy") {
+                Some(policy) => DuplicatePolicy::from_name(policy.as_str()?)?,
+                None => default.policy
+            },
+            kick_message: data.get("kick_message")
+                .and_then(|o| o.as_str())
+                .map(|o| o.to_string())
+                .unwrap_or(default.kick_message),
+            deny_message: data.get("deny_message")
+                .and_then(|o| o.as_str())
+                .map(|o| o.to_string())
+                .unwrap_or(default.deny_message)
+        })
+    }
+}
+
 #[derive(Clone)]
 pub struct Messaging {
     pub host: String,
     pub secret: String
 }
 
+#[derive(Clone)]
+pub struct ApiServer {
+    pub host: String,
+    pub token: Option<String>
+}
+
+impl ApiServer {
+    pub fn from_data(data: &Mapping) -> Option<ApiServer> {
+        if !data.get("enabled")?.as_bool()? {
+            return None;
+        }
+
+        Some(ApiServer {
+            host: data.get("host")?.as_str()?.to_string(),
+            token: data.get("token")
+                .and_then(|o| o.as_str())
+                .map(|o| o.to_string())
+        })
+    }
+}
+
+/// Additional address proxy listens on, connections to it go to fixed server or domain
+#[derive(Clone, Debug)]
+pub struct ListenerInfo {
+    pub host: String,
+    /// Server every connection goes to, regardless of handshake address
+    pub server: Option<String>,
+    /// Domain that replaces handshake address, routed as usual
+    pub domain: Option<String>
+}
+
+impl ListenerInfo {
+    pub fn from_data(data: &Mapping) -> Option<ListenerInfo> {
+        let text = |key: &str| data.get(key)
+            .and_then(|o| o.as_str())
+            .map(|o| o.to_string());
+
+        Some(ListenerInfo {
+            host: text("host")?,
+            server: text("server"),
+            domain: text("domain")
+        })
+    }
+
+    pub fn port(&self) -> Option<u16> {
+        self.host.rsplit(':').next()?.parse().ok()
+    }
+}
+
 #[derive(Clone)]
 pub struct ProxyConfig {
     pub host: String,
+    /// Listened in addition to host, bound on start only
+    pub listeners: Vec<ListenerInfo>,
     pub servers: Vec<ServerInfo>,
+    /// Regex rules checked before server domains
+    pub routes: Vec<RouteRule>,
     pub messaging: Option<Messaging>,
     pub default_forwarding: PlayerForwarding,
-    pub incoming_forwarding: PlayerForwarding
+    pub incoming_forwarding: PlayerForwarding,
+    /// Addresses of upstream proxies allowed to send incoming forwarding data
+    pub trusted_proxies: Vec<IpAddr>,
+    /// Forwarding data from direct clients (ip and uuid spoofing)
+    pub untrusted_forwarding: UntrustedForwarding,
+    /// Name of this proxy in hop list of chained meexprox proxies, player is kicked if it comes back through it
+    pub proxy_id: String,
+    pub handshake_timeout: Option<Duration>,
+    pub sanitizer: Option<Sanitizer>,
+    /// Rate limits of relayed traffic of every player
+    pub throttle: Option<ThrottleConfig>,
+    pub slow_listener_warning: Option<Duration>,
+    /// Panics after which event listener is disabled
+    pub listener_panic_limit: Option<u32>,
+    pub fallback_status: Option<StatusInfo>,
+    /// Status shown when server of domain is down
+    pub offline_status: Option<StatusInfo>,
+    pub motd: Option<MotdRotation>,
+    pub player_count: Option<PlayerCount>,
+    pub admin: Option<ApiServer>,
+    pub grpc: Option<ApiServer>,
+    pub health_check_interval: Option<Duration>,
+    /// Timeout of status pings of servers reported on startup
+    pub startup_report: Option<Duration>,
+    pub connect_retry: Option<ConnectRetry>,
+    /// Delay between parallel connection attempts to `try` servers
+    pub try_stagger: Duration,
+    pub connection_pool: Option<PoolConfig>,
+    /// Unknown protocol versions are rejected when not set
+    pub snapshot_tolerance: Option<SnapshotTolerance>,
+    /// Status requests are logged at debug level only
+    pub quiet_status: bool,
+    /// Backends can ask proxy questions on `meexprox:query` channel
+    pub backend_query: bool,
+    /// Interval of status request count summary in quiet mode
+    pub status_summary_interval: Option<Duration>,
+    /// Count of last events kept in memory, status requests are not kept
+    pub event_history: usize,
+    /// Interval of tab list latency updates measured by proxy, backend ones are dropped when set
+    pub tab_latency_interval: Option<Duration>,
+    pub maintenance: Option<String>,
+    pub join_message: Option<JoinMessage>,
+    pub global_chat: Option<GlobalChat>,
+    pub alert: AlertMessage,
+    pub duplicate_session: DuplicateSession,
+    pub username: UsernameRules,
+    pub staff_chat: StaffChat,
+    pub discord: Option<DiscordNotifier>,
+    pub broadcasts: Option<Broadcasts>,
+    pub storage: Option<StorageConfig>,
+    pub webhooks: Vec<Webhook>,
+    pub screener: Option<ScreenerConfig>,
+    pub ip_blocks: Option<IpBlockConfig>,
+    /// Clientbound packets are coalesced into vectored writes
+    pub write_batching: Option<WriteBatching>,
+    /// Signed cookies of transferred players, transfers are not accepted when not set
+    pub transfer: Option<TransferConfig>,
+    /// Accepts compressed links of other proxies
+    pub tunnel: Option<TunnelListener>,
+    /// Sidebar scoreboard shown by proxy
+    pub sidebar: Option<SidebarConfig>,
+    /// Thread settings, applied on start only
+    pub workers: WorkerConfig,
+    /// Raw sections of `plugins` mapping by plugin name
+    pub plugins: HashMap<String, Value>,
+    /// MaxMind city database for `nearest` routing strategy
+    pub geoip_database: Option<PathBuf>,
+    /// Groups file, relative to config file
+    pub permissions_file: Option<PathBuf>,
+    pub permissions: Arc<Permissions>,
+    /// File the config was loaded from, used on reload
+    pub path: Option<PathBuf>
 }
 
 impl ProxyConfig {
@@ -102,10 +690,60 @@ impl ProxyConfig {
     ) -> ProxyConfig {
         ProxyConfig {
             host,
+            listeners: Vec::new(),
             servers,
+            routes: Vec::new(),
             messaging,
             default_forwarding,
-            incoming_forwarding
+            incoming_forwarding,
+            trusted_proxies: Vec::new(),
+            untrusted_forwarding: UntrustedForwarding::default(),
+            proxy_id: forwarding::random_proxy_id(),
+            handshake_timeout: Some(Duration::from_secs(5)),
+            sanitizer: None,
+            throttle: None,
+            slow_listener_warning: Some(Duration::from_millis(50)),
+            listener_panic_limit: None,
+            fallback_status: None,
+            offline_status: None,
+            motd: None,
+            player_count: None,
+            admin: None,
+            grpc: None,
+            health_check_interval: Some(Duration::from_secs(10)),
+            startup_report: None,
+            connect_retry: None,
+            try_stagger: Duration::from_millis(250),
+            connection_pool: None,
+            snapshot_tolerance: None,
+            quiet_status: true,
+            backend_query: false,
+            status_summary_interval: Some(Duration::from_secs(300)),
+            event_history: 1000,
+            tab_latency_interval: Some(Duration::from_secs(5)),
+            maintenance: None,
+            join_message: None,
+            global_chat: None,
+            alert: AlertMessage::default(),
+            duplicate_session: DuplicateSession::default(),
+            username: UsernameRules::default(),
+            staff_chat: StaffChat::default(),
+            discord: None,
+            broadcasts: None,
+            storage: None,
+            webhooks: Vec::new(),
+            screener: None,
+            ip_blocks: None,
+            write_batching: None,
+            transfer: None,
+            tunnel: None,
+            sidebar: None,
+            workers: WorkerConfig::default(),
+            plugins: HashMap::new(),
+            geoip_database: None,
+            permissions_file: None,
+            permissions: Arc::new(Permissions::default()),
+            path: None
         }
     }
 
@@ -134,7 +772,7 @@ impl ProxyConfig {
             .iter()
             .filter_map(|o| -> Option<ServerInfo> {
                 let map = o.1.as_mapping()?;
-                Some(ServerInfo::new(
+                let mut server = ServerInfo::new(
                     o.0.as_str()?.to_string(), 
                     map.get("host")?.as_str()?.to_string(), 
                     map.get("domains")?.as_sequence()?
@@ -145,7 +783,35 @@ impl ProxyConfig {
                     PlayerForwarding::from_data(
                         map.get("forwarding")?.as_mapping()?.clone()
                     )?
-                ))
+                );
+                server.aliases = map.get("aliases")
+                    .and_then(|o| o.as_sequence())
+                    .map(|o| o.iter()
+                        .filter_map(|o| o.as_str())
+                        .map(|o| o.to_string())
+                        .collect())
+                    .unwrap_or_default();
+                server.try_servers = map.get("try")
+                    .and_then(|o| o.as_sequence())
+                    .map(|o| o.iter()
+                        .filter_map(|o| o.as_str())
+                        .map(|o| o.to_string())
+                        .collect())
+                    .unwrap_or_default();
+                if let Some(strategy) = map.get("strategy") {
+                    server.strategy = RoutingStrategy::from_name(strategy.as_str()?)?;
+                }
+                server.location = map.get("location").and_then(Location::from_data);
+                if map.contains_key("address_extra") {
+                    server.address_extra = AddressExtra::from_data(map)?;
+                }
+                server.tunnel = map.get("tunnel")
+                    .and_then(|o| o.as_mapping())
+                    .and_then(TunnelLink::from_data);
+                server.offline_status = map.get("offline_status")
+                    .and_then(|o| o.as_mapping())
+                    .and_then(StatusInfo::from_data);
+                Some(server)
             })
             .collect();
 
@@ -157,17 +823,272 @@ impl ProxyConfig {
             data.get("incoming_forwarding")?.as_mapping()?.clone()
         )?;
 
-        Some(ProxyConfig::new(
+        let trusted_proxies = data.get("incoming_forwarding")?.as_mapping()?
+            .get("trusted")
+            .and_then(|o| o.as_sequence())
+            .map(|o| o.iter()
+                .filter_map(|o| o.as_str()?.parse().ok())
+                .collect())
+            .unwrap_or_default();
+
+        let mut config = ProxyConfig::new(
             host,
             servers,
             messaging,
             default_forwarding,
             incoming_forwarding
-        ))
+        );
+
+        config.trusted_proxies = trusted_proxies;
+
+        if let Some(untrusted) = data.get("incoming_forwarding")?.as_mapping()?.get("untrusted") {
+            config.untrusted_forwarding = UntrustedForwarding::from_name(untrusted.as_str()?)?;
+        }
+
+        if let Some(id) = data.get("proxy_id") {
+            config.proxy_id = id.as_str()?.to_string();
+        }
+
+        if let Some(listeners) = data.get("listeners").and_then(|o| o.as_sequence()) {
+            config.listeners = listeners.iter()
+                .filter_map(|o| ListenerInfo::from_data(o.as_mapping()?))
+                .collect();
+        }
+
+        if let Some(routes) = data.get("routes").and_then(|o| o.as_sequence()) {
+            config.routes = routes.iter()
+                .filter_map(|o| RouteRule::from_data(o.as_mapping()?))
+                .collect();
+        }
+
+        if let Some(timeout) = data.get("handshake_timeout") {
+            let timeout = timeout.as_f64()?;
+            config.handshake_timeout = if timeout > 0.0 {
+                Some(Duration::from_secs_f64(timeout))
+            } else {
+                None
+            };
+        }
+
+        if let Some(warning) = data.get("slow_listener_warning") {
+            let warning = warning.as_u64()?;
+            config.slow_listener_warning = if warning > 0 {
+                Some(Duration::from_millis(warning))
+            } else {
+                None
+            };
+        }
+
+        if let Some(limit) = data.get("listener_panic_limit") {
+            let limit = limit.as_u64()? as u32;
+            config.listener_panic_limit = if limit > 0 { Some(limit) } else { None };
+        }
+
+        if let Some(map) = data.get("admin") {
+            config.admin = ApiServer::from_data(map.as_mapping()?);
+        }
+
+        if let Some(map) = data.get("grpc") {
+            config.grpc = ApiServer::from_data(map.as_mapping()?);
+        }
+
+        if let Some(interval) = data.get("health_check_interval") {
+            let interval = interval.as_u64()?;
+            config.health_check_interval = if interval > 0 {
+                Some(Duration::from_secs(interval))
+            } else {
+                None
+            };
+        }
+
+        if let Some(timeout) = data.get("startup_report") {
+            let timeout = timeout.as_u64()?;
+            config.startup_report = if timeout > 0 {
+                Some(Duration::from_secs(timeout))
+            } else {
+                None
+            };
+        }
+
+        if let Some(map) = data.get("connect_retry") {
+            config.connect_retry = ConnectRetry::from_data(map.as_mapping()?);
+        }
+
+        if let Some(stagger) = data.get("try_stagger") {
+            config.try_stagger = Duration::from_millis(stagger.as_u64()?);
+        }
+
+        if let Some(map) = data.get("connection_pool") {
+            config.connection_pool = PoolConfig::from_data(map.as_mapping()?);
+        }
+
+        if let Some(map) = data.get("snapshot_tolerance") {
+            config.snapshot_tolerance = SnapshotTolerance::from_data(map.as_mapping()?);
+        }
+
+        if let Some(quiet) = data.get("quiet_status") {
+            config.quiet_status = quiet.as_bool()?;
+        }
+
+        if let Some(query) = data.get("backend_query") {
+            config.backend_query = query.as_bool()?;
+        }
+
+        if let Some(count) = data.get("event_history") {
+            config.event_history = count.as_u64()? as usize;
+        }
+
+        if let Some(interval) = data.get("status_summary_interval") {
+            let interval = interval.as_u64()?;
+            config.status_summary_interval = if interval > 0 {
+                Some(Duration::from_secs(interval))
+            } else {
+                None
+            };
+        }
+
+        if let Some(interval) = data.get("tab_latency_interval") {
+            let interval = interval.as_u64()?;
+            config.tab_latency_interval = if interval > 0 {
+                Some(Duration::from_secs(interval))
+            } else {
+                None
+            };
+        }
+
+        if let Some(map) = data.get("discord") {
+            config.discord = DiscordNotifier::from_data(map.as_mapping()?);
+        }
+
+        if let Some(map) = data.get("broadcasts") {
+            config.broadcasts = Broadcasts::from_data(map.as_mapping()?);
+        }
+
+        if let Some(map) = data.get("storage") {
+            config.storage = StorageConfig::from_data(map.as_mapping()?);
+        }
+
+        if let Some(map) = data.get("screener") {
+            config.screener = ScreenerConfig::from_data(map.as_mapping()?);
+        }
+
+        if let Some(map) = data.get("ip_blocks") {
+            config.ip_blocks = IpBlockConfig::from_data(map.as_mapping()?);
+        }
+
+        if let Some(map) = data.get("write_batching") {
+            config.write_batching = WriteBatching::from_data(map.as_mapping()?);
+        }
+
+        if let Some(map) = data.get("sidebar") {
+            config.sidebar = SidebarConfig::from_data(map.as_mapping()?);
+        }
+
+        if let Some(map) = data.get("tunnel") {
+            config.tunnel = TunnelListener::from_data(map.as_mapping()?);
+        }
+
+        if let Some(map) = data.get("transfer") {
+            config.transfer = TransferConfig::from_data(map.as_mapping()?);
+        }
+
+        if let Some(map) = data.get("workers") {
+            config.workers = WorkerConfig::from_data(map.as_mapping()?)?;
+        }
+
+        if let Some(webhooks) = data.get("webhooks").and_then(|o| o.as_sequence()) {
+            config.webhooks = webhooks.iter()
+                .filter_map(|o| Webhook::from_data(o.as_mapping()?))
+                .collect();
+        }
+
+        if let Some(plugins) = data.get("plugins").and_then(|o| o.as_mapping()) {
+            config.plugins = plugins.iter()
+                .filter_map(|(k, v)| Some((k.as_str()?.to_string(), v.clone())))
+                .collect();
+        }
+
+        if let Some(map) = data.get("join_message") {
+            config.join_message = JoinMessage::from_data(map.as_mapping()?);
+        }
+
+        if let Some(map) = data.get("global_chat") {
+            config.global_chat = GlobalChat::from_data(map.as_mapping()?);
+        }
+
+        if let Some(file) = data.get("geoip_database") {
+            config.geoip_database = Some(PathBuf::from(file.as_str()?));
+        }
+
+        if let Some(file) = data.get("permissions") {
+            config.permissions_file = Some(PathBuf::from(file.as_str()?));
+        }
+
+        if let Some(map) = data.get("alert") {
+            config.alert = AlertMessage::from_data(map.as_mapping()?);
+        }
+
+        if let Some(map) = data.get("username") {
+            config.username = UsernameRules::from_data(map.as_mapping()?)?;
+        }
+
+        if let Some(map) = data.get("duplicate_session") {
+            config.duplicate_session = DuplicateSession::from_data(map.as_mapping()?)?;
+        }
+
+        if let Some(map) = data.get("staff_chat") {
+            config.staff_chat = StaffChat::from_data(map.as_mapping()?);
+        }
+
+        if let Some(map) = data.get("maintenance") {
+            let map = map.as_mapping()?;
+
+            if map.get("enabled")?.as_bool()? {
+                config.maintenance = Some(map.get("message")?.as_str()?.to_string());
+            }
+        }
+
+        if let Some(map) = data.get("motd") {
+            config.motd = MotdRotation::from_data(map.as_mapping()?);
+        }
+
+        if let Some(map) = data.get("player_count") {
+            config.player_count = PlayerCount::from_data(map.as_mapping()?);
+        }
+
+        if let Some(map) = data.get("fallback_status") {
+            config.fallback_status = StatusInfo::from_data(map.as_mapping()?);
+        }
+
+        if let Some(map) = data.get("offline_status") {
+            config.offline_status = StatusInfo::from_data(map.as_mapping()?);
+        }
+
+        if let Some(map) = data.get("sanitizer") {
+            config.sanitizer = Sanitizer::from_data(map.as_mapping()?);
+        }
+
+        if let Some(map) = data.get("throttle") {
+            config.throttle = ThrottleConfig::from_data(map.as_mapping()?);
+        }
+
+        Some(config)
     }
 
     pub fn load(path: impl AsRef<Path>) -> Option<ProxyConfig> {
-        Self::load_yml(fs::read_to_string(path).ok()?)
+        let mut config = Self::load_yml(fs::read_to_string(path.as_ref()).ok()?)?;
+        config.path = Some(path.as_ref().to_path_buf());
+
+        if let Some(file) = &config.permissions_file {
+            let file = path.as_ref().parent().unwrap_or(Path::new("")).join(file);
+            if file.exists() {
+                config.permissions = Arc::new(Permissions::load(&file)?);
+            } else {
+                error!("permissions file {} not found, players have no permissions", file.display());
+            }
+        }
+
+        Some(config)
     }
 
     pub fn get_server_by_name(&self, name: &str) -> Option<ServerInfo> {
@@ -176,9 +1097,79 @@ impl ProxyConfig {
                 return Some(server.clone());
             }
         }
+
+        for server in &self.servers {
+            if server.has_name(name) {
+                return Some(server.clone());
+            }
+        }
+
         None
     }
 
+    /// Problems of config that would make routing fail, like references to missing servers
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if self.servers.is_empty() {
+            problems.push("no servers configured".to_string());
+        }
+
+        for server in &self.servers {
+            for name in &server.try_servers {
+                if self.get_server_by_name(name).is_none() {
+                    problems.push(format!("server {} tries unknown server {}", server.name, name));
+                }
+            }
+            if server.strategy == RoutingStrategy::Nearest && server.location.is_none() {
+                problems.push(format!("server {} uses nearest strategy without location", server.name));
+            }
+        }
+
+        for listener in &self.listeners {
+            if listener.port().is_none() {
+                problems.push(format!("listener {} has no port", listener.host));
+            }
+            if let Some(name) = listener.server.as_ref().filter(|o| self.get_server_by_name(o).is_none()) {
+                problems.push(format!("listener {} routes to unknown server {}", listener.host, name));
+            }
+        }
+
+        // targets with capture groups are known only for actual addresses
+        for route in self.routes.iter().filter(|o| !o.server.contains('$')) {
+            if self.get_server_by_name(&route.server).is_none() {
+                problems.push(format!("route {} targets unknown server {}", route.pattern, route.server));
+            }
+        }
+
+        if let Some(name) = self.connect_retry.as_ref().and_then(|o| o.fallback.as_ref()) {
+            if self.get_server_by_name(name).is_none() {
+                problems.push(format!("connect_retry fallback is unknown server {}", name));
+            }
+        }
+
+        if let Some(tolerance) = &self.snapshot_tolerance {
+            if self.get_server_by_name(&tolerance.server).is_none() {
+                problems.push(format!("snapshot_tolerance server is unknown server {}", tolerance.server));
+            }
+        }
+
+        problems
+    }
+
+    /// Additional listener bound to port
+    pub fn listener(&self, port: u16) -> Option<&ListenerInfo> {
+        self.listeners.iter().find(|o| o.port() == Some(port))
+    }
+
+    /// Server for handshake address, routing rules are checked first and then server domains
+    pub fn route(&self, address: &str) -> Option<ServerInfo> {
+        self.routes.iter()
+            .filter_map(|o| o.target(address))
+            .find_map(|o| self.get_server_by_name(&o))
+            .or_else(|| self.get_server_by_domain(address))
+    }
+
     pub fn get_server_by_domain(&self, domain: &str) -> Option<ServerInfo> {
         for server in &self.servers {
             if server.domains.contains(&domain.to_string()) {
@@ -1,37 +1,38 @@
-use std::{net::{SocketAddr, TcpStream}, sync::{Arc, Mutex}, thread};
+use std::{collections::VecDeque, net::{SocketAddr, TcpStream}, sync::{atomic::{AtomicBool, Ordering}, Arc, Weak}, thread, time::{Duration, Instant}};
 
-use bytebuffer::ByteBuffer;
 use ignore_result::Ignore;
-use log::info;
-use ring::hmac;
+use log::{debug, error, info, warn};
+use md5::{Digest, Md5};
+use parking_lot::{Mutex, RwLock};
+use serde_json::Value;
 use rust_mc_proto::{DataBufferReader, DataBufferWriter, MCConnTcp, Packet, ProtocolError};
+use tracing::{field, info_span, Span};
 use uuid::Uuid;
 
-use super::{config::{PlayerForwarding, ProxyConfig, ServerInfo}, error::{AsProxyResult, ProxyError}};
+use super::{chat, command::CommandSender, component::Component, config::{PlayerForwarding, ProxyConfig, ServerInfo}, error::{AsProxyResult, ErrorContext, ProxyError}, event::{Event, LoginDisconnectEvent, PlayerKickEvent}, forwarding::{self, ForwardedPlayer, PlayerIdentity, ProfileProperty, MAX_HOPS, MEEXPROX_CHANNEL, VELOCITY_CHANNEL}, protocol::{self, Handshake, HandshakeAddress, ProtocolPackets}, query, record::ProxyEventRecord, placeholders::PlaceholderContext, packets::{ChatVisibility, ClientSettings, LoginStart, PacketContext, TypedPacket}, sanitizer::Sanitizer, sidebar::{self, Sidebar, SidebarConflict, SidebarState, SIDEBAR_SLOT}, split::SplitConn, stats::PacketDirection, throttle::Throttle, transfer, MeexProx};
+
+/// Actions of player info update packet with only latency set
+const PLAYER_INFO_UPDATE_LATENCY: u8 = 0x10;
 
 #[derive(Clone, Debug)]
 pub struct LoginInfo {
-    protocol_version: u16,
-    server_address: String,
+    /// As sent by client
+    protocol_version: i32,
+    server_address: HandshakeAddress,
     server_port: u16,
-    name: String,
-    uuid: Uuid,
+    identity: PlayerIdentity,
     shared_secret: Option<Vec<u8>>,
     verify_token: Option<Vec<u8>>
 }
 
 impl LoginInfo {
-    pub fn write(&self, _config: &ProxyConfig, stream: &mut MCConnTcp) -> Result<(), ProtocolError> {
-        stream.write_packet(&Packet::build(0x00, |p| {
-            p.write_u16_varint(self.protocol_version)?;
-            p.write_string(&self.server_address)?;
-            p.write_short(self.server_port as i16)?;
-            p.write_u8_varint(2)
-        })?)?;
+    pub fn write(&self, server: &ServerInfo, stream: &mut MCConnTcp) -> Result<(), ProtocolError> {
+        let server_address = self.identity.handshake_address(&self.server_address, server);
+        stream.write_packet(&login_handshake(self.protocol_version, &server_address, self.server_port)?)?;
 
         stream.write_packet(&Packet::build(0x00, |p| {
-            p.write_string(&self.name)?;
-            p.write_uuid(&self.uuid)
+            p.write_string(&self.identity.name)?;
+            p.write_uuid(&self.identity.uuid)
         })?)?;
 
         loop {
@@ -53,6 +54,29 @@ impl LoginInfo {
                     let compression = Some(packet.read_usize_varint()?);
                     stream.set_compression(compression);
                 }
+                0x04 => {
+                    let message_id = packet.read_isize_varint()?;
+                    let channel = packet.read_string()?;
+
+                    let response = match (channel.as_str(), &server.player_forwarding) {
+                        (VELOCITY_CHANNEL, PlayerForwarding::Velocity(secret)) => {
+                            Some(self.identity.velocity_response(velocity_version(&mut packet)?, secret)?)
+                        }
+                        (MEEXPROX_CHANNEL, PlayerForwarding::Meexprox(secret)) => {
+                            Some(self.identity.meexprox_response(&remaining(&mut packet)?, secret)?)
+                        }
+                        _ => None
+                    };
+
+                    stream.write_packet(&Packet::build(0x02, |p| {
+                        p.write_isize_varint(message_id)?;
+                        p.write_boolean(response.is_some())?;
+                        match &response {
+                            Some(data) => p.write_bytes(data),
+                            None => Ok(())
+                        }
+                    })?)?;
+                }
                 _ => {}
             }
         }
@@ -63,221 +87,1134 @@ impl LoginInfo {
     }
 }
 
+/// Handshake packet of login connection to backend
+fn login_handshake(protocol_version: i32, server_address: &str, server_port: u16) -> Result<Packet, ProtocolError> {
+    Packet::build(0x00, |p| {
+        p.write_i32_varint(protocol_version)?;
+        p.write_string(server_address)?;
+        p.write_unsigned_short(server_port)?;
+        p.write_u8_varint(2)
+    })
+}
+
+/// Offline uuid of player whose login start was not parsed, the same as backends in offline mode give
+/// (`UUID.nameUUIDFromBytes` of `OfflinePlayer:<name>`)
+fn name_uuid(name: &str) -> Uuid {
+    let hash = Md5::digest(format!("OfflinePlayer:{}", name).as_bytes());
+    uuid::Builder::from_md5_bytes(hash.into()).into_uuid()
+}
+
+/// Limits next read of client to time left until deadline of handshake, so client sending
+/// every packet just before timeout can't stretch handshake and login
+pub(crate) fn limit_read(stream: &TcpStream, deadline: Option<Instant>) -> Result<(), ProxyError> {
+    let Some(deadline) = deadline else {
+        return Ok(());
+    };
+
+    let left = deadline.saturating_duration_since(Instant::now());
+    if left.is_zero() {
+        return Err(ProxyError::HandshakeTimeout);
+    }
+    stream.set_read_timeout(Some(left)).ignore();
+
+    Ok(())
+}
+
+/// Forwarding version requested by backend in velocity:player_info, 1 if not set
+fn velocity_version(packet: &mut Packet) -> Result<u8, ProtocolError> {
+    if packet.buffer().len() - packet.buffer().get_rpos() == 1 {
+        packet.read_byte()
+    } else {
+        Ok(1)
+    }
+}
+
+/// Rest of packet data after read fields
+fn remaining(packet: &mut Packet) -> Result<Vec<u8>, ProtocolError> {
+    let len = packet.buffer().len() - packet.buffer().get_rpos();
+    packet.read_bytes(len)
+}
+
+/// Sends login plugin request to upstream proxy, returns response data or None if it is not understood
+fn request_forwarding(client_conn: &mut MCConnTcp, channel: &str, data: &[u8]) -> Result<Option<Vec<u8>>, ProtocolError> {
+    client_conn.write_packet(&Packet::build(0x04, |p| {
+        p.write_isize_varint(0)?;
+        p.write_string(channel)?;
+        p.write_bytes(data)
+    })?)?;
+
+    let mut packet = client_conn.read_packet()?;
+    if packet.id() != 0x02 || packet.read_isize_varint()? != 0 || !packet.read_boolean()? {
+        return Ok(None);
+    }
+
+    remaining(&mut packet).map(Some)
+}
+
+/// Asks upstream velocity proxy for player identity, None if it is not sent or not signed with secret
+fn read_velocity_identity(client_conn: &mut MCConnTcp, secret: &str) -> Result<Option<PlayerIdentity>, ProtocolError> {
+    Ok(request_forwarding(client_conn, VELOCITY_CHANNEL, &[1])?
+        .and_then(|o| PlayerIdentity::from_velocity(&o, secret)))
+}
+
+/// Asks upstream meexprox proxy for player identity with hops it came through
+fn read_meexprox_identity(client_conn: &mut MCConnTcp, secret: &str) -> Result<Option<PlayerIdentity>, ProtocolError> {
+    let nonce = forwarding::forwarding_nonce();
+    Ok(request_forwarding(client_conn, MEEXPROX_CHANNEL, &nonce)?
+        .and_then(|o| PlayerIdentity::from_meexprox(&o, &nonce, secret)))
+}
+
+/// Requests cookie stored on client before transfer, None if client has no cookie with the key
+fn request_cookie(client_conn: &mut MCConnTcp, key: &str) -> Result<Option<Vec<u8>>, ProtocolError> {
+    client_conn.write_packet(&Packet::build(0x05, |p| p.write_string(key))?)?;
+
+    let mut packet = client_conn.read_packet()?;
+    if packet.id() != 0x04 || packet.read_string()? != key || !packet.read_boolean()? {
+        return Ok(None);
+    }
+
+    let len = packet.read_usize_varint()?;
+    packet.read_bytes(len).map(Some)
+}
+
+/// Kicks player in login because of invalid incoming forwarding
+fn deny_forwarding(meexprox: &MeexProx, client_conn: &mut MCConnTcp, error: &'static str, reason: &str) -> ProxyError {
+    warn!("incoming forwarding rejected: {}", error);
+    meexprox.report_error(None, None, None, &ProxyError::Forwarding(error));
+    if let Ok(packet) = Packet::build(0x00, |p| p.write_string(&Component::text(reason).to_json().to_string())) {
+        client_conn.write_packet(&packet).ignore();
+    }
+    client_conn.close();
+    ProxyError::LoginDenied
+}
+
+/// Connection state of client, tracked to know when play packets are sent
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClientState {
+    Login,
+    Configuration,
+    Play
+}
+
+impl ClientState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClientState::Login => "login",
+            ClientState::Configuration => "configuration",
+            ClientState::Play => "play"
+        }
+    }
+}
+
+/// Keep alives relayed to client, to measure latency between proxy and client
+#[derive(Default)]
+struct Latency {
+    /// Keep alive ids sent by backend and when they were relayed
+    pending: VecDeque<(i64, Instant)>,
+    last: Option<Duration>
+}
+
+impl Latency {
+    fn sent(&mut self, id: i64) {
+        // client answers only the last one if several are pending
+        if self.pending.len() >= 4 {
+            self.pending.pop_front();
+        }
+        self.pending.push_back((id, Instant::now()));
+    }
+
+    fn received(&mut self, id: i64) {
+        if let Some(index) = self.pending.iter().position(|o| o.0 == id) {
+            self.last = Some(self.pending[index].1.elapsed());
+            self.pending.drain(..=index);
+        }
+    }
+}
+
+/// Brand of client if packet is `minecraft:brand` plugin message
+fn read_brand(mut packet: Packet) -> Option<String> {
+    if packet.read_string().ok()? != "minecraft:brand" {
+        return None;
+    }
+    packet.read_string().ok()
+}
+
+/// If command line starts with proxy command that sender is allowed to use
+fn is_proxy_command(meexprox: &Arc<MeexProx>, sender: &CommandSender, line: &str) -> bool {
+    line.split_whitespace().next()
+        .and_then(|o| meexprox.commands().get(o))
+        .is_some_and(|o| sender.can_use(meexprox, o.as_ref()))
+}
+
+/// Executes proxy command sent by player, returns false if it has to be sent to backend
+fn dispatch_command(meexprox: &Arc<MeexProx>, name: &str, uuid: Uuid, mut packet: Packet) -> bool {
+    let Ok(line) = packet.read_string() else {
+        return false;
+    };
+
+    let sender = CommandSender::Player(name.to_string());
+    if !is_proxy_command(meexprox, &sender, &line) {
+        return false;
+    }
+
+    info!("Player {} issued proxy command: /{}", name, line);
+
+    for message in meexprox.execute_command(&sender, &line) {
+        if let Some(player) = meexprox.get_players().iter().find(|o| o.uuid == uuid) {
+            player.send_message(&message).ignore();
+        }
+    }
+
+    true
+}
+
+/// Answers suggestions request for arguments of proxy command, returns false if it has to be sent to backend.
+/// Command names are completed by client from command tree of backend
+fn dispatch_suggestions(meexprox: &Arc<MeexProx>, name: &str, uuid: Uuid, protocol_version: u16, mut packet: Packet) -> bool {
+    let (Ok(transaction), Ok(text)) = (packet.read_i32_varint(), packet.read_string()) else {
+        return false;
+    };
+    let Some(line) = text.strip_prefix('/').filter(|o| o.contains(' ')) else {
+        return false;
+    };
+
+    let sender = CommandSender::Player(name.to_string());
+    if !is_proxy_command(meexprox, &sender, line) {
+        return false;
+    }
+
+    let (start, suggestions) = meexprox.commands().complete(meexprox, line);
+    // client counts offsets in characters, including the slash
+    let start = line[..start].chars().count() + 1;
+    let length = line.chars().count() + 1 - start;
+
+    let Some(packets) = ProtocolPackets::get(protocol_version) else {
+        return true;
+    };
+    let response = Packet::build(packets.command_suggestions, |p| {
+        p.write_i32_varint(transaction)?;
+        p.write_usize_varint(start)?;
+        p.write_usize_varint(length)?;
+        p.write_usize_varint(suggestions.len())?;
+        for suggestion in &suggestions {
+            p.write_string(suggestion)?;
+            p.write_boolean(false)?; // no tooltip
+        }
+        Ok(())
+    });
+
+    if let (Ok(response), Some(player)) = (response, meexprox.get_players().iter().find(|o| o.uuid == uuid)) {
+        player.write_priority_client_packet(&response).ignore();
+    }
+
+    true
+}
+
 pub struct Player {
-    client_conn: Arc<Mutex<MCConnTcp>>,
-    server_conn: Arc<Mutex<MCConnTcp>>,
+    meexprox: Weak<MeexProx>,
+    client_conn: Arc<SplitConn>,
+    server_conn: Arc<SplitConn>,
     login_info: Option<LoginInfo>,
+    sanitizer: Option<Sanitizer>,
+    span: Span,
     pub name: String,
     pub uuid: Uuid,
-    pub server: Option<ServerInfo>,
+    server: RwLock<Option<ServerInfo>>,
+    state: Arc<RwLock<ClientState>>,
+    /// Packets of player are logged when set
+    debug: Arc<AtomicBool>,
+    latency: Arc<Mutex<Latency>>,
+    /// Set once first play login is relayed, join message is sent then
+    joined: Arc<AtomicBool>,
+    /// Player sends and receives global chat when set
+    global_chat: Arc<AtomicBool>,
+    /// Sent by client in `minecraft:brand` plugin message
+    client_brand: Arc<RwLock<Option<String>>>,
+    /// Last client information sent by client, sent again to every next backend
+    client_settings: Arc<RwLock<Option<ClientSettings>>>,
+    /// Name of the last private message partner, used by `/reply`
+    reply_target: RwLock<Option<String>>,
+    sidebar: Arc<Mutex<SidebarState>>,
+    /// Clientbound limits, kept on player so switching servers doesn't refill the burst
+    clientbound_throttle: Arc<Mutex<Option<Throttle>>>,
+    /// Profile properties received from upstream proxy
+    pub properties: Vec<ProfileProperty>,
+    /// Ids of proxies player came through, this one is the last
+    pub hops: Vec<String>,
+    /// Handshake sent by client, before routing changed its address
+    handshake: Handshake,
+    /// When login of player was accepted
+    connected_at: Instant,
+    /// When player joined its current server
+    server_since: RwLock<Instant>,
+    /// Payload of verified cookie stored on player by proxy it was transferred from
+    transfer_payload: Option<Vec<u8>>,
     pub protocol_version: u16,
     pub addr: SocketAddr
 }
 
 impl Player {
     pub fn read(
-        _config: &ProxyConfig,
-        protocol_version: u16, 
-        server_address: String, 
-        server_port: u16, 
-        server: ServerInfo,
+        meexprox: &Arc<MeexProx>,
+        handshake: Handshake,
+        mut server: ServerInfo,
         addr: SocketAddr,
+        forwarded: Option<ForwardedPlayer>,
         mut client_conn: MCConnTcp, 
         mut server_conn: MCConnTcp
     ) -> Result<Player, ProxyError> {
-        let mut packet = client_conn.read_packet().as_proxy()?;
+        let Handshake { protocol_version, raw_protocol, address: server_address, port: server_port, deadline, .. } = handshake.clone();
+        limit_read(client_conn.get_ref(), deadline)?;
+        let packet = client_conn.read_packet().as_proxy()?;
 
-        if packet.id() != 0x00 { return Err(ProxyError::LoginPacket); }
+        // login of snapshots is not parsed strictly, it may change before release
+        let tolerant = meexprox.config().snapshot_tolerance.is_some() && !protocol::is_known_protocol(raw_protocol);
 
-        let name = packet.read_string().as_proxy()?;
-        let uuid = packet.read_uuid().as_proxy()?;
-
-        server_conn.write_packet(&packet).as_proxy()?;
+        if packet.id() != 0x00 {
+            if !tolerant {
+                return Err(ProxyError::LoginPacket(packet.id()));
+            }
+            debug!("login start of snapshot {} has unexpected id 0x{:02X}", raw_protocol, packet.id());
+        }
+        let start = match LoginStart::decode(&mut packet.clone(), protocol_version) {
+            // name is first field in every version
+            Err(_) if tolerant => {
+                let name = packet.clone().read_string().as_proxy()?;
+                LoginStart { uuid: name_uuid(&name), name }
+            }
+            start => start.as_proxy()?
+        };
+        let context = PacketContext { meexprox, name: &start.name, uuid: start.uuid, protocol_version };
+        if !meexprox.packet_handlers().handle(&context, ClientState::Login, PacketDirection::Serverbound, &packet) {
+            client_conn.close();
+            return Err(ProxyError::LoginDenied);
+        }
 
-        let mut player = Player {
-            addr,
-            client_conn: Arc::new(Mutex::new(client_conn)),
-            server_conn: Arc::new(Mutex::new(server_conn)),
-            login_info: None,
-            name: name.clone(),
-            uuid,
-            server: Some(server.clone()),
-            protocol_version
+        let mut identity = PlayerIdentity {
+            name: start.name,
+            uuid: start.uuid,
+            ip: addr.ip(),
+            properties: Vec::new(),
+            hops: Vec::new()
         };
 
-        let mut shared_secret = None;
-        let mut verify_token = None;
+        // identity from upstream proxy replaces the one of connection
+        if let Some(forwarded) = forwarded {
+            identity.ip = forwarded.ip;
+            identity.uuid = forwarded.uuid;
+            identity.properties = forwarded.properties;
+        }
 
-        loop {
-            let mut packet = player.read_server_packet()?;
-            match packet.id() {
-                0x01 => {
-                    player.write_client_packet(&packet)?;
-                    let mut packet = player.read_client_packet()?;
-                    let i = packet.read_usize_varint().as_proxy()?;
-                    shared_secret = Some(packet.read_bytes(i).as_proxy()?);
-                    let i = packet.read_usize_varint().as_proxy()?;
-                    verify_token = Some(packet.read_bytes(i).as_proxy()?);
-                    player.write_server_packet(&packet)?;
-                }
-                0x02 => {
-                    player.write_client_packet(&packet)?;
-                    // player.write_server_packet(&player.read_client_packet()?)?;
-                    break;
+        let config = meexprox.config();
+        match &config.incoming_forwarding {
+            PlayerForwarding::Velocity(secret) => {
+                limit_read(client_conn.get_ref(), deadline)?;
+                identity = read_velocity_identity(&mut client_conn, secret).as_proxy()?.ok_or_else(|| deny_forwarding(
+                    meexprox, &mut client_conn,
+                    "invalid velocity forwarding data",
+                    "Invalid forwarding data, enable modern forwarding in your proxy"
+                ))?;
+            }
+            PlayerForwarding::Meexprox(secret) => {
+                limit_read(client_conn.get_ref(), deadline)?;
+                identity = read_meexprox_identity(&mut client_conn, secret).as_proxy()?.ok_or_else(|| deny_forwarding(
+                    meexprox, &mut client_conn,
+                    "invalid meexprox forwarding data",
+                    "Invalid forwarding data, check forwarding secret of your proxy"
+                ))?;
+
+                if identity.hops.contains(&config.proxy_id) || identity.hops.len() >= MAX_HOPS {
+                    return Err(deny_forwarding(meexprox, &mut client_conn, "proxy loop detected", "Proxy loop detected"));
                 }
-                0x03 => {
-                    player.write_client_packet(&packet)?;
-                    let compression = Some(packet.read_usize_varint().as_proxy()?);
-                    player.set_server_compression(compression);
-                    player.set_client_compression(compression);
+            }
+            _ => {}
+        }
+        identity.hops.push(config.proxy_id.clone());
+
+        if identity.hops.len() > 1 {
+            info!("Player {} came through proxies {}", identity.name, identity.hops.join(" -> "));
+        }
+
+        // cookies can be requested in login state only, so before backend login
+        let transfer_payload = match (&config.transfer, handshake.is_transfer()) {
+            (Some(transfer), true) => {
+                limit_read(client_conn.get_ref(), deadline)?;
+                let payload = request_cookie(&mut client_conn, &transfer.cookie).as_proxy()?
+                    .and_then(|o| transfer::verify(&transfer.secret, &transfer.cookie, identity.uuid, &o, transfer.max_age));
+                if payload.is_none() && transfer.required {
+                    info!("Player {} login denied: invalid transfer cookie", identity.name);
+                    client_conn.write_packet(&Packet::build(0x00, |p| {
+                        p.write_string(&Component::text(transfer.message.clone()).to_json().to_string())
+                    }).as_proxy()?).as_proxy()?;
+                    client_conn.close();
+                    return Err(ProxyError::LoginDenied);
                 }
-                0x04 => { // login plugin request
-                    let message_id = packet.read_isize_varint().as_proxy()?;
-                    let channel = packet.read_string().as_proxy()?;
-
-                    if channel == "velocity:player_info" {
-                        if let PlayerForwarding::Velocity(secret) = &server.player_forwarding {
-                            let version: u8 = if packet.buffer().len() - packet.buffer().get_rpos() == 1 {
-                                packet.read_byte().as_proxy()?
-                            } else {
-                                1
-                            };
-
-                            let response = Packet::build(0x02, |p| {
-                                p.write_isize_varint(message_id)?;
-                                p.write_boolean(true)?;
+                payload
+            }
+            _ => None
+        };
 
-                                let mut buf = ByteBuffer::new();
-                                DataBufferWriter::write_u8_varint(&mut buf, version)?;
-                                DataBufferWriter::write_string(&mut buf, &addr.to_string())?;
-                                DataBufferWriter::write_uuid(&mut buf, &uuid)?;
-                                DataBufferWriter::write_string(&mut buf, &name)?;
-                                DataBufferWriter::write_u8_varint(&mut buf, 0)?; // properties // maybe fix later
-                                let buf = buf.as_bytes();
+        let name = identity.name.clone();
+        let uuid = identity.uuid;
+        let addr = SocketAddr::new(identity.ip, addr.port());
 
-                                let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
-                                let sig = hmac::sign(&key, &buf);
+        // login errors are logged with player name
+        let context = name.clone();
+        (move || -> Result<Player, ProxyError> {
+            client_conn.get_ref().set_read_timeout(None).ignore();
 
-                                p.write_bytes(sig.as_ref())?;
-                                p.write_bytes(buf.as_ref())?;
+            // invalid names are denied first, so they never reach storage or screeners
+            let denied = (!config.username.is_valid(&name)).then(|| config.username.message.clone())
+                .or_else(|| meexprox.check_login(&name, uuid))
+                .or_else(|| meexprox.screen_connection(addr.ip(), &name))
+                .or_else(|| meexprox.check_duplicate_session(&name, uuid));
+            if let Some(reason) = denied {
+                let context = PlaceholderContext::new().player(&name).uuid(uuid).server(&server.name).domain(handshake.virtual_host());
+                let reason = meexprox.render(&reason, &context);
+                info!("Player {} login denied: {}", name, reason);
+                client_conn.write_packet(&Packet::build(0x00, |p| {
+                    p.write_string(&Component::text(reason).to_json().to_string())
+                }).as_proxy()?).as_proxy()?;
+                client_conn.close();
+                return Err(ProxyError::LoginDenied);
+            }
+
+            // connection span is entered by accept_client
+            let span = Span::current();
+            span.record("player", name.as_str());
+            span.record("uuid", field::display(uuid));
 
-                                Ok(())
-                            }).as_proxy()?;
+            // handshake is sent only now, when forwarding data of backend is known
+            server_conn.write_packet(&login_handshake(
+                raw_protocol,
+                &identity.handshake_address(&server_address, &server),
+                server_port
+            ).as_proxy()?).as_proxy()?;
+            server_conn.write_packet(&packet).as_proxy()?;
+            let login_start = packet;
+
+            let client_conn = Arc::new(SplitConn::new(client_conn)?);
+            if let Some(batching) = meexprox.config().write_batching {
+                client_conn.start_batching(batching);
+            }
 
-                            player.write_server_packet(&response)?;
+            let mut player = Player {
+                meexprox: Arc::downgrade(meexprox),
+                addr,
+                client_conn,
+                server_conn: Arc::new(SplitConn::new(server_conn)?),
+                login_info: None,
+                sanitizer: meexprox.config().sanitizer,
+                span,
+                name: name.clone(),
+                uuid,
+                server: RwLock::new(Some(server.clone())),
+                state: Arc::new(RwLock::new(ClientState::Login)),
+                debug: Arc::new(AtomicBool::new(false)),
+                latency: Arc::new(Mutex::new(Latency::default())),
+                joined: Arc::new(AtomicBool::new(false)),
+                global_chat: Arc::new(AtomicBool::new(true)),
+                client_brand: Arc::new(RwLock::new(None)),
+                client_settings: Arc::new(RwLock::new(None)),
+                reply_target: RwLock::new(None),
+                sidebar: Arc::new(Mutex::new(SidebarState::default())),
+                clientbound_throttle: Arc::new(Mutex::new(config.throttle.map(|o| Throttle::new(&o.clientbound, o.burst)))),
+                properties: identity.properties.clone(),
+                hops: identity.hops.clone(),
+                handshake,
+                connected_at: Instant::now(),
+                server_since: RwLock::new(Instant::now()),
+                transfer_payload,
+                protocol_version
+            };
+
+            let mut shared_secret = None;
+            let mut verify_token = None;
+
+            loop {
+                let mut packet = player.read_server_packet()?;
+                match packet.id() {
+                    0x00 => { // login disconnect
+                        let reason = packet.read_string().as_proxy()?;
+                        let reason = serde_json::from_str(&reason).unwrap_or(Value::String(reason));
+
+                        let mut event = LoginDisconnectEvent::builder()
+                            .name(name.clone())
+                            .uuid(uuid)
+                            .server(server.name.clone())
+                            .reason(reason)
+                            .build();
+                        // failed listener doesn't redirect, reason of backend is still shown
+                        if let Err(e) = meexprox.trigger_event(&mut event) {
+                            meexprox.report_error(Some(&name), Some(&server.name), None, &e);
+                        }
+
+                        if let Some(target) = event.redirect().as_ref().and_then(|o| meexprox.config().get_server_by_name(o)) {
+                            info!("Player {} redirected from {} to {} on login disconnect", name, server.name, target.name);
+                            let mut server_conn = target.connect()
+                                .map(MCConnTcp::new)
+                                .map_err(|source| ProxyError::ServerConnect { server: target.name.clone(), source })?;
+                            server_conn.write_packet(&login_handshake(
+                                raw_protocol,
+                                &identity.handshake_address(&server_address, &target),
+                                server_port
+                            ).as_proxy()?).as_proxy()?;
+                            server_conn.write_packet(&login_start).as_proxy()?;
+
+                            player.server_conn.close();
+                            player.server_conn.replace(server_conn)?;
+                            *player.server.write() = Some(target.clone());
+                            *player.server_since.write() = Instant::now();
+                            player.span.record("server", target.name.as_str());
+                            server = target;
                             continue;
                         }
+
+                        info!("Server {} denied login of player {}: {}", server.name, name, event.reason());
+                        if !event.is_cancelled() {
+                            player.write_client_packet(&Packet::build(0x00, |p| {
+                                p.write_string(&event.reason().to_string())
+                            }).as_proxy()?).ignore();
+                        }
+                        player.close();
+                        return Err(ProxyError::LoginDenied);
                     }
+                    0x01 => {
+                        player.write_client_packet(&packet)?;
+                        let mut packet = player.read_client_packet()?;
+                        let i = packet.read_usize_varint().as_proxy()?;
+                        shared_secret = Some(packet.read_bytes(i).as_proxy()?);
+                        let i = packet.read_usize_varint().as_proxy()?;
+                        verify_token = Some(packet.read_bytes(i).as_proxy()?);
+                        player.write_server_packet(&packet)?;
+                    }
+                    0x02 => {
+                        player.write_client_packet(&packet)?;
+                        if ProtocolPackets::get(protocol_version).is_none() {
+                            // no configuration state before 1.20.2
+                            *player.state.write() = ClientState::Play;
+                        }
+                        // player.write_server_packet(&player.read_client_packet()?)?;
+                        break;
+                    }
+                    0x03 => {
+                        player.write_client_packet(&packet)?;
+                        let compression = Some(packet.read_usize_varint().as_proxy()?);
+                        player.set_server_compression(compression);
+                        player.set_client_compression(compression);
+                    }
+                    0x04 => { // login plugin request
+                        let message_id = packet.read_isize_varint().as_proxy()?;
+                        let channel = packet.read_string().as_proxy()?;
+
+                        let response = match (channel.as_str(), &server.player_forwarding) {
+                            (VELOCITY_CHANNEL, PlayerForwarding::Velocity(secret)) => {
+                                let version = velocity_version(&mut packet).as_proxy()?;
+                                Some(identity.velocity_response(version, secret).as_proxy()?)
+                            }
+                            (MEEXPROX_CHANNEL, PlayerForwarding::Meexprox(secret)) => {
+                                Some(identity.meexprox_response(&remaining(&mut packet).as_proxy()?, secret).as_proxy()?)
+                            }
+                            _ => None
+                        };
+
+                        if let Some(data) = response {
+                            player.write_server_packet(&Packet::build(0x02, |p| {
+                                p.write_isize_varint(message_id)?;
+                                p.write_boolean(true)?;
+                                p.write_bytes(&data)
+                            }).as_proxy()?)?;
+                            continue;
+                        }
 
-                    player.write_client_packet(&packet)?;
-                    player.write_server_packet(&player.read_client_packet()?)?;
+                        player.write_client_packet(&packet)?;
+                        player.write_server_packet(&player.read_client_packet()?)?;
+                    }
+                    id if tolerant => {
+                        // new login packets are requests answered by client, like cookie request is
+                        debug!("relaying unknown login packet 0x{:02X} of snapshot client", id);
+                        player.write_client_packet(&packet)?;
+                        player.write_server_packet(&player.read_client_packet()?)?;
+                    }
+                    _ => {
+                        return Err(ProxyError::LoginPacket(packet.id()));
+                    },
                 }
-                _ => {
-                    return Err(ProxyError::LoginPacket);
-                },
             }
-        }
 
-        player.login_info = Some(LoginInfo {
-            protocol_version,
-            server_address,
-            server_port,
-            name,
-            uuid,
-            shared_secret,
-            verify_token
-        });
+            player.login_info = Some(LoginInfo {
+                protocol_version: raw_protocol,
+                server_address,
+                server_port,
+                identity,
+                shared_secret,
+                verify_token
+            });
 
-        player.client_recv_loop();
-        player.server_recv_loop();
+            player.client_recv_loop();
+            player.server_recv_loop();
 
-        Ok(player)
+            Ok(player)
+        })().with_player(&context)
     }
 
     pub fn client_recv_loop(&self) {
-        let mut client: rust_mc_proto::MinecraftConnection<TcpStream> = self.client_conn.clone().lock().unwrap().try_clone().unwrap();
+        let mut client = self.client_conn.try_clone_reader().unwrap();
         let server = self.server_conn.clone();
         let name = self.name.clone();
+        let uuid = self.uuid;
+        let sanitizer = self.sanitizer;
+        let protocol_version = self.protocol_version;
+        let raw_protocol = self.handshake.raw_protocol;
+        let span = info_span!(parent: &self.span, "serverbound");
+        let meexprox = self.meexprox.upgrade().unwrap();
+        let packets = ProtocolPackets::get(protocol_version);
+        let state = self.state.clone();
+        let debug = self.debug.clone();
+        let latency = self.latency.clone();
+        let global_chat = self.global_chat.clone();
+        let client_brand = self.client_brand.clone();
+        let client_settings = self.client_settings.clone();
+        let addr = self.addr;
+        let server_name = self.server().map(|o| o.name).unwrap_or_default();
+        let throttle_config = meexprox.config().throttle;
+        let mut throttle = throttle_config.map(|o| Throttle::new(&o.serverbound, o.burst));
 
-        thread::spawn(move || {
-            info!("Player {} connected", name);
-            while let Ok(packet) = client.read_packet() {
-                while !server.lock().unwrap().is_alive() {}
-                server.lock().unwrap().write_packet(&packet).ignore();
+        let proxy = meexprox.clone();
+        let spawned = proxy.workers().spawn_relay(move || {
+            let _enter = span.enter();
+            info!("Player {} connected with {}", name, protocol::raw_version_name(raw_protocol));
+            meexprox.online().joined(addr, uuid, &server_name);
+            loop {
+                let packet = match client.read_packet().as_proxy() {
+                    Ok(packet) => packet,
+                    Err(ProxyError::ConnectionClosed) => break,
+                    Err(e) => {
+                        meexprox.report_error(Some(&name), None, Some(PacketDirection::Serverbound), &e);
+                        break;
+                    }
+                };
+                if let Some(Err(e)) = sanitizer.map(|o| o.check_serverbound(protocol_version, &packet)) {
+                    warn!("Player {} sent blocked packet: {}", name, e);
+                    meexprox.report_error(Some(&name), None, Some(PacketDirection::Serverbound), &e);
+                    break;
+                }
+                if let Some(wait) = throttle.as_mut().map(|o| o.take(packet.buffer().len())).filter(|o| !o.is_zero()) {
+                    if throttle_config.is_some_and(|o| o.kick) {
+                        warn!("Player {} exceeded rate limit", name);
+                        meexprox.report_error(Some(&name), None, Some(PacketDirection::Serverbound), &ProxyError::RateLimited);
+                        // players in login are not listed yet, their connection is just closed
+                        let player = meexprox.get_players().iter().find(|o| o.uuid == uuid).cloned();
+                        if let Some(player) = player {
+                            player.kick("Rate limit exceeded".to_string()).ignore();
+                        }
+                        break;
+                    }
+                    // client is slowed down, it can't send faster than relay reads
+                    thread::sleep(wait);
+                }
+                meexprox.stats().add_serverbound(*state.read(), packet.id(), packet.buffer().len());
+                if debug.load(Ordering::Relaxed) {
+                    info!("Player {} sent packet 0x{:02X} ({} bytes) in {:?} state", name, packet.id(), packet.buffer().len(), *state.read());
+                }
+                let current = *state.read();
+                if let Some(packets) = &packets {
+                    let id = packet.id();
+                    match current {
+                        ClientState::Login if id == 0x03 => *state.write() = ClientState::Configuration,
+                        ClientState::Configuration if id == packets.finish_configuration_ack => *state.write() = ClientState::Play,
+                        ClientState::Play if id == packets.configuration_ack => *state.write() = ClientState::Configuration,
+                        ClientState::Configuration if id == packets.configuration_plugin_message => {
+                            if let Some(brand) = read_brand(packet.clone()) {
+                                *client_brand.write() = Some(brand);
+                            }
+                        }
+                        ClientState::Configuration if id == packets.configuration_client_information => {
+                            if let Ok(settings) = ClientSettings::decode(&mut packet.clone()) {
+                                *client_settings.write() = Some(settings);
+                            }
+                        }
+                        ClientState::Play if id == packets.client_information => {
+                            if let Ok(settings) = ClientSettings::decode(&mut packet.clone()) {
+                                *client_settings.write() = Some(settings);
+                            }
+                        }
+                        ClientState::Play if id == packets.plugin_message => {
+                            if let Some(brand) = read_brand(packet.clone()) {
+                                *client_brand.write() = Some(brand);
+                            }
+                        }
+                        ClientState::Play if id == packets.keep_alive_response => {
+                            if let Ok(id) = packet.clone().read_long() {
+                                latency.lock().received(id);
+                            }
+                        }
+                        ClientState::Play if (id == packets.chat_command || Some(id) == packets.signed_chat_command)
+                            && dispatch_command(&meexprox, &name, uuid, packet.clone()) => continue,
+                        ClientState::Play if id == packets.command_suggestions_request
+                            && dispatch_suggestions(&meexprox, &name, uuid, protocol_version, packet.clone()) => continue,
+                        ClientState::Play if id == packets.chat_message && global_chat.load(Ordering::Relaxed)
+                            && chat::dispatch_global_chat(&meexprox, &name, packet.clone(), &server) => continue,
+                        _ => {}
+                    }
+                }
+                let context = PacketContext { meexprox: &meexprox, name: &name, uuid, protocol_version };
+                if !meexprox.packet_handlers().handle(&context, current, PacketDirection::Serverbound, &packet) {
+                    continue;
+                }
+                while !server.is_alive() {}
+                server.write_packet(&packet).ignore();
             }
             info!("Player {} disconnected", name);
-            server.lock().unwrap().close();
+            meexprox.online().left(addr);
+            client.close();
+            server.close();
+            meexprox.record_event(ProxyEventRecord::Quit { player: name, uuid });
         });
+        if let Err(e) = spawned {
+            error!("serverbound relay thread spawn error: {}", e);
+            self.close();
+        }
     }
 
-    pub fn disconnect(&self) {
-        self.client_conn.lock().unwrap().close();
-        self.server_conn.lock().unwrap().close();
+    pub fn server(&self) -> Option<ServerInfo> {
+        self.server.read().clone()
     }
 
-    pub fn kick(&self, text: String) -> Result<(), ProxyError> {
-        self.write_client_packet(&Packet::build(
-            0x1D, |p| p.write_string(&text)
-        ).as_proxy()?)?;
-        self.disconnect();
+    pub fn state(&self) -> ClientState {
+        *self.state.read()
+    }
+
+    /// Enables logging of every packet relayed for player
+    pub fn set_debug(&self, debug: bool) {
+        self.debug.store(debug, Ordering::Relaxed);
+    }
+
+    pub fn is_debug(&self) -> bool {
+        self.debug.load(Ordering::Relaxed)
+    }
+
+    /// Disables sending and receiving global chat, chat of player goes to its backend only
+    pub fn set_global_chat(&self, enabled: bool) {
+        self.global_chat.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn is_global_chat(&self) -> bool {
+        self.global_chat.load(Ordering::Relaxed)
+    }
+
+    /// Client brand like `vanilla`, `fabric` or `forge`, known once client sent it in configuration, supported since 1.20.2
+    pub fn client_brand(&self) -> Option<String> {
+        self.client_brand.read().clone()
+    }
+
+    /// Client information last sent by client, supported since 1.20.2
+    pub fn client_settings(&self) -> Option<ClientSettings> {
+        self.client_settings.read().clone()
+    }
+
+    /// Language of client like `en_us`
+    pub fn locale(&self) -> Option<String> {
+        self.client_settings.read().as_ref().map(|o| o.locale.clone())
+    }
+
+    pub fn view_distance(&self) -> Option<i8> {
+        self.client_settings.read().as_ref().map(|o| o.view_distance)
+    }
+
+    pub fn chat_visibility(&self) -> Option<ChatVisibility> {
+        self.client_settings.read().as_ref().map(|o| o.chat_visibility)
+    }
+
+    pub fn reply_target(&self) -> Option<String> {
+        self.reply_target.read().clone()
+    }
+
+    pub fn set_reply_target(&self, name: &str) {
+        *self.reply_target.write() = Some(name.to_string());
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.client_conn.is_alive()
+    }
+
+    /// Closes connections of player without disconnect message, see [`Player::disconnect`]
+    pub fn close(&self) {
+        self.client_conn.close();
+        self.server_conn.close();
+    }
+
+    /// Sends system chat message to player, supported since 1.20.2
+    pub fn send_message(&self, message: impl Into<Component>) -> Result<(), ProxyError> {
+        let packets = ProtocolPackets::get(self.protocol_version).ok_or(ProxyError::UnsupportedProtocol(self.protocol_version))?;
+        let message = message.into();
+        self.write_priority_client_packet(&Packet::build(packets.system_chat, |p| {
+            message.write(p, self.protocol_version)?;
+            p.write_boolean(false) // overlay
+        }).as_proxy()?)
+    }
+
+    /// Shows title to player, times are in ticks, supported since 1.20.2
+    pub fn send_title(
+        &self, 
+        title: impl Into<Component>, 
+        subtitle: impl Into<Component>, 
+        fade_in: i32, 
+        stay: i32, 
+        fade_out: i32
+    ) -> Result<(), ProxyError> {
+        let packets = ProtocolPackets::get(self.protocol_version).ok_or(ProxyError::UnsupportedProtocol(self.protocol_version))?;
+        let (title, subtitle) = (title.into(), subtitle.into());
+
+        self.write_priority_client_packet(&Packet::build(packets.set_title_times, |p| {
+            p.write_int(fade_in)?;
+            p.write_int(stay)?;
+            p.write_int(fade_out)
+        }).as_proxy()?)?;
+        self.write_priority_client_packet(&Packet::build(packets.set_subtitle_text, |p| {
+            subtitle.write(p, self.protocol_version)
+        }).as_proxy()?)?;
+        // title is shown when its text is received, so it goes last
+        self.write_priority_client_packet(&Packet::build(packets.set_title_text, |p| {
+            title.write(p, self.protocol_version)
+        }).as_proxy()?)
+    }
+
+    /// Sets sidebar of this player, it replaces global sidebar. None shows global one again
+    pub fn set_sidebar(&self, sidebar: Option<Sidebar>) {
+        self.sidebar.lock().own = sidebar;
+        if let Some(meexprox) = self.meexprox.upgrade() {
+            meexprox.update_sidebar(self);
+        }
+    }
+
+    /// Sidebar set for this player by [`Player::set_sidebar`]
+    pub fn sidebar(&self) -> Option<Sidebar> {
+        self.sidebar.lock().own.clone()
+    }
+
+    /// Objective that backend shows in sidebar slot
+    pub fn backend_sidebar(&self) -> Option<String> {
+        self.sidebar.lock().backend().map(|o| o.to_string())
+    }
+
+    /// Changes sidebar shown by proxy to this one, only changed lines are sent. Supported since 1.20.2
+    pub(crate) fn show_sidebar(&self, sidebar: Option<&Sidebar>) -> Result<(), ProxyError> {
+        let packets = ProtocolPackets::get(self.protocol_version).ok_or(ProxyError::UnsupportedProtocol(self.protocol_version))?;
+        // lock is held while writing, so relay sees sidebar as it is on client
+        let mut state = self.sidebar.lock();
+        for packet in state.update(sidebar, &packets, self.protocol_version).as_proxy()? {
+            self.write_client_packet(&packet)?;
+        }
         Ok(())
     }
 
+    /// Round trip of last keep alive relayed to player
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency.lock().last
+    }
+
+    /// Sets ping shown in tab list of player for players with these uuids, supported since 1.20.2
+    pub fn send_latencies(&self, latencies: &[(Uuid, Duration)]) -> Result<(), ProxyError> {
+        let packets = ProtocolPackets::get(self.protocol_version).ok_or(ProxyError::UnsupportedProtocol(self.protocol_version))?;
+        self.write_priority_client_packet(&Packet::build(packets.player_info_update, |p| {
+            p.write_byte(PLAYER_INFO_UPDATE_LATENCY)?;
+            p.write_usize_varint(latencies.len())?;
+            for (uuid, latency) in latencies {
+                p.write_uuid(uuid)?;
+                p.write_usize_varint(latency.as_millis() as usize)?;
+            }
+            Ok(())
+        }).as_proxy()?)
+    }
+
+    /// Time since login of player was accepted
+    pub fn connected_for(&self) -> Duration {
+        self.connected_at.elapsed()
+    }
+
+    /// Time since player joined its current server
+    pub fn server_for(&self) -> Duration {
+        self.server_since.read().elapsed()
+    }
+
+    /// Original handshake of player, as client sent it
+    pub fn handshake(&self) -> &Handshake {
+        &self.handshake
+    }
+
+    /// Server address typed by client, even if player was routed by listener domain
+    pub fn virtual_host(&self) -> &str {
+        self.handshake.virtual_host()
+    }
+
+    /// Payload of cookie verified when player was transferred here by [`Player::transfer_with_cookie`]
+    pub fn transfer_payload(&self) -> Option<&[u8]> {
+        self.transfer_payload.as_deref()
+    }
+
+    /// Stores cookie with payload signed with transfer secret and player uuid on player and transfers it
+    /// to another proxy, which verifies cookie with the same `transfer` config on login. Supported since 1.20.5
+    pub fn transfer_with_cookie(&self, host: &str, port: u16, payload: &[u8]) -> Result<(), ProxyError> {
+        let meexprox = self.meexprox.upgrade().ok_or(ProxyError::ConnectionClosed)?;
+        let config = meexprox.config().transfer.clone().ok_or(ProxyError::Transfer("transfer is not enabled"))?;
+        let packets = ProtocolPackets::get(self.protocol_version).ok_or(ProxyError::UnsupportedProtocol(self.protocol_version))?;
+
+        let (store_cookie, transfer) = match self.state() {
+            ClientState::Configuration => (packets.configuration_store_cookie, packets.configuration_transfer),
+            ClientState::Play => (packets.store_cookie, packets.transfer),
+            ClientState::Login => return Err(ProxyError::Transfer("player is not logged in"))
+        };
+        let (Some(store_cookie), Some(transfer)) = (store_cookie, transfer) else {
+            return Err(ProxyError::UnsupportedProtocol(self.protocol_version));
+        };
+
+        let cookie = transfer::sign(&config.secret, &config.cookie, self.uuid, payload).ok_or(ProxyError::Transfer("cookie payload is too large"))?;
+        self.write_client_packet(&Packet::build(store_cookie, |p| {
+            p.write_string(&config.cookie)?;
+            p.write_usize_varint(cookie.len())?;
+            p.write_bytes(&cookie)
+        }).as_proxy()?)?;
+        self.write_client_packet(&Packet::build(transfer, |p| {
+            p.write_string(host)?;
+            p.write_usize_varint(port as usize)
+        }).as_proxy()?)?;
+
+        info!("Player {} transferred to {}:{}", self.name, host, port);
+        Ok(())
+    }
+
+    /// Disconnect packet with reason for current state and protocol version of player
+    fn disconnect_packet(&self, reason: &Component) -> Result<Packet, ProxyError> {
+        match self.state() {
+            // login disconnect is json string on every version
+            ClientState::Login => Packet::build(0x00, |p| {
+                p.write_string(&reason.to_json().to_string())
+            }).as_proxy(),
+            ClientState::Configuration => {
+                let packets = ProtocolPackets::get(self.protocol_version).ok_or(ProxyError::UnsupportedProtocol(self.protocol_version))?;
+                Packet::build(packets.configuration_disconnect, |p| reason.write(p, self.protocol_version)).as_proxy()
+            }
+            ClientState::Play => {
+                let id = protocol::play_disconnect(self.protocol_version).ok_or(ProxyError::UnsupportedProtocol(self.protocol_version))?;
+                Packet::build(id, |p| reason.write(p, self.protocol_version)).as_proxy()
+            }
+        }
+    }
+
+    /// Disconnects player with reason encoded for its state and protocol version, player stays connected
+    /// if [`PlayerKickEvent`] is cancelled. Connection is closed even if reason can not be encoded
+    pub fn disconnect(&self, reason: impl Into<Component>) -> Result<(), ProxyError> {
+        let meexprox = self.meexprox.upgrade();
+        let mut event = PlayerKickEvent::builder()
+            .name(self.name.clone())
+            .uuid(self.uuid)
+            .reason(reason.into())
+            .build();
+        if let Some(meexprox) = &meexprox {
+            match meexprox.trigger_event(&mut event) {
+                Ok(_) if event.is_cancelled() => return Ok(()),
+                Ok(_) => {}
+                // failed listener can't keep player connected, bans and duplicate sessions ignore result
+                Err(e) => meexprox.report_error(Some(&self.name), None, None, &e)
+            }
+        }
+
+        let packet = self.disconnect_packet(event.reason());
+        if let Ok(packet) = &packet {
+            self.write_priority_client_packet(packet).ignore();
+        }
+        self.close();
+        if let Some(meexprox) = meexprox {
+            meexprox.record_event(ProxyEventRecord::Kick { player: self.name.clone(), uuid: self.uuid, reason: event.reason().to_plain() });
+        }
+        packet.map(|_| ())
+    }
+
+    /// Disconnects player with plain text message
+    pub fn kick(&self, text: String) -> Result<(), ProxyError> {
+        self.disconnect(Component::text(text))
+    }
+
     pub fn server_recv_loop(&self) {
-        let mut server = self.server_conn.clone().lock().unwrap().try_clone().unwrap();
+        let mut server = self.server_conn.try_clone_reader().unwrap();
         let client = self.client_conn.clone();
-        let server_name = self.server.as_ref().unwrap().name.clone();
+        let server_name = self.server().map(|o| o.name).unwrap_or_default();
         let name = self.name.clone();
+        let sanitizer = self.sanitizer;
+        let protocol_version = self.protocol_version;
+        let span = info_span!(parent: &self.span, "clientbound");
+        let meexprox = self.meexprox.upgrade().unwrap();
+        let packets = ProtocolPackets::get(protocol_version);
+        let state = self.state.clone();
+        let debug = self.debug.clone();
+        let latency = self.latency.clone();
+        let joined = self.joined.clone();
+        let sidebar = self.sidebar.clone();
+        // queries of backend are answered by clientbound relay
+        let server_writer = self.server_conn.clone();
+        let uuid = self.uuid;
+        let throttle = self.clientbound_throttle.clone();
 
-        thread::spawn(move || {
+        let proxy = meexprox.clone();
+        let spawned = proxy.workers().spawn_relay(move || {
+            let _enter = span.enter();
             info!("Server {} connected player {}", server_name, name);
-            while let Ok(packet) = server.read_packet() {
-                client.lock().unwrap().write_packet(&packet).ignore();
+            loop {
+                let packet = match server.read_packet().as_proxy() {
+                    Ok(packet) => packet,
+                    Err(ProxyError::ConnectionClosed) => break,
+                    Err(e) => {
+                        meexprox.report_error(Some(&name), Some(&server_name), Some(PacketDirection::Clientbound), &e);
+                        break;
+                    }
+                };
+                if let Some(Err(e)) = sanitizer.map(|o| o.check_clientbound(protocol_version, &packet)) {
+                    warn!("Server {} sent blocked packet to player {}: {}", server_name, name, e);
+                    meexprox.report_error(Some(&name), Some(&server_name), Some(PacketDirection::Clientbound), &e);
+                    continue;
+                }
+                let wait = throttle.lock().as_mut().map(|o| o.take(packet.buffer().len()));
+                if let Some(wait) = wait.filter(|o| !o.is_zero()) {
+                    thread::sleep(wait);
+                }
+                meexprox.stats().add_clientbound(*state.read(), packet.id(), packet.buffer().len());
+                if debug.load(Ordering::Relaxed) {
+                    info!("Server {} sent packet 0x{:02X} ({} bytes) to player {} in {:?} state", server_name, packet.id(), packet.buffer().len(), name, *state.read());
+                }
+                if let (Some(packets), ClientState::Play) = (&packets, *state.read()) {
+                    if packet.id() == packets.keep_alive {
+                        if let Ok(id) = packet.clone().read_long() {
+                            latency.lock().sent(id);
+                        }
+                    } else if packet.id() == packets.player_info_update
+                            && packet.clone().read_byte().ok() == Some(PLAYER_INFO_UPDATE_LATENCY)
+                            && meexprox.config().tab_latency_interval.is_some() {
+                        continue; // backend latency is replaced with the one measured by proxy
+                    } else if Some(packet.id()) == packets.display_objective {
+                        if let Ok((SIDEBAR_SLOT, objective)) = sidebar::read_display_objective(&packet) {
+                            let conflict = meexprox.config().sidebar.as_ref().map(|o| o.conflict).unwrap_or_default();
+                            if !sidebar.lock().backend_displayed(objective, conflict) {
+                                continue; // proxy keeps sidebar slot
+                            }
+                            if conflict == SidebarConflict::Backend {
+                                client.queue_packet(&packet).ignore();
+                                meexprox.update_sidebar_of(uuid);
+                                continue;
+                            }
+                        }
+                    } else if packet.id() == packets.clientbound_plugin_message && meexprox.config().backend_query {
+                        if let Some(request) = query::read_query(packet.clone()) {
+                            let answer = query::answer(&meexprox, &request);
+                            debug!("Server {} query {} answered with {}", server_name, request, answer);
+                            if let Err(e) = query::answer_packet(packets.plugin_message, &answer).as_proxy()
+                                    .and_then(|o| server_writer.write_packet(&o)) {
+                                meexprox.report_error(Some(&name), Some(&server_name), Some(PacketDirection::Serverbound), &e);
+                            }
+                            continue;
+                        }
+                    } else if Some(packet.id()) == packets.update_objectives {
+                        if let Ok(Some(objective)) = sidebar::read_removed_objective(&packet) {
+                            sidebar.lock().backend_removed(&objective);
+                        }
+                    }
+                }
+                let context = PacketContext { meexprox: &meexprox, name: &name, uuid, protocol_version };
+                if !meexprox.packet_handlers().handle(&context, *state.read(), PacketDirection::Clientbound, &packet) {
+                    continue;
+                }
+                let keep_alive = packets.is_some_and(|o| o.keep_alive == packet.id()) && *state.read() == ClientState::Play;
+                if keep_alive {
+                    // answered in time even when client has chunk data queued
+                    client.write_priority_packet(&packet).ignore();
+                } else {
+                    client.queue_packet(&packet).ignore();
+                }
+                if let (Some(packets), ClientState::Play) = (&packets, *state.read()) {
+                    if packet.id() == packets.login {
+                        // priority packets sent from now on must not get ahead of login
+                        client.flush().ignore();
+                        // scoreboard of client is cleared by login, also when player switches server
+                        sidebar.lock().reset();
+                        meexprox.update_sidebar_of(uuid);
+                        if !joined.swap(true, Ordering::Relaxed) {
+                            meexprox.send_join_message(uuid);
+                        }
+                    }
+                }
             }
             info!("Server {} disconnected player {}", server_name, name);
         });
+        if let Err(e) = spawned {
+            error!("clientbound relay thread spawn error: {}", e);
+            self.close();
+        }
     }
 
-    pub fn connect_server(&self, config: &ProxyConfig, server: ServerInfo) -> Result<(), ProxyError> {
-        self.server_conn.lock().unwrap().close();
-        let mut server_conn = MCConnTcp::connect(&server.host).as_proxy()?;
+    pub fn connect_server(&self, _config: &ProxyConfig, server: ServerInfo) -> Result<(), ProxyError> {
+        self.server_conn.close();
+        self.span.record("server", server.name.as_str());
+        let mut server_conn = server.connect()
+            .map(MCConnTcp::new)
+            .map_err(|source| ProxyError::ServerConnect { server: server.name.clone(), source })
+            .inspect_err(|_| {
+                if let Some(meexprox) = self.meexprox.upgrade() {
+                    meexprox.stats().add_connect_failure(&server.name);
+                }
+            })?;
         if let Some(login_info) = &self.login_info {
-            login_info.write(config, &mut server_conn).as_proxy()?;
+            login_info.write(&server, &mut server_conn).as_proxy()?;
+        }
+        // backend is in configuration after login, client sends its settings only once
+        if let (Some(packets), Some(settings)) = (ProtocolPackets::get(self.protocol_version), self.client_settings()) {
+            server_conn.write_packet(&Packet::build(packets.configuration_client_information, |p| settings.write(p)).as_proxy()?).as_proxy()?;
         }
-        *self.server_conn.lock().unwrap() = server_conn;
+        self.server_conn.replace(server_conn)?;
+        let from = self.server.write().replace(server.clone()).map(|o| o.name);
+        *self.server_since.write() = Instant::now();
         self.server_recv_loop();
+        if let Some(meexprox) = self.meexprox.upgrade() {
+            meexprox.online().switched(self.addr, &server.name);
+            meexprox.record_event(ProxyEventRecord::Switch { player: self.name.clone(), uuid: self.uuid, from, to: server.name });
+        }
         Ok(())
     }
 
     pub fn write_client_packet(&self, packet: &Packet) -> Result<(), ProxyError> {
-        self.client_conn.lock().unwrap().write_packet(packet).as_proxy()
+        self.client_conn.write_packet(packet)
+    }
+
+    /// Writes packet ahead of relayed packets queued by write batching, so it's not delayed by chunk data.
+    /// Order with relayed packets is not kept, so packet must not depend on them
+    pub fn write_priority_client_packet(&self, packet: &Packet) -> Result<(), ProxyError> {
+        self.client_conn.write_priority_packet(packet)
     }
 
     pub fn write_server_packet(&self, packet: &Packet) -> Result<(), ProxyError> {
-        self.server_conn.lock().unwrap().write_packet(packet).as_proxy()
+        self.server_conn.write_packet(packet)
     }
 
     fn read_client_packet(&self) -> Result<Packet, ProxyError> {
-        self.client_conn.lock().unwrap().read_packet().as_proxy()
+        self.client_conn.read_packet()
     }
 
     fn read_server_packet(&self) -> Result<Packet, ProxyError> {
-        self.server_conn.lock().unwrap().read_packet().as_proxy()
+        self.server_conn.read_packet()
     }
 
     fn set_server_compression(&self, threshold: Option<usize>) {
-        self.server_conn.lock().unwrap().set_compression(threshold);
+        self.server_conn.set_compression(threshold);
     }
 
     fn set_client_compression(&self, threshold: Option<usize>) {
-        self.client_conn.lock().unwrap().set_compression(threshold);
+        self.client_conn.set_compression(threshold);
     }
 
     pub fn server_compression(&self) -> Option<usize> {
-        self.server_conn.lock().unwrap().compression()
+        self.server_conn.compression()
     }
 
     pub fn client_compression(&self) -> Option<usize> {
-        self.client_conn.lock().unwrap().compression()
+        self.client_conn.compression()
     }
 }
\ No newline at end of file
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+
+use super::{admin::{HttpRequest, HttpResponse}, MeexProx};
+
+const PAGE: &str = include_str!("dashboard.html");
+
+/// Handles dashboard page and its api, returns None for unknown paths
+pub fn route(meexprox: &Arc<MeexProx>, request: &HttpRequest) -> Option<HttpResponse> {
+    Some(match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/") => HttpResponse::html(PAGE),
+        ("GET", "/api/dashboard") => HttpResponse::json(200, dashboard(meexprox)),
+        ("POST", "/api/kick") => kick(meexprox, request),
+        ("POST", "/api/move") => move_player(meexprox, request),
+        _ => return None
+    })
+}
+
+fn dashboard(meexprox: &Arc<MeexProx>) -> Value {
+    let players = meexprox.get_players();
+
+    let servers: Vec<Value> = meexprox.config().servers.iter()
+        .map(|server| {
+            let health = meexprox.server_health(&server.name);
+            let players: Vec<Value> = players.iter()
+                .filter(|o| o.is_connected())
+                .filter(|o| o.server().map(|o| o.name == server.name).unwrap_or(false))
+                .map(|o| json!({
+                    "name": o.name,
+                    "uuid": o.uuid,
+                    "addr": o.addr.to_string(),
+                    "protocol_version": o.protocol_version
+                }))
+                .collect();
+
+            json!({
+                "name": server.name,
+                "host": server.host,
+                "reachable": health.as_ref().map(|o| o.reachable),
+                "latency": health.as_ref().and_then(|o| o.latency).map(|o| o.as_millis() as u64),
+                "ping": health.and_then(|o| o.ping).map(|o| o.as_millis() as u64),
+                "players": players
+            })
+        })
+        .collect();
+
+    let stats = meexprox.stats();
+
+    json!({
+        "servers": servers,
+        "connections": stats.connections(),
+        "bytes_serverbound": stats.bytes_serverbound(),
+        "bytes_clientbound": stats.bytes_clientbound()
+    })
+}
+
+fn kick(meexprox: &Arc<MeexProx>, request: &HttpRequest) -> HttpResponse {
+    let Some(name) = request.query.get("player") else {
+        return HttpResponse::text(400, "player is required");
+    };
+
+    let reason = request.query.get("reason").cloned().unwrap_or("Kicked".to_string());
+
+    for player in meexprox.get_players().iter() {
+        if &player.name == name && player.is_connected() {
+            return match player.kick(reason) {
+                Ok(_) => HttpResponse::text(200, "ok"),
+                Err(e) => HttpResponse::text(503, &e.to_string())
+            };
+        }
+    }
+
+    HttpResponse::text(404, "player not found")
+}
+
+fn move_player(meexprox: &Arc<MeexProx>, request: &HttpRequest) -> HttpResponse {
+    let (Some(name), Some(server)) = (request.query.get("player"), request.query.get("server")) else {
+        return HttpResponse::text(400, "player and server are required");
+    };
+
+    let Some(server) = meexprox.config().get_server_by_name(server) else {
+        return HttpResponse::text(404, "server not found");
+    };
+
+    let player = meexprox.get_players().iter()
+        .find(|o| &o.name == name && o.is_connected())
+        .cloned();
+    let Some(player) = player else {
+        return HttpResponse::text(404, "player not found");
+    };
+
+    match player.connect_server(&meexprox.config(), server) {
+        Ok(_) => HttpResponse::text(200, "ok"),
+        Err(e) => HttpResponse::text(503, &e.to_string())
+    }
+}
@@ -0,0 +1,93 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use log::warn;
+use serde_json::json;
+use serde_yml::Mapping;
+
+use super::{record::ProxyEventRecord, MeexProx};
+
+#[derive(Clone, Debug)]
+pub struct DiscordNotifier {
+    pub webhook: String,
+    pub rate_limit: usize,
+    /// Embed text by record type, records without template are not sent
+    pub templates: HashMap<String, String>
+}
+
+impl DiscordNotifier {
+    pub fn from_data(data: &Mapping) -> Option<DiscordNotifier> {
+        if !data.get("enabled")?.as_bool()? {
+            return None;
+        }
+
+        Some(DiscordNotifier {
+            webhook: data.get("webhook")?.as_str()?.to_string(),
+            rate_limit: data.get("rate_limit")
+                .and_then(|o| o.as_u64())
+                .unwrap_or(20) as usize,
+            templates: data.get("templates")?.as_mapping()?
+                .iter()
+                .filter_map(|(k, v)| Some((k.as_str()?.to_string(), v.as_str()?.to_string())))
+                .collect()
+        })
+    }
+
+    fn color(record: &ProxyEventRecord) -> u32 {
+        match record {
+            ProxyEventRecord::Join { .. }
+            | ProxyEventRecord::ServerUp { .. }
+            | ProxyEventRecord::ProxyStart { .. } => 0x57F287,
+            ProxyEventRecord::Quit { .. }
+            | ProxyEventRecord::Kick { .. }
+            | ProxyEventRecord::ServerDown { .. }
+            | ProxyEventRecord::ProxyStop => 0xED4245,
+            _ => 0x5865F2
+        }
+    }
+
+    fn send(&self, record: &ProxyEventRecord, template: &str) {
+        let body = json!({
+            "embeds": [{
+                "description": record.format(template),
+                "color": Self::color(record)
+            }]
+        });
+
+        if let Err(e) = ureq::post(&self.webhook)
+            .header("Content-Type", "application/json")
+            .send(body.to_string()) {
+            warn!("discord webhook error: {}", e);
+        }
+    }
+}
+
+/// Starts thread posting records to discord webhook, delaying messages above rate limit
+pub fn start(meexprox: Arc<MeexProx>, notifier: DiscordNotifier) -> JoinHandle<()> {
+    let records = meexprox.subscribe();
+
+    thread::spawn(move || {
+        let mut sent: VecDeque<Instant> = VecDeque::new();
+
+        for record in records {
+            let Some(template) = notifier.templates.get(record.name()) else {
+                continue;
+            };
+
+            while sent.len() >= notifier.rate_limit.max(1) {
+                let oldest = sent.pop_front().unwrap();
+                let window = Duration::from_secs(60);
+                if oldest.elapsed() < window {
+                    thread::sleep(window - oldest.elapsed());
+                }
+            }
+
+            notifier.send(&record, template);
+            sent.push_back(Instant::now());
+        }
+    })
+}
@@ -0,0 +1,151 @@
+use std::{
+    fmt::Write as _,
+    fs, io,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use serde_yml::Value;
+
+use super::{
+    config::PlayerForwarding,
+    protocol::{self, ProtocolPackets},
+    storage,
+    MeexProx,
+};
+
+/// Config values under keys containing these words are replaced in dump
+const SECRET_KEYS: [&str; 6] = ["secret", "token", "password", "url", "key", "webhook"];
+
+/// Recent errors included in dump
+const DUMP_ERRORS: usize = 50;
+
+/// Replaces secrets in raw config, so dump can be attached to public bug reports
+pub fn redact(value: &mut Value) {
+    match value {
+        Value::Mapping(map) => {
+            for (key, value) in map.iter_mut() {
+                let secret = key.as_str().is_some_and(|key| {
+                    let key = key.to_lowercase();
+                    SECRET_KEYS.iter().any(|o| key.contains(o))
+                });
+                if secret && !value.is_mapping() && !value.is_sequence() {
+                    *value = Value::String("<redacted>".to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Sequence(items) => items.iter_mut().for_each(redact),
+        Value::Tagged(tagged) => redact(&mut tagged.value),
+        _ => {}
+    }
+}
+
+fn forwarding_name(forwarding: &PlayerForwarding) -> &'static str {
+    match forwarding {
+        PlayerForwarding::Velocity(_) => "velocity",
+        PlayerForwarding::Bungeecord(_) => "bungeecord",
+        PlayerForwarding::Meexprox(_) => "meexprox",
+        PlayerForwarding::None => "none"
+    }
+}
+
+/// Threads of proxy process, known on linux only
+fn thread_count() -> Option<usize> {
+    fs::read_to_string("/proc/self/status").ok()?
+        .lines()
+        .find_map(|o| o.strip_prefix("Threads:"))?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Diagnostics text with version, config, threads, connections, listener timings and recent errors
+pub fn build(meexprox: &Arc<MeexProx>) -> String {
+    let config = meexprox.config();
+    let mut dump = String::new();
+
+    writeln!(dump, "# version").ok();
+    writeln!(dump, "meexprox {}", env!("CARGO_PKG_VERSION")).ok();
+    writeln!(dump, "os: {} {}", std::env::consts::OS, std::env::consts::ARCH).ok();
+    let supported: Vec<String> = (0..=u16::MAX)
+        .filter(|o| ProtocolPackets::get(*o).is_some())
+        .map(protocol::version_name)
+        .collect();
+    writeln!(dump, "full support: {}", supported.join(", ")).ok();
+
+    writeln!(dump, "\n# config").ok();
+    writeln!(dump, "host: {}", config.host).ok();
+    writeln!(dump, "listening: {}", meexprox.listening().join(", ")).ok();
+    for server in &config.servers {
+        writeln!(dump, "server {}: {} (forwarding {})", server.name, server.host, forwarding_name(&server.player_forwarding)).ok();
+    }
+    writeln!(dump, "incoming forwarding: {}", forwarding_name(&config.incoming_forwarding)).ok();
+    writeln!(dump, "maintenance: {}", config.maintenance.is_some()).ok();
+    for (name, enabled) in meexprox.plugins().list() {
+        writeln!(dump, "plugin {}: {}", name, if enabled { "enabled" } else { "disabled" }).ok();
+    }
+    let raw = config.path.as_ref()
+        .and_then(|o| fs::read_to_string(o).ok())
+        .and_then(|o| serde_yml::from_str::<Value>(&o).ok());
+    if let Some(mut raw) = raw {
+        redact(&mut raw);
+        writeln!(dump, "\n{}", serde_yml::to_string(&raw).unwrap_or_default().trim_end()).ok();
+    }
+
+    writeln!(dump, "\n# threads").ok();
+    match thread_count() {
+        Some(count) => writeln!(dump, "threads: {}", count).ok(),
+        None => writeln!(dump, "threads: unknown").ok()
+    };
+    let workers = meexprox.workers().config();
+    writeln!(dump, "accept threads: {}", workers.accept_threads).ok();
+    writeln!(dump, "connection workers: {}", workers.connection_workers).ok();
+
+    writeln!(dump, "\n# connections").ok();
+    writeln!(dump, "online: {}", meexprox.online_count()).ok();
+    for player in meexprox.get_players().iter() {
+        writeln!(
+            dump,
+            "{} {} {} server={} state={} version={} connected={} latency={}",
+            player.name,
+            player.uuid,
+            player.addr,
+            player.server().map(|o| o.name).unwrap_or_default(),
+            player.state().name(),
+            protocol::raw_version_name(player.handshake().raw_protocol),
+            player.is_connected(),
+            player.latency().map(|o| format!("{}ms", o.as_millis())).unwrap_or("unknown".to_string())
+        ).ok();
+    }
+
+    // listeners are called on relay threads, slow ones hold packets of players like lock waits do
+    writeln!(dump, "\n# listener timings").ok();
+    for timing in meexprox.listener_timings() {
+        writeln!(
+            dump,
+            "listener {} {}: {} calls, {}us average, {}us max",
+            timing.listener, timing.event, timing.calls, timing.average().as_micros(), timing.max.as_micros()
+        ).ok();
+    }
+
+    writeln!(dump, "\n# recent errors").ok();
+    let now = storage::now();
+    let errors: Vec<_> = meexprox.recent_events(usize::MAX)
+        .into_iter()
+        .filter(|o| o.record.name() == "error")
+        .collect();
+    for error in &errors[errors.len().saturating_sub(DUMP_ERRORS)..] {
+        writeln!(dump, "{} ago: {}", storage::format_duration(now - error.time), error.record.to_json()).ok();
+    }
+
+    dump
+}
+
+/// Writes dump to `dump-<time>.txt` in working directory, returns its path
+pub fn write(meexprox: &Arc<MeexProx>) -> io::Result<PathBuf> {
+    let path = PathBuf::from(format!("dump-{}.txt", storage::now()));
+    fs::write(&path, build(meexprox))?;
+    Ok(path)
+}
@@ -1,24 +1,106 @@
+use std::io;
+
 use rust_mc_proto::ProtocolError;
+use thiserror::Error;
+
+use super::protocol;
 
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum ProxyError {
+    #[error("config parse error")]
     ConfigParse,
-    ServerConnect,
+    #[error("no server for address {0:?}")]
+    NoServer(String),
+    #[error("server {server} connect error: {source}")]
+    ServerConnect {
+        server: String,
+        #[source]
+        source: io::Error
+    },
+    #[error("event changed")]
     EventChanged,
-    HandshakePacket,
-    LoginPacket,
-    PeerAddr,
+    #[error("unexpected handshake packet 0x{0:02X}")]
+    HandshakePacket(u8),
+    #[error("handshake timed out")]
+    HandshakeTimeout,
+    #[error("unexpected login packet 0x{0:02X}")]
+    LoginPacket(u8),
+    #[error("peer address error: {0}")]
+    PeerAddr(#[source] io::Error),
+    #[error("protocol error: {0:?}")]
     ProtocolError(ProtocolError),
-    ConnectionClosed
+    #[error("connection closed")]
+    ConnectionClosed,
+    #[error("exploit packet 0x{0:02X}")]
+    ExploitPacket(u8),
+    #[error("rate limit exceeded")]
+    RateLimited,
+    #[error("unsupported protocol version {0} ({name})", name = protocol::version_name(*.0))]
+    UnsupportedProtocol(u16),
+    #[error("unknown protocol version {0} ({name})", name = protocol::raw_version_name(*.0))]
+    UnknownProtocol(i32),
+    #[error("login denied")]
+    LoginDenied,
+    #[error("forwarding error: {0}")]
+    Forwarding(&'static str),
+    #[error("transfer error: {0}")]
+    Transfer(&'static str),
+    #[error("listener {host} bind error: {source}")]
+    ListenerBind {
+        host: String,
+        #[source]
+        source: io::Error
+    },
+    /// Error in connection of player
+    #[error("player {player}: {source}")]
+    Player {
+        player: String,
+        #[source]
+        source: Box<ProxyError>
+    },
+    /// Error in connection to backend server
+    #[error("server {server}: {source}")]
+    Server {
+        server: String,
+        #[source]
+        source: Box<ProxyError>
+    }
 }
 
-impl std::fmt::Display for ProxyError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "({:?})", self)
+impl ProxyError {
+    /// Error without player and server context
+    pub fn root(&self) -> &ProxyError {
+        match self {
+            ProxyError::Player { source, .. } | ProxyError::Server { source, .. } => source.root(),
+            o => o
+        }
     }
-}
 
-impl std::error::Error for ProxyError {}
+    /// Name of error variant, without context
+    pub fn kind(&self) -> &'static str {
+        match self.root() {
+            ProxyError::ConfigParse => "config_parse",
+            ProxyError::NoServer(_) => "no_server",
+            ProxyError::ServerConnect { .. } => "server_connect",
+            ProxyError::EventChanged => "event_changed",
+            ProxyError::HandshakePacket(_) => "handshake_packet",
+            ProxyError::HandshakeTimeout => "handshake_timeout",
+            ProxyError::LoginPacket(_) => "login_packet",
+            ProxyError::PeerAddr(_) => "peer_addr",
+            ProxyError::ProtocolError(_) => "protocol_error",
+            ProxyError::ConnectionClosed => "connection_closed",
+            ProxyError::ExploitPacket(_) => "exploit_packet",
+            ProxyError::RateLimited => "rate_limited",
+            ProxyError::UnsupportedProtocol(_) => "unsupported_protocol",
+            ProxyError::UnknownProtocol(_) => "unknown_protocol",
+            ProxyError::LoginDenied => "login_denied",
+            ProxyError::Forwarding(_) => "forwarding",
+            ProxyError::Transfer(_) => "transfer",
+            ProxyError::ListenerBind { .. } => "listener_bind",
+            ProxyError::Player { .. } | ProxyError::Server { .. } => unreachable!()
+        }
+    }
+}
 
 pub trait AsProxyError {
     fn as_proxy(self) -> ProxyError;
@@ -28,6 +110,12 @@ pub trait AsProxyResult<T> {
     fn as_proxy(self) -> Result<T, ProxyError>;
 }
 
+/// Adds player or server name to error, so it can be told apart in log
+pub trait ErrorContext<T> {
+    fn with_player(self, player: &str) -> Result<T, ProxyError>;
+    fn with_server(self, server: &str) -> Result<T, ProxyError>;
+}
+
 impl AsProxyError for ProtocolError {
     fn as_proxy(self) -> ProxyError {
         match self {
@@ -44,4 +132,14 @@ impl <T> AsProxyResult<T> for Result<T, ProtocolError> {
     fn as_proxy(self) -> Result<T, ProxyError> {
         self.map_err(|o| o.as_proxy())
     }
-}
\ No newline at end of file
+}
+
+impl <T> ErrorContext<T> for Result<T, ProxyError> {
+    fn with_player(self, player: &str) -> Result<T, ProxyError> {
+        self.map_err(|source| ProxyError::Player { player: player.to_string(), source: Box::new(source) })
+    }
+
+    fn with_server(self, server: &str) -> Result<T, ProxyError> {
+        self.map_err(|source| ProxyError::Server { server: server.to_string(), source: Box::new(source) })
+    }
+}
@@ -1,8 +1,11 @@
-use std::{any::Any, net::SocketAddr};
+use std::{any::Any, cmp::Reverse, net::{IpAddr, SocketAddr}, time::Duration};
 
-use make_event::MakeEvent;
+use make_event::{event_registry, MakeEvent};
+pub use make_event::{event_handler, event_listener};
+use serde_json::Value;
+use uuid::Uuid;
 
-use super::error::ProxyError;
+use super::{component::Component, error::ProxyError, stats::PacketDirection, status::StatusResponse, MeexProx};
 
 pub trait Event {
     fn name(&self) -> String;
@@ -35,16 +38,180 @@ where
 
 pub trait EventListener<T: Event>: AsAny {
     fn on_event(&self, event: &mut T) -> Result<(), ProxyError>;
+
+    /// Position among listeners of the same event, set by `#[priority]` and `#[order]` on handlers
+    fn order(&self) -> ListenerOrder {
+        ListenerOrder::default()
+    }
+}
+
+/// Listeners with higher priority are called first
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EventPriority {
+    Lowest,
+    Low,
+    #[default]
+    Normal,
+    High,
+    Highest
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ListenerOrder {
+    pub priority: EventPriority,
+    /// Name referenced by `before` and `after` of other listeners, `Type::method` for handlers
+    pub label: Option<String>,
+    /// Labels of listeners this one has to be called before
+    pub before: Vec<String>,
+    /// Labels of listeners this one has to be called after
+    pub after: Vec<String>
+}
+
+impl ListenerOrder {
+    /// Whether this listener has to be called before the other one
+    fn precedes(&self, other: &ListenerOrder) -> bool {
+        self.before.iter().any(|o| other.label.as_ref() == Some(o))
+            || other.after.iter().any(|o| self.label.as_ref() == Some(o))
+    }
+}
+
+/// Indexes of listeners in calling order: by priority, then moved to satisfy `before` and `after`,
+/// constraints forming a cycle are ignored
+pub fn sort_listeners(mut listeners: Vec<(usize, ListenerOrder)>) -> Vec<usize> {
+    listeners.sort_by_key(|o| Reverse(o.1.priority));
+
+    let mut sorted = Vec::with_capacity(listeners.len());
+    while !listeners.is_empty() {
+        let next = (0..listeners.len())
+            .find(|&i| !listeners.iter().enumerate().any(|(j, o)| i != j && o.1.precedes(&listeners[i].1)))
+            .unwrap_or(0);
+        sorted.push(listeners.remove(next).0);
+    }
+    sorted
+}
+
+/// Listener with methods marked `#[event_handler]`, implemented by `#[event_listener]` on its impl block
+pub trait EventHandlers {
+    /// Adds listener of every handled event type to proxy
+    fn register(self, meexprox: &mut MeexProx);
+}
+
+/// Time spent by one registered listener handling one type of event
+#[derive(Clone, Debug)]
+pub struct ListenerTiming {
+    pub listener: usize,
+    pub event: String,
+    pub calls: u64,
+    pub total: Duration,
+    pub max: Duration
+}
+
+impl ListenerTiming {
+    pub fn new(listener: usize, event: String) -> ListenerTiming {
+        ListenerTiming {
+            listener,
+            event,
+            calls: 0,
+            total: Duration::ZERO,
+            max: Duration::ZERO
+        }
+    }
+
+    pub fn record(&mut self, elapsed: Duration) {
+        self.calls += 1;
+        self.total += elapsed;
+        self.max = self.max.max(elapsed);
+    }
+
+    pub fn average(&self) -> Duration {
+        if self.calls == 0 {
+            Duration::ZERO
+        } else {
+            self.total / self.calls as u32
+        }
+    }
 }
 
 #[derive(MakeEvent)]
-#[MakeEvent("status")]
+#[MakeEvent("status", builder)]
 pub struct StatusEvent {
     cancelled: bool,
     addr: SocketAddr,
     #[setter]
-    motd: String,
+    #[mutable]
+    status: StatusResponse,
     server_address: String,
     server_port: u16,
     protocol_version: u16
-}
\ No newline at end of file
+}
+/// Backend disconnected player during login, reason is relayed to player unless cancelled or redirected
+#[derive(MakeEvent)]
+#[MakeEvent("login_disconnect", builder)]
+pub struct LoginDisconnectEvent {
+    cancelled: bool,
+    name: String,
+    uuid: Uuid,
+    server: String,
+    #[setter]
+    #[mutable]
+    reason: Value,
+    /// Server to try logging in instead
+    #[setter]
+    #[optional]
+    redirect: Option<String>
+}
+
+/// Proxy disconnects player with reason, player stays connected if cancelled
+#[derive(MakeEvent)]
+#[MakeEvent("player_kick", builder)]
+pub struct PlayerKickEvent {
+    cancelled: bool,
+    name: String,
+    uuid: Uuid,
+    #[setter]
+    #[mutable]
+    reason: Component
+}
+
+/// Status ping relayed to server, pong is not sent to client if cancelled
+#[derive(MakeEvent)]
+#[MakeEvent("server_status_ping")]
+pub struct ServerStatusPingEvent {
+    cancelled: bool,
+    addr: SocketAddr,
+    server: String,
+    payload: i64,
+    /// Round trip from proxy to server
+    latency: Duration
+}
+
+/// Error in relay loop or login of player, for monitoring, cancelling does nothing
+#[derive(MakeEvent)]
+#[MakeEvent("proxy_error", builder)]
+pub struct ProxyErrorEvent {
+    cancelled: bool,
+    #[optional]
+    player: Option<String>,
+    #[optional]
+    server: Option<String>,
+    /// Relay loop where error happened, None for login
+    #[optional]
+    direction: Option<PacketDirection>,
+    /// Error variant, like `exploit_packet`
+    kind: &'static str,
+    error: String
+}
+
+/// Ip reached limit of failed logins, it is not blocked if cancelled
+#[derive(MakeEvent)]
+#[MakeEvent("ip_block", builder)]
+pub struct IpBlockEvent {
+    cancelled: bool,
+    ip: IpAddr,
+    /// Failed logins in window of `ip_blocks` config
+    failures: usize,
+    #[setter]
+    duration: Duration
+}
+
+event_registry!(StatusEvent, LoginDisconnectEvent, PlayerKickEvent, ServerStatusPingEvent, ProxyErrorEvent, IpBlockEvent);
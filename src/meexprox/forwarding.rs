@@ -0,0 +1,365 @@
+use std::net::IpAddr;
+
+use bytebuffer::ByteBuffer;
+use ignore_result::Ignore;
+use log::warn;
+use ring::{hmac, rand::{SecureRandom, SystemRandom}};
+use rust_mc_proto::{DataBufferReader, DataBufferWriter, Packet, ProtocolError};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{config::{PlayerForwarding, ProxyConfig, ServerInfo, UntrustedForwarding}, protocol::HandshakeAddress};
+
+pub const VELOCITY_CHANNEL: &str = "velocity:player_info";
+pub const MEEXPROX_CHANNEL: &str = "meexprox:player_info";
+pub const MEEXPROX_FORWARDING_VERSION: u8 = 1;
+/// Chained proxies player can come through, more is treated as a loop
+pub const MAX_HOPS: usize = 8;
+
+/// Random id of proxy, used when `proxy_id` is not set in config
+pub fn random_proxy_id() -> String {
+    forwarding_nonce()[..4].iter()
+        .map(|o| format!("{:02x}", o))
+        .collect()
+}
+
+/// Random bytes sent in meexprox:player_info request, response is signed with them so it can't be replayed
+pub fn forwarding_nonce() -> [u8; 16] {
+    let mut nonce = [0; 16];
+    SystemRandom::new().fill(&mut nonce).ignore();
+    nonce
+}
+
+fn sign(secret: &str, nonce: &[u8], buf: &[u8]) -> hmac::Tag {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let mut ctx = hmac::Context::with_key(&key);
+    ctx.update(nonce);
+    ctx.update(buf);
+    ctx.sign()
+}
+
+/// Splits signature from signed data, None if it is not signed with secret
+fn verify<'a>(data: &'a [u8], secret: &str, nonce: &[u8]) -> Option<&'a [u8]> {
+    if data.len() < 32 {
+        return None;
+    }
+    let (sig, buf) = data.split_at(32);
+
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let mut signed = nonce.to_vec();
+    signed.extend_from_slice(buf);
+    hmac::verify(&key, &signed, sig).ok()?;
+
+    Some(buf)
+}
+
+/// Property of game profile, like skin textures
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ProfileProperty {
+    pub name: String,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>
+}
+
+/// Player identity received from upstream proxy
+#[derive(Clone, Debug)]
+pub struct ForwardedPlayer {
+    pub ip: IpAddr,
+    pub uuid: Uuid,
+    pub properties: Vec<ProfileProperty>
+}
+
+impl ForwardedPlayer {
+    /// Parses bungeecord fields `ip\0uuid\0properties` from extra data of address,
+    /// bungeeguard token is checked and removed from properties if set
+    pub fn from_bungeecord(address: &HandshakeAddress, token: Option<&str>) -> Option<ForwardedPlayer> {
+        let fields = address.extra_fields();
+
+        let ip = fields.first()?.parse().ok()?;
+        let uuid = Uuid::parse_str(fields.get(1)?).ok()?;
+        let mut properties: Vec<ProfileProperty> = match fields.get(2) {
+            Some(properties) => serde_json::from_str(properties).ok()?,
+            None => Vec::new()
+        };
+
+        if let Some(token) = token {
+            let index = properties.iter().position(|o| o.name == "bungeeguard-token")?;
+            if properties.remove(index).value != token {
+                return None;
+            }
+        }
+
+        Some(ForwardedPlayer { ip, uuid, properties })
+    }
+}
+
+/// Reads player identity sent by upstream proxy as set in `incoming_forwarding`,
+/// forwarding data is removed from address, error is kick reason.
+/// Forwarding data of direct clients is stripped or rejected as set in `untrusted`
+pub fn read_incoming(
+    config: &ProxyConfig,
+    address: &mut HandshakeAddress,
+    source: IpAddr
+) -> Result<Option<ForwardedPlayer>, &'static str> {
+    match &config.incoming_forwarding {
+        PlayerForwarding::Bungeecord(token) => {
+            if !config.trusted_proxies.contains(&source) {
+                return Err("Connection is not from trusted proxy");
+            }
+
+            let forwarded = ForwardedPlayer::from_bungeecord(address, token.as_deref())
+                .ok_or("Invalid forwarding data, enable ip forwarding in your proxy")?;
+
+            // fields after properties (forge marker) stay in address
+            let rest: Vec<&str> = address.extra_fields().into_iter().skip(3).collect();
+            address.extra = if rest.is_empty() { None } else { Some(rest.join("\0")) };
+
+            Ok(Some(forwarded))
+        }
+        _ if !address.has_forwarding_fields() => Ok(None),
+        _ => match config.untrusted_forwarding {
+            UntrustedForwarding::Strip => {
+                warn!("stripped forwarding data from handshake address of {}", source);
+                address.strip_forwarding_fields();
+                Ok(None)
+            }
+            UntrustedForwarding::Disconnect => Err("Forwarding data is not allowed from direct connections")
+        }
+    }
+}
+
+/// Canonical identity of player, whatever format it came in,
+/// translated to forwarding format of every backend
+#[derive(Clone, Debug)]
+pub struct PlayerIdentity {
+    pub name: String,
+    pub uuid: Uuid,
+    pub ip: IpAddr,
+    pub properties: Vec<ProfileProperty>,
+    /// Ids of proxies player came through, starting from the one client connected to
+    pub hops: Vec<String>
+}
+
+impl PlayerIdentity {
+    /// Handshake address for server, with bungeecord fields if server uses bungeecord forwarding
+    pub fn handshake_address(&self, address: &HandshakeAddress, server: &ServerInfo) -> String {
+        match &server.player_forwarding {
+            PlayerForwarding::Bungeecord(token) => {
+                let mut properties = self.properties.clone();
+                if let Some(token) = token {
+                    properties.push(ProfileProperty {
+                        name: "bungeeguard-token".to_string(),
+                        value: token.clone(),
+                        signature: None
+                    });
+                }
+
+                // backends split address by null and expect exactly these fields
+                format!(
+                    "{}\0{}\0{}\0{}",
+                    address.host,
+                    self.ip,
+                    self.uuid.simple(),
+                    serde_json::to_string(&properties).unwrap_or("[]".to_string())
+                )
+            }
+            _ => address.forward(&server.address_extra)
+        }
+    }
+
+    fn write_profile(&self, buf: &mut ByteBuffer) -> Result<(), ProtocolError> {
+        DataBufferWriter::write_string(buf, &self.ip.to_string())?;
+        DataBufferWriter::write_uuid(buf, &self.uuid)?;
+        DataBufferWriter::write_string(buf, &self.name)?;
+        DataBufferWriter::write_usize_varint(buf, self.properties.len())?;
+        for property in &self.properties {
+            DataBufferWriter::write_string(buf, &property.name)?;
+            DataBufferWriter::write_string(buf, &property.value)?;
+            DataBufferWriter::write_boolean(buf, property.signature.is_some())?;
+            if let Some(signature) = &property.signature {
+                DataBufferWriter::write_string(buf, signature)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn read_profile(buf: &mut Packet) -> Option<PlayerIdentity> {
+        let ip = buf.read_string().ok()?.parse().ok()?;
+        let uuid = buf.read_uuid().ok()?;
+        let name = buf.read_string().ok()?;
+
+        let mut properties = Vec::new();
+        for _ in 0..buf.read_usize_varint().ok()? {
+            properties.push(ProfileProperty {
+                name: buf.read_string().ok()?,
+                value: buf.read_string().ok()?,
+                signature: if buf.read_boolean().ok()? { Some(buf.read_string().ok()?) } else { None }
+            });
+        }
+
+        Some(PlayerIdentity { name, uuid, ip, properties, hops: Vec::new() })
+    }
+
+    /// Data of response to velocity:player_info request, signed with secret
+    pub fn velocity_response(&self, version: u8, secret: &str) -> Result<Vec<u8>, ProtocolError> {
+        let mut buf = ByteBuffer::new();
+        DataBufferWriter::write_u8_varint(&mut buf, version)?;
+        self.write_profile(&mut buf)?;
+        let buf = buf.as_bytes();
+
+        let mut data = sign(secret, &[], buf).as_ref().to_vec();
+        data.extend_from_slice(buf);
+        Ok(data)
+    }
+
+    /// Reads response to velocity:player_info request, None if signature is wrong
+    pub fn from_velocity(data: &[u8], secret: &str) -> Option<PlayerIdentity> {
+        let mut buf = Packet::from_bytes(0x00, verify(data, secret, &[])?);
+        buf.read_u8_varint().ok()?; // version
+        Self::read_profile(&mut buf)
+    }
+
+    /// Data of response to meexprox:player_info request of chained proxy, signed with secret and request nonce
+    pub fn meexprox_response(&self, nonce: &[u8], secret: &str) -> Result<Vec<u8>, ProtocolError> {
+        let mut buf = ByteBuffer::new();
+        DataBufferWriter::write_u8_varint(&mut buf, MEEXPROX_FORWARDING_VERSION)?;
+        self.write_profile(&mut buf)?;
+        DataBufferWriter::write_usize_varint(&mut buf, self.hops.len())?;
+        for hop in &self.hops {
+            DataBufferWriter::write_string(&mut buf, hop)?;
+        }
+        let buf = buf.as_bytes();
+
+        let mut data = sign(secret, nonce, buf).as_ref().to_vec();
+        data.extend_from_slice(buf);
+        Ok(data)
+    }
+
+    /// Reads response to meexprox:player_info request, None if signature or version is wrong
+    pub fn from_meexprox(data: &[u8], nonce: &[u8], secret: &str) -> Option<PlayerIdentity> {
+        let mut buf = Packet::from_bytes(0x00, verify(data, secret, nonce)?);
+        if buf.read_u8_varint().ok()? != MEEXPROX_FORWARDING_VERSION {
+            return None;
+        }
+        let mut identity = Self::read_profile(&mut buf)?;
+
+        let count = buf.read_usize_varint().ok()?;
+        if count > MAX_HOPS {
+            return None;
+        }
+        for _ in 0..count {
+            identity.hops.push(buf.read_string().ok()?);
+        }
+
+        Some(identity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UUID: &str = "069a79f444e94726a5befca90e38aaf5";
+
+    fn bungeecord_address(properties: &str) -> HandshakeAddress {
+        HandshakeAddress::parse(&format!("play.localhost\x001.2.3.4\0{}\0{}", UUID, properties))
+    }
+
+    fn config(trusted: Vec<IpAddr>, untrusted: UntrustedForwarding) -> ProxyConfig {
+        let incoming = if trusted.is_empty() { PlayerForwarding::None } else { PlayerForwarding::Bungeecord(None) };
+        let mut config = ProxyConfig::new("127.0.0.1:25565".to_string(), Vec::new(), None, PlayerForwarding::None, incoming);
+        config.trusted_proxies = trusted;
+        config.untrusted_forwarding = untrusted;
+        config
+    }
+
+    fn identity(hops: usize) -> PlayerIdentity {
+        PlayerIdentity {
+            name: "MeexReay".to_string(),
+            uuid: Uuid::parse_str(UUID).unwrap(),
+            ip: "1.2.3.4".parse().unwrap(),
+            properties: Vec::new(),
+            hops: (0..hops).map(|o| o.to_string()).collect()
+        }
+    }
+
+    #[test]
+    fn bungeeguard_token_is_checked_and_removed() {
+        let address = bungeecord_address(r#"[{"name":"bungeeguard-token","value":"token"},{"name":"textures","value":"skin"}]"#);
+
+        let player = ForwardedPlayer::from_bungeecord(&address, Some("token")).unwrap();
+        assert_eq!(player.ip, "1.2.3.4".parse::<IpAddr>().unwrap());
+        assert_eq!(player.uuid, Uuid::parse_str(UUID).unwrap());
+        assert_eq!(player.properties.len(), 1);
+        assert_eq!(player.properties[0].name, "textures");
+
+        assert!(ForwardedPlayer::from_bungeecord(&address, Some("wrong")).is_none());
+        assert!(ForwardedPlayer::from_bungeecord(&bungeecord_address("[]"), Some("token")).is_none());
+    }
+
+    #[test]
+    fn malformed_bungeecord_fields_are_rejected() {
+        for address in [
+            "play.localhost".to_string(),
+            format!("play.localhost\0not an ip\0{}", UUID),
+            "play.localhost\x001.2.3.4\0not a uuid".to_string(),
+            format!("play.localhost\x001.2.3.4\0{}\0{{not json", UUID)
+        ] {
+            assert!(ForwardedPlayer::from_bungeecord(&HandshakeAddress::parse(&address), None).is_none(), "{:?}", address);
+        }
+    }
+
+    #[test]
+    fn forwarding_is_read_from_trusted_proxies_only() {
+        let trusted: IpAddr = "10.0.0.1".parse().unwrap();
+        let config = config(vec![trusted], UntrustedForwarding::Strip);
+
+        let mut address = bungeecord_address("[]");
+        assert!(read_incoming(&config, &mut address, "10.0.0.2".parse().unwrap()).is_err());
+
+        let mut address = HandshakeAddress::parse(&format!("play.localhost\x001.2.3.4\0{}\0[]\0FML3", UUID));
+        let forwarded = read_incoming(&config, &mut address, trusted).unwrap().unwrap();
+        assert_eq!(forwarded.ip, "1.2.3.4".parse::<IpAddr>().unwrap());
+        assert_eq!(address.extra.as_deref(), Some("FML3"));
+    }
+
+    #[test]
+    fn forwarding_of_direct_clients_is_stripped_or_rejected() {
+        let source = "10.0.0.2".parse().unwrap();
+
+        let mut address = HandshakeAddress::parse(&format!("play.localhost\x001.2.3.4\0{}\0[]\0FML3", UUID));
+        assert!(read_incoming(&config(Vec::new(), UntrustedForwarding::Strip), &mut address, source).unwrap().is_none());
+        assert_eq!(address.extra.as_deref(), Some("FML3"));
+
+        let mut address = bungeecord_address("[]");
+        assert!(read_incoming(&config(Vec::new(), UntrustedForwarding::Disconnect), &mut address, source).is_err());
+    }
+
+    #[test]
+    fn meexprox_identity_with_wrong_signature_is_rejected() {
+        let nonce = forwarding_nonce();
+        let data = identity(1).meexprox_response(&nonce, "secret").unwrap();
+        assert_eq!(PlayerIdentity::from_meexprox(&data, &nonce, "secret").unwrap().hops, vec!["0"]);
+
+        assert!(PlayerIdentity::from_meexprox(&data, &nonce, "wrong").is_none());
+        // response to another request can't be replayed
+        assert!(PlayerIdentity::from_meexprox(&data, &forwarding_nonce(), "secret").is_none());
+
+        let mut tampered = data.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(PlayerIdentity::from_meexprox(&tampered, &nonce, "secret").is_none());
+        assert!(PlayerIdentity::from_meexprox(&data[..31], &nonce, "secret").is_none());
+    }
+
+    #[test]
+    fn meexprox_identity_over_hop_limit_is_rejected() {
+        let nonce = forwarding_nonce();
+
+        let data = identity(MAX_HOPS).meexprox_response(&nonce, "secret").unwrap();
+        assert!(PlayerIdentity::from_meexprox(&data, &nonce, "secret").is_some());
+
+        let data = identity(MAX_HOPS + 1).meexprox_response(&nonce, "secret").unwrap();
+        assert!(PlayerIdentity::from_meexprox(&data, &nonce, "secret").is_none());
+    }
+}
@@ -0,0 +1,69 @@
+use std::{net::IpAddr, path::Path};
+
+use serde_yml::Value;
+
+/// Point on Earth in degrees
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Location {
+    pub latitude: f64,
+    pub longitude: f64
+}
+
+impl Location {
+    /// Parses `[latitude, longitude]`
+    pub fn from_data(data: &Value) -> Option<Location> {
+        match data.as_sequence()?.as_slice() {
+            [latitude, longitude] => Some(Location {
+                latitude: latitude.as_f64()?,
+                longitude: longitude.as_f64()?
+            }),
+            _ => None
+        }
+    }
+
+    /// Great-circle distance in kilometers
+    pub fn distance(&self, other: &Location) -> f64 {
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (other.longitude - self.longitude).to_radians();
+
+        let a = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        6371.0 * 2.0 * a.sqrt().asin()
+    }
+}
+
+/// MaxMind city database, lookups always fail without `geoip` feature
+pub struct GeoIp {
+    #[cfg(feature = "geoip")]
+    reader: maxminddb::Reader<Vec<u8>>
+}
+
+impl GeoIp {
+    #[cfg(feature = "geoip")]
+    pub fn open(path: impl AsRef<Path>) -> Result<GeoIp, String> {
+        maxminddb::Reader::open_readfile(path)
+            .map(|reader| GeoIp { reader })
+            .map_err(|e| e.to_string())
+    }
+
+    #[cfg(not(feature = "geoip"))]
+    pub fn open(_path: impl AsRef<Path>) -> Result<GeoIp, String> {
+        Err("meexprox is built without geoip feature".to_string())
+    }
+
+    #[cfg(feature = "geoip")]
+    pub fn locate(&self, ip: IpAddr) -> Option<Location> {
+        let city: maxminddb::geoip2::City = self.reader.lookup(ip).ok()?;
+        let location = city.location?;
+
+        Some(Location {
+            latitude: location.latitude?,
+            longitude: location.longitude?
+        })
+    }
+
+    #[cfg(not(feature = "geoip"))]
+    pub fn locate(&self, _ip: IpAddr) -> Option<Location> {
+        None
+    }
+}
@@ -0,0 +1,173 @@
+use std::{net::SocketAddr, sync::Arc, thread};
+
+use log::{error, info};
+use tonic::{transport::Server, Request, Response, Status};
+
+use super::{config::{ApiServer, ServerInfo}, connection::Player, error::ProxyError, MeexProx};
+
+pub mod proto {
+    tonic::include_proto!("meexprox");
+}
+
+use proto::{
+    control_server::{Control, ControlServer},
+    Empty, KickPlayerRequest, Maintenance, MovePlayerRequest, PlayerList, RemoveServerRequest, ServerList,
+};
+
+struct ControlService {
+    meexprox: Arc<MeexProx>
+}
+
+impl ControlService {
+    /// Runs blocking closure on connected player with given name
+    async fn with_player<F>(&self, name: String, action: F) -> Result<Response<Empty>, Status>
+    where
+        F: FnOnce(&MeexProx, &Player) -> Result<(), ProxyError> + Send + 'static
+    {
+        let meexprox = self.meexprox.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let player = meexprox.get_players().iter()
+                .find(|o| o.name == name && o.is_connected())
+                .cloned()
+                .ok_or(Status::not_found("player not found"))?;
+            action(&meexprox, &player).map_err(|e| Status::unavailable(e.to_string()))
+        })
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?
+        .map(|_| Response::new(Empty {}))
+    }
+}
+
+#[tonic::async_trait]
+impl Control for ControlService {
+    async fn list_players(&self, _request: Request<Empty>) -> Result<Response<PlayerList>, Status> {
+        let players = self.meexprox.get_players().iter()
+            .filter(|o| o.is_connected())
+            .map(|o| proto::Player {
+                name: o.name.clone(),
+                uuid: o.uuid.to_string(),
+                addr: o.addr.to_string(),
+                server: o.server().map(|o| o.name).unwrap_or_default(),
+                protocol_version: o.protocol_version as u32
+            })
+            .collect();
+
+        Ok(Response::new(PlayerList { players }))
+    }
+
+    async fn kick_player(&self, request: Request<KickPlayerRequest>) -> Result<Response<Empty>, Status> {
+        let request = request.into_inner();
+        self.with_player(request.player, move |_, player| player.kick(request.reason)).await
+    }
+
+    async fn move_player(&self, request: Request<MovePlayerRequest>) -> Result<Response<Empty>, Status> {
+        let request = request.into_inner();
+        let server = self.meexprox.config()
+            .get_server_by_name(&request.server)
+            .ok_or(Status::not_found("server not found"))?;
+        self.with_player(request.player, move |meexprox, player| {
+            player.connect_server(&meexprox.config(), server)
+        }).await
+    }
+
+    async fn list_servers(&self, _request: Request<Empty>) -> Result<Response<ServerList>, Status> {
+        let servers = self.meexprox.config().servers.iter()
+            .map(|o| proto::Server {
+                name: o.name.clone(),
+                host: o.host.clone(),
+                domains: o.domains.clone(),
+                aliases: o.aliases.clone()
+            })
+            .collect();
+
+        Ok(Response::new(ServerList { servers }))
+    }
+
+    async fn add_server(&self, request: Request<proto::Server>) -> Result<Response<Empty>, Status> {
+        let request = request.into_inner();
+
+        if request.name.is_empty() || request.host.is_empty() {
+            return Err(Status::invalid_argument("name and host are required"));
+        }
+
+        let forwarding = self.meexprox.config().default_forwarding.clone();
+        let mut server = ServerInfo::new(request.name, request.host, request.domains, forwarding);
+        server.aliases = request.aliases;
+        self.meexprox.add_server(server);
+
+        Ok(Response::new(Empty {}))
+    }
+
+    async fn remove_server(&self, request: Request<RemoveServerRequest>) -> Result<Response<Empty>, Status> {
+        let request = request.into_inner();
+        let meexprox = self.meexprox.clone();
+
+        let removed = tokio::task::spawn_blocking(move || {
+            let move_players_to = Some(request.move_players_to).filter(|o| !o.is_empty());
+            meexprox.remove_server(&request.name, move_players_to.as_deref())
+        })
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?;
+
+        match removed {
+            Some(_) => Ok(Response::new(Empty {})),
+            None => Err(Status::not_found("server not found"))
+        }
+    }
+
+    async fn get_maintenance(&self, _request: Request<Empty>) -> Result<Response<Maintenance>, Status> {
+        let message = self.meexprox.config().maintenance.clone();
+
+        Ok(Response::new(Maintenance {
+            enabled: message.is_some(),
+            message: message.unwrap_or_default()
+        }))
+    }
+
+    async fn set_maintenance(&self, request: Request<Maintenance>) -> Result<Response<Empty>, Status> {
+        let request = request.into_inner();
+        self.meexprox.set_maintenance(request.enabled.then_some(request.message));
+
+        Ok(Response::new(Empty {}))
+    }
+}
+
+/// Starts grpc control server with its own tokio runtime in new thread
+pub fn start(meexprox: Arc<MeexProx>, config: ApiServer) {
+    let addr: SocketAddr = match config.host.parse() {
+        Ok(addr) => addr,
+        Err(e) => {
+            error!("grpc server host error: {}", e);
+            return;
+        }
+    };
+
+    thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .expect("grpc runtime error");
+
+        let token = config.token.map(|o| format!("Bearer {}", o));
+        let service = ControlServer::with_interceptor(
+            ControlService { meexprox },
+            move |request: Request<()>| {
+                match &token {
+                    Some(token) if request.metadata()
+                        .get("authorization")
+                        .and_then(|o| o.to_str().ok()) != Some(token.as_str()) => {
+                        Err(Status::unauthenticated("invalid token"))
+                    }
+                    _ => Ok(request)
+                }
+            }
+        );
+
+        info!("grpc server started on {}", addr);
+
+        if let Err(e) = runtime.block_on(Server::builder().add_service(service).serve(addr)) {
+            error!("grpc server error: {}", e);
+        }
+    });
+}
@@ -0,0 +1,81 @@
+use std::{
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+use rust_mc_proto::{DataBufferReader, DataBufferWriter, MCConnTcp, Packet};
+
+use super::{config::ServerInfo, error::{AsProxyResult, ProxyError}, status::StatusResponse};
+
+/// Protocol version sent in handshake of status ping, servers answer it with their own version
+const STATUS_PING_PROTOCOL: u16 = 767;
+
+/// Result of last reachability probe of backend server
+#[derive(Clone, Debug)]
+pub struct ServerHealth {
+    pub reachable: bool,
+    pub latency: Option<Duration>,
+    pub checked_at: Instant,
+    /// Round trip of last status ping relayed to server
+    pub ping: Option<Duration>
+}
+
+impl ServerHealth {
+    /// Opens tcp connection to server and measures how long it takes
+    pub fn check(server: &ServerInfo, timeout: Duration) -> ServerHealth {
+        let start = Instant::now();
+
+        let reachable = server.host
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut o| o.next())
+            .map(|addr| TcpStream::connect_timeout(&addr, timeout).is_ok())
+            .unwrap_or(false);
+
+        ServerHealth {
+            reachable,
+            latency: if reachable { Some(start.elapsed()) } else { None },
+            checked_at: Instant::now(),
+            ping: None
+        }
+    }
+
+    /// Sends status request to server, returns its status and round trip of the request
+    pub fn status(server: &ServerInfo, timeout: Duration) -> Result<(StatusResponse, Duration), ProxyError> {
+        let connect_error = |source| ProxyError::ServerConnect { server: server.name.clone(), source };
+
+        let addr: SocketAddr = server.host
+            .to_socket_addrs()
+            .map_err(connect_error)?
+            .next()
+            .ok_or(ProxyError::NoServer(server.host.clone()))?;
+
+        let stream = TcpStream::connect_timeout(&addr, timeout).map_err(connect_error)?;
+        stream.set_read_timeout(Some(timeout)).map_err(connect_error)?;
+        stream.set_write_timeout(Some(timeout)).map_err(connect_error)?;
+        let mut conn = MCConnTcp::new(stream);
+
+        let host = server.host.rsplit_once(':').map(|o| o.0).unwrap_or(&server.host);
+        let handshake = Packet::build(0x00, |p| {
+            p.write_u16_varint(STATUS_PING_PROTOCOL)?;
+            p.write_string(host)?;
+            p.write_unsigned_short(addr.port())?;
+            p.write_u8_varint(1)
+        }).as_proxy()?;
+
+        let start = Instant::now();
+        conn.write_packet(&handshake).as_proxy()?;
+        conn.write_packet(&Packet::empty(0x00)).as_proxy()?;
+
+        let mut packet = conn.read_packet().as_proxy()?;
+        let ping = start.elapsed();
+        if packet.id() != 0x00 {
+            return Err(ProxyError::HandshakePacket(packet.id()));
+        }
+        let json = packet.read_string().as_proxy()?;
+
+        StatusResponse::from_json(&json)
+            .map(|o| (o, ping))
+            .ok_or(ProxyError::ConnectionClosed)
+    }
+}
@@ -0,0 +1,118 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+use serde_yml::Mapping;
+
+use super::error::ProxyError;
+
+#[derive(Clone, Debug)]
+pub struct IpBlockConfig {
+    /// Failed logins in window that block ip
+    pub failures: usize,
+    pub window: Duration,
+    /// How long ip stays blocked
+    pub duration: Duration
+}
+
+impl IpBlockConfig {
+    pub fn from_data(data: &Mapping) -> Option<IpBlockConfig> {
+        if !data.get("enabled")?.as_bool()? {
+            return None;
+        }
+
+        Some(IpBlockConfig {
+            failures: data.get("failures").and_then(|o| o.as_u64()).unwrap_or(5).max(1) as usize,
+            window: Duration::from_secs(data.get("window").and_then(|o| o.as_u64()).unwrap_or(60)),
+            duration: Duration::from_secs(data.get("duration").and_then(|o| o.as_u64()).unwrap_or(300))
+        })
+    }
+}
+
+/// Whether error means failed login: garbage in handshake or login, denied login or forwarding
+pub fn is_login_failure(error: &ProxyError) -> bool {
+    matches!(
+        error.root(),
+        ProxyError::HandshakePacket(_)
+            | ProxyError::LoginPacket(_)
+            | ProxyError::ProtocolError(_)
+            | ProxyError::ExploitPacket(_)
+            | ProxyError::LoginDenied
+            | ProxyError::Forwarding(_)
+    )
+}
+
+/// Failed logins and temporary blocks by ip
+#[derive(Debug, Default)]
+pub struct IpBlocks {
+    failures: Mutex<HashMap<IpAddr, VecDeque<Instant>>>,
+    /// Time block ends at
+    blocked: Mutex<HashMap<IpAddr, Instant>>
+}
+
+impl IpBlocks {
+    pub fn new() -> IpBlocks {
+        IpBlocks::default()
+    }
+
+    pub fn is_blocked(&self, ip: IpAddr) -> bool {
+        let mut blocked = self.blocked.lock();
+        match blocked.get(&ip) {
+            Some(until) if *until > Instant::now() => true,
+            Some(_) => {
+                blocked.remove(&ip);
+                false
+            }
+            None => false
+        }
+    }
+
+    /// Counts failed login of ip, returns failures in last window
+    pub fn add_failure(&self, ip: IpAddr, window: Duration) -> usize {
+        let now = Instant::now();
+        let mut failures = self.failures.lock();
+        failures.retain(|_, o| o.back().is_some_and(|o| now - *o < window));
+
+        let times = failures.entry(ip).or_default();
+        times.push_back(now);
+        while times.front().is_some_and(|o| now - *o >= window) {
+            times.pop_front();
+        }
+        times.len()
+    }
+
+    /// Blocks ip and forgets its failures
+    pub fn block(&self, ip: IpAddr, duration: Duration) {
+        self.failures.lock().remove(&ip);
+        self.blocked.lock().insert(ip, Instant::now() + duration);
+    }
+
+    /// Blocked ips with time left, longest first
+    pub fn blocked(&self) -> Vec<(IpAddr, Duration)> {
+        let now = Instant::now();
+        let mut blocked: Vec<(IpAddr, Duration)> = self.blocked.lock()
+            .iter()
+            .filter(|o| *o.1 > now)
+            .map(|o| (*o.0, *o.1 - now))
+            .collect();
+        blocked.sort_by_key(|o| std::cmp::Reverse(o.1));
+        blocked
+    }
+
+    pub fn unblock(&self, ip: IpAddr) -> bool {
+        self.failures.lock().remove(&ip);
+        self.blocked.lock().remove(&ip).is_some()
+    }
+
+    /// Removes every block, returns how many were removed
+    pub fn clear(&self) -> usize {
+        self.failures.lock().clear();
+        let mut blocked = self.blocked.lock();
+        let count = blocked.len();
+        blocked.clear();
+        count
+    }
+}
@@ -1,139 +1,1673 @@
-use log::{error, info};
+use ignore_result::Ignore;
+use log::{debug, error, info, warn};
+use parking_lot::{Mutex, RwLock, RwLockReadGuard};
 use rust_mc_proto::{
     read_packet, write_packet, DataBufferReader, DataBufferWriter, MCConnTcp, Packet
 };
+use tracing::{field, info_span};
+use uuid::Uuid;
 use std::{
-    net::{TcpListener, TcpStream},
+    any::{Any, TypeId},
+    collections::{HashMap, VecDeque},
+    io,
+    net::{IpAddr, SocketAddr, TcpListener, TcpStream},
+    panic::{self, AssertUnwindSafe},
     sync::{
-        Arc, RwLock, RwLockReadGuard
-    }, thread,
+        atomic::{AtomicBool, Ordering}, mpsc::{self, Receiver, Sender}, Arc
+    }, thread::{self, JoinHandle}, time::{Duration, Instant},
 };
 
-use super::{config::ProxyConfig, connection::Player, error::{AsProxyResult, ProxyError}, event::{Event, EventListener, StatusEvent}};
+use super::{admin, broadcast, builder::MeexProxBuilder, chat, discord, webhook, command::{CommandManager, CommandSender}, commands, component::Component, config::{DuplicatePolicy, ListenerInfo, PlayerCount, ProxyConfig, RoutingStrategy, ServerInfo, StatusInfo}, connection::{self, ClientState, Player}, error::{AsProxyResult, ErrorContext, ProxyError}, forwarding, geoip::GeoIp, event::{sort_listeners, Event, EventHandlers, EventListener, IpBlockEvent, ListenerTiming, ProxyErrorEvent, ServerStatusPingEvent, StatusEvent}, health::ServerHealth, ipblock::{self, IpBlocks}, online::OnlineCounts, placeholders::{self, PlaceholderContext, PlaceholderProvider, PlaceholderProviders}, packets::{PacketContext, PacketHandlers, TypedPacket}, plugin::{Plugin, PluginManager}, protocol::{self, Handshake, HandshakeAddress, TRANSFER_INTENT, UNKNOWN_PROTOCOL}, pool::{self, ConnectionPool}, record::{ProxyEventRecord, TimedRecord}, screener::{ConnectionScreener, HttpScreener}, sidebar::{Sidebar, SidebarConflict}, stats::{PacketDirection, ProxyStats}, status::StatusResponse, storage::{self, Ban, Storage, StorageError}, tunnel, workers::Workers};
 
 
+/// Time shutdown waits for discord and webhook threads to send the last records
+const NOTIFIER_FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Bound listener socket, accept threads stop when stop is set
+struct BoundListener {
+    info: ListenerInfo,
+    addr: SocketAddr,
+    stop: Arc<AtomicBool>
+}
+
 pub struct MeexProx {
-    config: ProxyConfig,
-    players: RwLock<Vec<Player>>,
-    event_listeners: Vec<Box<dyn EventListener<dyn Event> + Send + Sync>>
+    config: RwLock<Arc<ProxyConfig>>,
+    players: RwLock<Vec<Arc<Player>>>,
+    /// Boxed `Box<dyn EventListener<T>>`, downcasted by event type on trigger
+    event_listeners: Vec<Box<dyn Any + Send + Sync>>,
+    /// Calling order of listeners by event type, built on first trigger
+    listener_order: RwLock<HashMap<TypeId, Vec<usize>>>,
+    listener_timings: Mutex<HashMap<(usize, String), ListenerTiming>>,
+    /// Panics by listener index, listeners over `listener_panic_limit` are not called anymore
+    listener_panics: Mutex<HashMap<usize, u32>>,
+    server_health: RwLock<HashMap<String, ServerHealth>>,
+    /// Bound listeners, host listener is the first
+    listening: RwLock<Vec<BoundListener>>,
+    stats: ProxyStats,
+    online: OnlineCounts,
+    packet_handlers: PacketHandlers,
+    placeholders: PlaceholderProviders,
+    /// Sidebar set by api for players without own one
+    sidebar: RwLock<Option<Sidebar>>,
+    record_subscribers: Mutex<Vec<Sender<ProxyEventRecord>>>,
+    /// Threads posting records to discord and webhooks
+    notifiers: Mutex<Vec<JoinHandle<()>>>,
+    event_history: Mutex<VecDeque<TimedRecord>>,
+    services: RwLock<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>,
+    plugins: PluginManager,
+    commands: CommandManager,
+    motd_counters: Mutex<HashMap<String, usize>>,
+    storage: Option<Arc<dyn Storage>>,
+    geoip: Option<GeoIp>,
+    screeners: RwLock<Vec<Arc<dyn ConnectionScreener>>>,
+    ip_blocks: IpBlocks,
+    pool: ConnectionPool,
+    workers: Workers
 }
 
 impl MeexProx {
     pub fn new(config: ProxyConfig) -> MeexProx {
+        let commands = CommandManager::new();
+        commands.register(commands::ListCommand);
+        commands.register(commands::GlistCommand);
+        commands.register(commands::FindCommand);
+        commands.register(commands::PlayerInfoCommand);
+        commands.register(commands::SendCommand);
+        commands.register(commands::ServerCommand);
+        commands.register(commands::KickCommand);
+        commands.register(commands::DebugCommand);
+        commands.register(commands::GlobalChatCommand);
+        commands.register(commands::MsgCommand);
+        commands.register(commands::ReplyCommand);
+        commands.register(commands::AlertCommand);
+        commands.register(commands::StaffChatCommand);
+        commands.register(commands::StatsCommand);
+        commands.register(commands::EventsCommand);
+        commands.register(commands::DumpCommand);
+        commands.register(commands::IpBlocksCommand);
+        commands.register(commands::ReloadCommand);
+        commands.register(commands::MaintenanceCommand);
+        commands.register(commands::EndCommand);
+        commands.register(commands::BanCommand);
+        commands.register(commands::TempbanCommand);
+        commands.register(commands::UnbanCommand);
+        commands.register(commands::WhitelistCommand);
+        commands.register(commands::SeenCommand);
+
+        let storage = config.storage.as_ref().and_then(|o| match storage::open(&o.backend) {
+            Ok(storage) => Some(storage),
+            Err(e) => {
+                error!("storage open error: {}", e);
+                None
+            }
+        });
+
+        let geoip = config.geoip_database.as_ref().and_then(|o| match GeoIp::open(o) {
+            Ok(geoip) => Some(geoip),
+            Err(e) => {
+                error!("geoip database open error: {}", e);
+                None
+            }
+        });
+
+        let screeners: Vec<Arc<dyn ConnectionScreener>> = config.screener.clone()
+            .map(|o| Arc::new(HttpScreener::new(o)) as Arc<dyn ConnectionScreener>)
+            .into_iter()
+            .collect();
+
+        let workers = Workers::new(config.workers.clone());
+
         MeexProx {
-            config,
+            config: RwLock::new(Arc::new(config)),
             players: RwLock::new(Vec::new()),
             event_listeners: Vec::new(),
+            listener_order: RwLock::new(HashMap::new()),
+            listener_timings: Mutex::new(HashMap::new()),
+            listener_panics: Mutex::new(HashMap::new()),
+            server_health: RwLock::new(HashMap::new()),
+            listening: RwLock::new(Vec::new()),
+            stats: ProxyStats::new(),
+            online: OnlineCounts::new(),
+            packet_handlers: PacketHandlers::new(),
+            placeholders: PlaceholderProviders::new(),
+            sidebar: RwLock::new(None),
+            record_subscribers: Mutex::new(Vec::new()),
+            notifiers: Mutex::new(Vec::new()),
+            event_history: Mutex::new(VecDeque::new()),
+            services: RwLock::new(HashMap::new()),
+            plugins: PluginManager::new(),
+            commands,
+            motd_counters: Mutex::new(HashMap::new()),
+            storage,
+            geoip,
+            screeners: RwLock::new(screeners),
+            ip_blocks: IpBlocks::new(),
+            pool: ConnectionPool::new(),
+            workers
+        }
+    }
+
+    /// Builder of proxy configured in code, without config file
+    pub fn builder() -> MeexProxBuilder {
+        MeexProxBuilder::new()
+    }
+
+    /// Snapshot of current config, later changes are not reflected in it
+    pub fn config(&self) -> Arc<ProxyConfig> {
+        self.config.read().clone()
+    }
+
+    pub fn set_config(&self, config: ProxyConfig) {
+        *self.config.write() = Arc::new(config);
+    }
+
+    /// Loads config again from the file it was loaded from
+    pub fn reload_config(&self) -> Result<(), ProxyError> {
+        let path = self.config().path.clone().ok_or(ProxyError::ConfigParse)?;
+        let config = ProxyConfig::load(path).ok_or(ProxyError::ConfigParse)?;
+        self.set_config(config);
+        Ok(())
+    }
+
+    fn update_config(&self, update: impl FnOnce(&mut ProxyConfig)) {
+        let mut config = self.config.write();
+        let mut new_config = config.as_ref().clone();
+        update(&mut new_config);
+        *config = Arc::new(new_config);
+    }
+
+    /// Adds server to routing, replacing the server with the same name
+    pub fn add_server(&self, server: ServerInfo) {
+        self.update_config(|config| {
+            config.servers.retain(|o| o.name != server.name);
+            config.servers.push(server);
+        });
+    }
+
+    /// Removes server from routing, its players are moved to `move_players_to` server or kicked if it is None or can't be connected
+    pub fn remove_server(&self, name: &str, move_players_to: Option<&str>) -> Option<ServerInfo> {
+        let mut removed = None;
+        self.update_config(|config| {
+            if let Some(index) = config.servers.iter().position(|o| o.name == name) {
+                removed = Some(config.servers.remove(index));
+            }
+        });
+        removed.as_ref()?;
+
+        let config = self.config();
+        let target = move_players_to.and_then(|o| config.get_server_by_name(o));
+        // players lock is not held while connecting, switching players need it too
+        let players: Vec<Arc<Player>> = self.get_players().iter()
+            .filter(|o| o.is_connected() && o.server().is_some_and(|o| o.name == name))
+            .cloned()
+            .collect();
+        for player in players {
+            let moved = match &target {
+                Some(target) => player.connect_server(&config, target.clone())
+                    .inspect_err(|e| warn!("player {} move to {} error: {}", player.name, target.name, e))
+                    .is_ok(),
+                None => false
+            };
+            if !moved {
+                player.kick(format!("Server {} was removed", name)).ignore();
+            }
+        }
+
+        removed
+    }
+
+    /// Section of `plugins` mapping in config, use `serde_yml::from_value` to deserialize it
+    pub fn plugin_config(&self, name: &str) -> Option<serde_yml::Value> {
+        self.config().plugins.get(name).cloned()
+    }
+
+    pub fn is_maintenance(&self) -> bool {
+        self.config().maintenance.is_some()
+    }
+
+    /// Enables maintenance with kick message or disables it with None
+    pub fn set_maintenance(&self, message: Option<String>) {
+        self.update_config(|config| config.maintenance = message);
+    }
+
+    /// Registers shared service, replacing previous service of the same type
+    pub fn provide<T: Any + Send + Sync>(&self, service: T) {
+        self.services.write().insert(TypeId::of::<T>(), Arc::new(service));
+    }
+
+    pub fn get_service<T: Any + Send + Sync>(&self) -> Option<Arc<T>> {
+        self.services.read()
+            .get(&TypeId::of::<T>())
+            .cloned()
+            .and_then(|o| o.downcast::<T>().ok())
+    }
+
+    pub fn storage(&self) -> Option<&Arc<dyn Storage>> {
+        self.storage.as_ref()
+    }
+
+    /// Bans player for duration in seconds (permanently if None) and kicks them if online
+    pub fn ban(&self, name: &str, reason: String, duration: Option<i64>) -> Result<Ban, StorageError> {
+        let storage = self.storage.as_ref().ok_or(StorageError("storage is not enabled".to_string()))?;
+
+        let online = self.get_players().iter()
+            .find(|o| o.name.eq_ignore_ascii_case(name) && o.is_connected())
+            .map(|o| o.uuid);
+        let uuid = match online {
+            Some(uuid) => Some(uuid),
+            None => storage.get_player(name)?.map(|o| o.uuid)
+        };
+
+        let created = storage::now();
+        let ban = Ban {
+            name: name.to_string(),
+            uuid,
+            reason,
+            created,
+            expires: duration.map(|o| created + o)
+        };
+        storage.add_ban(&ban)?;
+
+        let message = self.ban_message(&ban);
+        for player in self.connected_players(|o| o.name.eq_ignore_ascii_case(name)) {
+            player.kick(self.render(&message, &PlaceholderContext::of(&player))).ignore();
+        }
+
+        Ok(ban)
+    }
+
+    pub fn ban_message(&self, ban: &Ban) -> String {
+        match &self.config().storage {
+            Some(config) => ban.message(config),
+            None => ban.reason.clone()
+        }
+    }
+
+    /// Reason to deny login of player, checks bans and whitelist
+    pub fn check_login(&self, name: &str, uuid: Uuid) -> Option<String> {
+        if let Some(storage) = &self.storage {
+            match storage.get_ban(name, Some(uuid)) {
+                Ok(Some(ban)) => return Some(self.ban_message(&ban)),
+                Ok(None) => {}
+                Err(e) => error!("storage error: {}", e)
+            }
+
+            if self.config().storage.as_ref().is_some_and(|o| o.whitelist) {
+                match storage.is_whitelisted(name) {
+                    Ok(true) => {}
+                    Ok(false) => return Some("You are not whitelisted on this server".to_string()),
+                    Err(e) => error!("storage error: {}", e)
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Applies `duplicate_session` policy to login of player, returns disconnect message if it is denied
+    pub fn check_duplicate_session(&self, name: &str, uuid: Uuid) -> Option<String> {
+        let config = self.config();
+        let session = &config.duplicate_session;
+        if session.policy == DuplicatePolicy::Allow {
+            return None;
+        }
+
+        let existing = self.connected_players(|o| o.uuid == uuid || o.name.eq_ignore_ascii_case(name));
+        if existing.is_empty() {
+            return None;
+        }
+
+        match session.policy {
+            DuplicatePolicy::Deny => Some(session.deny_message.clone()),
+            _ => {
+                for player in existing {
+                    info!("Player {} logged in from another location", player.name);
+                    player.kick(self.render(&session.kick_message, &PlaceholderContext::of(&player))).ignore();
+                }
+                None
+            }
+        }
+    }
+
+    /// Adds screener checked at every login after bans and whitelist
+    pub fn add_screener(&self, screener: impl ConnectionScreener + 'static) {
+        self.screeners.write().push(Arc::new(screener));
+    }
+
+    /// Kick message of first screener that denies connection
+    pub fn screen_connection(&self, ip: IpAddr, name: &str) -> Option<String> {
+        let screeners = self.screeners.read().clone();
+        screeners.iter().find_map(|o| o.screen(ip, name))
+    }
+
+    pub fn ip_blocks(&self) -> &IpBlocks {
+        &self.ip_blocks
+    }
+
+    pub fn workers(&self) -> &Workers {
+        &self.workers
+    }
+
+    /// Counts failed login of ip, blocks it when failures reach limit of `ip_blocks` config
+    pub fn login_failed(&self, ip: IpAddr, error: &ProxyError) {
+        let Some(config) = self.config().ip_blocks.clone() else {
+            return;
+        };
+        if !ipblock::is_login_failure(error) {
+            return;
+        }
+
+        let failures = self.ip_blocks.add_failure(ip, config.window);
+        if failures < config.failures {
+            return;
+        }
+
+        let mut event = IpBlockEvent::builder()
+            .ip(ip)
+            .failures(failures)
+            .duration(config.duration)
+            .build();
+        if let Err(e) = self.trigger_event(&mut event) {
+            warn!("ip block event listener error: {}", e);
         }
+        if event.is_cancelled() {
+            return;
+        }
+
+        let duration = *event.duration();
+        self.ip_blocks.block(ip, duration);
+        warn!("ip {} blocked for {} after {} failed logins", ip, storage::format_duration(duration.as_secs() as i64), failures);
+        self.record_event(ProxyEventRecord::IpBlock { ip, failures, duration: duration.as_secs() });
+    }
+
+    pub fn pool(&self) -> &ConnectionPool {
+        &self.pool
+    }
+
+    pub fn stats(&self) -> &ProxyStats {
+        &self.stats
+    }
+
+    /// Players relayed by proxy, counted without locking player list
+    pub fn online_count(&self) -> usize {
+        self.online.total()
+    }
+
+    pub fn online_count_for(&self, server: &str) -> usize {
+        self.online.server(server)
+    }
+
+    /// Different players that joined since midnight utc
+    pub fn unique_players_today(&self) -> usize {
+        self.online.unique_today()
+    }
+
+    pub(crate) fn online(&self) -> &OnlineCounts {
+        &self.online
     }
 
-    pub fn add_event_listener(
+    pub fn server_health(&self, name: &str) -> Option<ServerHealth> {
+        self.server_health.read().get(name).cloned()
+    }
+
+    /// Hosts proxy accepts connections on, empty until `listen` is called
+    pub fn listening(&self) -> Vec<String> {
+        self.listening.read().iter().map(|o| o.info.host.clone()).collect()
+    }
+
+    /// Binds listener and accepts connections on it in background
+    pub fn add_listener(self: &Arc<Self>, listener: ListenerInfo) -> Result<(), ProxyError> {
+        let target = listener.server.clone().or(listener.domain.clone());
+        let host = listener.host.clone();
+        let (socket, stop) = self.bind_listener(listener)?;
+
+        match target {
+            Some(target) => info!("meexprox listening on {} for {}", host, target),
+            None => info!("meexprox listening on {}", host)
+        }
+
+        let self_arc = self.clone();
+        thread::spawn(move || self_arc.accept_loop(socket, stop));
+        Ok(())
+    }
+
+    /// Binds socket of listener and adds it to listening, returns it with its stop flag
+    fn bind_listener(&self, listener: ListenerInfo) -> Result<(TcpListener, Arc<AtomicBool>), ProxyError> {
+        let bind_error = |source| ProxyError::ListenerBind { host: listener.host.clone(), source };
+        if self.listening.read().iter().any(|o| o.info.host == listener.host) {
+            return Err(bind_error(io::Error::new(io::ErrorKind::AddrInUse, "already listening")));
+        }
+
+        let socket = TcpListener::bind(&listener.host).map_err(bind_error)?;
+        let addr = socket.local_addr().map_err(bind_error)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        self.listening.write().push(BoundListener { info: listener, addr, stop: stop.clone() });
+        Ok((socket, stop))
+    }
+
+    /// Closes listener, connections accepted by it stay, returns false if host is not listened
+    pub fn remove_listener(&self, host: &str) -> bool {
+        let listener = {
+            let mut listening = self.listening.write();
+            match listening.iter().position(|o| o.info.host == host) {
+                Some(index) => listening.remove(index),
+                None => return false
+            }
+        };
+
+        listener.stop.store(true, Ordering::Relaxed);
+
+        // accept threads are blocked in accept, every one is woken by own connection
+        let mut wake = listener.addr;
+        if wake.ip().is_unspecified() {
+            wake.set_ip(match wake {
+                SocketAddr::V4(_) => IpAddr::from([127, 0, 0, 1]),
+                SocketAddr::V6(_) => IpAddr::from([0, 0, 0, 0, 0, 0, 0, 1])
+            });
+        }
+        for _ in 0..self.workers.config().accept_threads {
+            TcpStream::connect_timeout(&wake, Duration::from_secs(1)).ignore();
+        }
+
+        info!("meexprox stopped listening on {}", host);
+        true
+    }
+
+    /// Saves round trip of status ping relayed to server
+    fn record_ping(&self, server: &str, ping: Duration) {
+        let mut server_health = self.server_health.write();
+        let health = server_health.entry(server.to_string()).or_insert(ServerHealth {
+            reachable: true,
+            latency: None,
+            checked_at: Instant::now(),
+            ping: None
+        });
+        health.ping = Some(ping);
+    }
+
+    fn check_servers_health(&self) {
+        for server in &self.config().servers {
+            let mut health = ServerHealth::check(server, Duration::from_secs(3));
+            let reachable = health.reachable;
+            let mut server_health = self.server_health.write();
+            health.ping = server_health.get(&server.name).and_then(|o| o.ping);
+            let previous = server_health
+                .insert(server.name.clone(), health)
+                .map(|o| o.reachable);
+            drop(server_health);
+
+            if previous.is_some() && previous != Some(reachable) || previous.is_none() && !reachable {
+                self.record_event(if reachable {
+                    ProxyEventRecord::ServerUp { server: server.name.clone() }
+                } else {
+                    ProxyEventRecord::ServerDown { server: server.name.clone() }
+                });
+            }
+        }
+    }
+
+    /// Pings every server in parallel and logs table of reachable and unreachable ones with their versions
+    fn report_servers(&self, timeout: Duration) {
+        let servers = self.config().servers.clone();
+        let probes: Vec<_> = servers.iter()
+            .map(|server| {
+                let server = server.clone();
+                thread::spawn(move || ServerHealth::status(&server, timeout))
+            })
+            .collect();
+
+        let rows: Vec<[String; 5]> = servers.iter()
+            .zip(probes)
+            .map(|(server, probe)| {
+                let (status, version, ping) = match probe.join().unwrap_or(Err(ProxyError::ConnectionClosed)) {
+                    Ok((status, ping)) => (
+                        "reachable".to_string(),
+                        status.version.map(|o| match o.protocol {
+                            Some(protocol) => format!("{} ({})", o.name, protocol),
+                            None => o.name
+                        }).unwrap_or_else(|| "-".to_string()),
+                        format!("{} ms", ping.as_millis())
+                    ),
+                    Err(e) => (format!("unreachable: {}", e), "-".to_string(), "-".to_string())
+                };
+                [server.name.clone(), server.host.clone(), version, ping, status]
+            })
+            .collect();
+
+        let header = ["server", "host", "version", "ping", "status"].map(|o| o.to_string());
+        let mut widths = header.clone().map(|o| o.len());
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        let reachable = rows.iter().filter(|o| o[4] == "reachable").count();
+        info!("{}/{} servers reachable", reachable, rows.len());
+        for row in std::iter::once(&header).chain(&rows) {
+            let line = row.iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("  ");
+            if row[4] == "reachable" || row == &header {
+                info!("{}", line.trim_end());
+            } else {
+                warn!("{}", line.trim_end());
+            }
+        }
+    }
+
+    pub fn add_event_listener<T: Event + 'static>(
         &mut self,
-        event_listener: Box<dyn EventListener<dyn Event> + Send + Sync>,
+        event_listener: Box<dyn EventListener<T> + Send + Sync>,
+    ) {
+        self.event_listeners.push(Box::new(event_listener));
+        self.listener_order.write().clear();
+    }
+
+    /// Adds handler of typed packet, packets are decoded only while they have handlers.
+    /// Packet is dropped when handler returns false
+    pub fn register_packet_handler<T: TypedPacket>(
+        &self,
+        handler: impl Fn(&PacketContext, &T) -> bool + Send + Sync + 'static
     ) {
-        self.event_listeners.push(event_listener);
+        self.packet_handlers.register(handler);
+    }
+
+    pub fn packet_handlers(&self) -> &PacketHandlers {
+        &self.packet_handlers
+    }
+
+    /// Adds placeholder rendered in text of config, like `{rank}`. Provider gets player and server the text is shown for
+    pub fn register_placeholder(&self, name: &str, provider: impl PlaceholderProvider + 'static) {
+        self.placeholders.register(name, provider);
+    }
+
+    pub fn placeholders(&self) -> &PlaceholderProviders {
+        &self.placeholders
+    }
+
+    /// Adds listener of every event handled by its `#[event_handler]` methods
+    pub fn add_event_handlers(&mut self, handlers: impl EventHandlers) {
+        handlers.register(self);
+    }
+
+    pub fn commands(&self) -> &CommandManager {
+        &self.commands
+    }
+
+    /// Executes command line as sender and returns lines to send back
+    pub fn execute_command(self: &Arc<Self>, sender: &CommandSender, line: &str) -> Vec<String> {
+        self.commands.execute(self, sender, line)
+    }
+
+    /// Adds plugin to be enabled on start, after the plugins it depends on
+    pub fn add_plugin(&mut self, plugin: Box<dyn Plugin>) {
+        self.plugins.add(plugin);
+    }
+
+    pub fn plugins(&self) -> &PluginManager {
+        &self.plugins
+    }
+
+    /// Disables plugin and plugins depending on it, their event listeners stop receiving events
+    pub fn disable_plugin(&self, name: &str) -> bool {
+        self.plugins.disable(self, name)
+    }
+
+    fn enable_plugins(&mut self) {
+        for mut plugin in self.plugins.take_pending() {
+            let name = plugin.name().to_string();
+
+            if let Some(dep) = plugin.depends().into_iter().find(|o| !self.plugins.is_enabled(o)) {
+                error!("plugin {} not enabled: dependency {} is not enabled", name, dep);
+                continue;
+            }
+
+            let start = self.event_listeners.len();
+
+            match plugin.on_enable(self) {
+                Ok(_) => {
+                    let end = self.event_listeners.len();
+                    self.plugins.push_loaded(plugin, start..end);
+                    info!("plugin {} enabled", name);
+                }
+                Err(e) => {
+                    self.event_listeners.truncate(start);
+                    self.listener_order.write().clear();
+                    error!("plugin {} enable error: {}", name, e);
+                }
+            }
+        }
     }
 
     pub fn trigger_event<T: Event + 'static>(&self, event: &mut T) -> Result<(), ProxyError> { 
-        for listener in &self.event_listeners {
-            if let Some(listener) = 
-                    listener.as_any_ref().downcast_ref::<Box<dyn EventListener<T> + Send + Sync + 'static>>() { 
-                listener.on_event(event)?;
+        for index in self.listener_order::<T>() {
+            if self.plugins.is_listener_disabled(index) || self.is_listener_panicked(index) {
+                continue;
+            }
+
+            if let Some(listener) = self.event_listener::<T>(index) {
+                let start = Instant::now();
+                // panic in one listener must not stop the others and the relay loop calling them
+                let result = panic::catch_unwind(AssertUnwindSafe(|| listener.on_event(event)));
+                self.record_listener_timing(index, event.name(), start.elapsed());
+                match result {
+                    Ok(result) => result?,
+                    Err(payload) => self.record_listener_panic(index, listener.order().label, &event.name(), payload)
+                }
             }
         }
         Ok(())
     }
 
-    pub async fn get_players(&self) -> RwLockReadGuard<'_, Vec<Player>> {
-        self.players.read().unwrap()
+    fn is_listener_panicked(&self, index: usize) -> bool {
+        let Some(limit) = self.config().listener_panic_limit else {
+            return false;
+        };
+        self.listener_panics.lock().get(&index).is_some_and(|o| *o >= limit)
+    }
+
+    fn record_listener_panic(&self, index: usize, label: Option<String>, event: &str, payload: Box<dyn Any + Send>) {
+        let message = payload.downcast_ref::<&str>().map(|o| o.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+
+        let mut name = format!("#{}", index);
+        if let Some(label) = label {
+            name += &format!(" ({})", label);
+        }
+        if let Some(plugin) = self.plugins.listener_plugin(index) {
+            name += &format!(" of plugin {}", plugin);
+        }
+
+        error!("event listener {} panicked handling {} event: {}", name, event, message);
+
+        let mut panics = self.listener_panics.lock();
+        let count = panics.entry(index).or_insert(0);
+        *count += 1;
+
+        if self.config().listener_panic_limit == Some(*count) {
+            error!("event listener {} disabled after {} panics", name, count);
+        }
+    }
+
+    fn event_listener<T: Event + 'static>(&self, index: usize) -> Option<&(dyn EventListener<T> + Send + Sync)> {
+        self.event_listeners.get(index)?
+            .downcast_ref::<Box<dyn EventListener<T> + Send + Sync + 'static>>()
+            .map(|o| o.as_ref())
+    }
+
+    /// Indexes of listeners of event type in calling order
+    fn listener_order<T: Event + 'static>(&self) -> Vec<usize> {
+        if let Some(order) = self.listener_order.read().get(&TypeId::of::<T>()) {
+            return order.clone();
+        }
+
+        let order = sort_listeners((0..self.event_listeners.len())
+            .filter_map(|i| Some((i, self.event_listener::<T>(i)?.order())))
+            .collect());
+        self.listener_order.write().insert(TypeId::of::<T>(), order.clone());
+        order
+    }
+
+    /// Fires ProxyErrorEvent, listener errors are only logged
+    pub fn report_error(&self, player: Option<&str>, server: Option<&str>, direction: Option<PacketDirection>, error: &ProxyError) {
+        let mut event = ProxyErrorEvent::builder()
+            .player(player.map(|o| o.to_string()))
+            .server(server.map(|o| o.to_string()))
+            .direction(direction)
+            .kind(error.kind())
+            .error(error.to_string())
+            .build();
+
+        if let Err(e) = self.trigger_event(&mut event) {
+            warn!("proxy error event listener error: {}", e);
+        }
+
+        self.record_event(ProxyEventRecord::Error {
+            player: player.map(|o| o.to_string()),
+            server: server.map(|o| o.to_string()),
+            kind: error.kind().to_string(),
+            error: error.to_string()
+        });
+    }
+
+    fn record_listener_timing(&self, listener: usize, event: String, elapsed: Duration) {
+        if let Some(warning) = self.config().slow_listener_warning {
+            if elapsed > warning {
+                warn!("event listener #{} took {:?} to handle {} event", listener, elapsed, event);
+            }
+        }
+
+        self.listener_timings.lock()
+            .entry((listener, event.clone()))
+            .or_insert_with(|| ListenerTiming::new(listener, event))
+            .record(elapsed);
+    }
+
+    /// Sends record to every subscriber, dropping the ones that hung up
+    pub fn record_event(&self, record: ProxyEventRecord) {
+        let capacity = self.config().event_history;
+        if capacity > 0 && !matches!(record, ProxyEventRecord::Status { .. }) {
+            let mut history = self.event_history.lock();
+            history.push_back(TimedRecord { time: storage::now(), record: record.clone() });
+            while history.len() > capacity {
+                history.pop_front();
+            }
+        }
+
+        self.record_subscribers.lock()
+            .retain(|o| o.send(record.clone()).is_ok());
+    }
+
+    /// Last events kept in memory, oldest first
+    pub fn recent_events(&self, last: usize) -> Vec<TimedRecord> {
+        let history = self.event_history.lock();
+        history.iter().skip(history.len().saturating_sub(last)).cloned().collect()
+    }
+
+    /// Channel receiving copy of every proxy event recorded from now on, for observing events from other threads
+    /// without event listeners. Subscription ends when receiver is dropped
+    pub fn subscribe(&self) -> Receiver<ProxyEventRecord> {
+        let (sender, receiver) = mpsc::channel();
+        self.record_subscribers.lock().push(sender);
+        receiver
+    }
+
+    pub fn listener_timings(&self) -> Vec<ListenerTiming> {
+        self.listener_timings.lock().values().cloned().collect()
+    }
+
+    pub fn get_players(&self) -> RwLockReadGuard<'_, Vec<Arc<Player>>> {
+        self.players.read()
+    }
+
+    /// Connected players matching filter, collected so players lock is not held while they are kicked
+    /// or messaged, as kick listeners and placeholder providers can lock it again
+    pub fn connected_players(&self, filter: impl Fn(&Player) -> bool) -> Vec<Arc<Player>> {
+        self.players.read().iter()
+            .filter(|o| o.is_connected() && filter(o))
+            .cloned()
+            .collect()
+    }
+
+    /// Names of connected players by server name, servers are in config order and players are sorted
+    pub fn player_distribution(&self) -> Vec<(String, Vec<String>)> {
+        let mut distribution: Vec<(String, Vec<String>)> = self.config().servers.iter()
+            .map(|o| (o.name.clone(), Vec::new()))
+            .collect();
+
+        for player in self.get_players().iter().filter(|o| o.is_connected()) {
+            let Some(server) = player.server().map(|o| o.name) else {
+                continue;
+            };
+
+            match distribution.iter_mut().find(|o| o.0 == server) {
+                Some(entry) => entry.1.push(player.name.clone()),
+                None => distribution.push((server, vec![player.name.clone()]))
+            }
+        }
+
+        for (_, players) in &mut distribution {
+            players.sort_by_key(|o| o.to_lowercase());
+        }
+
+        distribution
+    }
+
+    pub fn accept_client(self: &Arc<Self>, client_conn: TcpStream) -> Result<(), ProxyError> {
+        let addr = client_conn.peer_addr().map_err(ProxyError::PeerAddr)?;
+        self.accept_client_from(client_conn, addr)
     }
 
-    pub fn accept_client(&self, mut client_conn: TcpStream) -> Result<(), ProxyError> {
-        let addr = client_conn.peer_addr().map_err(|_| ProxyError::PeerAddr)?;
+    /// Accepts client connected from address other than peer of its stream, like clients of tunnels
+    pub fn accept_client_from(self: &Arc<Self>, mut client_conn: TcpStream, addr: SocketAddr) -> Result<(), ProxyError> {
+        let config = self.config();
+
+        if self.ip_blocks.is_blocked(addr.ip()) {
+            debug!("connection {} dropped, ip is blocked", addr);
+            return Ok(());
+        }
+
+        let span = info_span!("connection", ip = %addr, player = field::Empty, uuid = field::Empty, server = field::Empty);
+        let _enter = span.enter();
+
+        // handshake and login have to arrive by deadline, Player::read clears timeout after them
+        let deadline = config.handshake_timeout.map(|o| Instant::now() + o);
+        connection::limit_read(&client_conn, deadline)?;
 
         let mut handshake = read_packet(&mut client_conn, None).as_proxy()?;
 
         if handshake.id() != 0x00 {
-            return Err(ProxyError::HandshakePacket);
+            return Err(ProxyError::HandshakePacket(handshake.id()));
         }
 
-        let protocol_version = handshake.read_u16_varint().as_proxy()?;
-        let server_address = handshake.read_string().as_proxy()?;
+        let raw_protocol = handshake.read_i32_varint().as_proxy()?;
+        // snapshots and versions newer than proxy get through only when tolerated
+        let tolerance = config.snapshot_tolerance.as_ref().filter(|_| !protocol::is_known_protocol(raw_protocol));
+        let protocol_version = match u16::try_from(raw_protocol) {
+            Ok(protocol_version) => protocol_version,
+            Err(_) if tolerance.is_some() => UNKNOWN_PROTOCOL,
+            Err(_) => return Err(ProxyError::UnknownProtocol(raw_protocol))
+        };
+        let raw_address = handshake.read_string().as_proxy()?;
+        let mut address = HandshakeAddress::parse(&raw_address);
+
+        let listener = client_conn.local_addr().ok().and_then(|addr| {
+            config.listener(addr.port()).cloned().or_else(|| self.listening.read().iter()
+                .find(|o| o.addr.port() == addr.port())
+                .map(|o| o.info.clone()))
+        });
+        if let Some(domain) = listener.as_ref().and_then(|o| o.domain.clone()) {
+            address.host = domain;
+        }
+        let server_address = address.host.clone();
         let server_port = handshake.read_unsigned_short().as_proxy()?;
         let next_state = handshake.read_u8_varint().as_proxy()?;
+        // transfers are accepted only when their cookies can be verified
+        let login = next_state == 2 || next_state == TRANSFER_INTENT && config.transfer.is_some();
+
+        if next_state == 1 {
+            self.stats.add_status_request();
+            if config.quiet_status {
+                debug!("status request for {}", server_address);
+            } else {
+                info!("status request for {}", server_address);
+            }
+        }
+
+        // clients often close status connections without ping, not worth an error in quiet mode
+        let status_result = |result: Result<(), ProxyError>| match result {
+            Err(e) if config.quiet_status => {
+                debug!("status connection error: {}", e);
+                Ok(())
+            }
+            result => result
+        };
+
+        let forwarded = if login {
+            match forwarding::read_incoming(&config, &mut address, addr.ip()) {
+                Ok(forwarded) => forwarded,
+                Err(reason) => {
+                    warn!("incoming forwarding rejected: {}", reason);
+                    self.report_error(None, None, None, &ProxyError::Forwarding(reason));
+                    self.login_failed(addr.ip(), &ProxyError::Forwarding(reason));
+                    let mut client_conn = MCConnTcp::new(client_conn);
+                    client_conn.write_packet(&Packet::build(0x00, |p| {
+                        p.write_string(&Component::text(reason).to_json().to_string())
+                    }).as_proxy()?).as_proxy()?;
+                    client_conn.close();
+                    return Ok(());
+                }
+            }
+        } else {
+            None
+        };
+
+        if let (true, Some(message)) = (login, &config.maintenance) {
+            let message = self.render(message, &PlaceholderContext::new().domain(&server_address));
+            let mut client_conn = MCConnTcp::new(client_conn);
+            client_conn.write_packet(&Packet::build(0x00, |p| {
+                p.write_string(&Component::text(message).to_json().to_string())
+            }).as_proxy()?).as_proxy()?;
+            client_conn.close();
+            return Ok(());
+        }
+
+        let routed = match (tolerance, listener.as_ref().and_then(|o| o.server.as_ref())) {
+            (Some(tolerance), _) => config.get_server_by_name(&tolerance.server),
+            (None, Some(server)) => config.get_server_by_name(server),
+            (None, None) => config.route(&server_address)
+        };
+
+        let server = match routed {
+            Some(server) => server,
+            None => {
+                if let (1, Some(status)) = (next_state, &config.fallback_status) {
+                    let status = self.render_status(status, PlaceholderContext::new().domain(&server_address), raw_protocol);
+                    return status_result(self.serve_status(
+                        MCConnTcp::new(client_conn), 
+                        addr, 
+                        status, 
+                        server_address, 
+                        server_port, 
+                        protocol_version
+                    ));
+                }
+                return Err(ProxyError::NoServer(server_address));
+            }
+        };
 
-        let server = self.config
-            .get_server_by_domain(&server_address)
-            .ok_or(ProxyError::ConfigParse)?;
+        let client_ip = forwarded.as_ref().map(|o| o.ip).unwrap_or(addr.ip());
+        let (mut server_conn, server) = match self.connect_backend(&config, server.clone(), login, client_ip) {
+            Ok(connected) => connected,
+            Err(e) => {
+                self.stats.add_connect_failure(&server.name);
+                let offline_status = server.offline_status.as_ref().or(config.offline_status.as_ref());
+                if let (1, Some(status)) = (next_state, offline_status) {
+                    let context = PlaceholderContext::new().domain(&server_address).server(&server.name);
+                    let status = self.render_status(status, context, raw_protocol);
+                    return status_result(self.serve_status(
+                        MCConnTcp::new(client_conn), 
+                        addr, 
+                        status, 
+                        server_address, 
+                        server_port, 
+                        protocol_version
+                    ));
+                }
+                return Err(e);
+            }
+        };
 
-        let mut server_conn = TcpStream::connect(&server.host).map_err(|_| ProxyError::ServerConnect)?;
+        span.record("server", server.name.as_str());
 
-        let handshake = Packet::build(0x00, |handshake| {
-            handshake.write_u16_varint(protocol_version)?;
-            handshake.write_string(&server_address)?;
-            handshake.write_unsigned_short(server_port)?;
-            handshake.write_u8_varint(next_state)?;
+        // login handshake is sent by Player::read, with forwarding data of player
+        if next_state == 1 {
+            let handshake = Packet::build(0x00, |handshake| {
+                handshake.write_i32_varint(raw_protocol)?;
+                handshake.write_string(&address.forward(&server.address_extra))?;
+                handshake.write_unsigned_short(server_port)?;
+                handshake.write_u8_varint(next_state)?;
 
-            Ok(())
-        }).as_proxy()?;
+                Ok(())
+            }).as_proxy()?;
 
-        write_packet(&mut server_conn, None, 0, &handshake).as_proxy()?;
+            write_packet(&mut server_conn, None, 0, &handshake).as_proxy()?;
+        }
 
         let mut client_conn = MCConnTcp::new(client_conn);
         let mut server_conn = MCConnTcp::new(server_conn);
 
         if next_state == 1 {
-            loop {
-                let packet = client_conn.read_packet().as_proxy()?;
-                server_conn.write_packet(&packet).as_proxy()?;
-                if packet.id() == 0x00 {
-                    let motd = server_conn.read_packet().as_proxy()?.read_string().as_proxy()?;
-                    
-                    let mut event = StatusEvent::new(addr.clone(), motd, server_address.clone(), server_port, protocol_version);
-                    self.trigger_event(&mut event)?;
-                    let motd = event.motd();
+            return status_result((|| -> Result<(), ProxyError> {
+                loop {
+                    let mut packet = client_conn.read_packet().as_proxy()?;
+                    server_conn.write_packet(&packet).as_proxy()?;
+                    if packet.id() == 0x01 {
+                        let start = Instant::now();
+                        let payload = packet.read_long().as_proxy()?;
+                        server_conn.read_packet().as_proxy()?;
+                        let latency = start.elapsed();
 
-                    client_conn.write_packet(&Packet::build(0x00, |o| o.write_string(&motd)).as_proxy()?).as_proxy()?;
-                } else {
-                    client_conn.write_packet(&server_conn.read_packet().as_proxy()?).as_proxy()?;
+                        self.record_ping(&server.name, latency);
+
+                        let mut event = ServerStatusPingEvent::new(addr, server.name.clone(), payload, latency);
+                        self.trigger_event(&mut event)?;
+
+                        if !event.is_cancelled() {
+                            client_conn.write_packet(&Packet::build(0x01, |o| o.write_long(*event.payload())).as_proxy()?).as_proxy()?;
+                        }
+                        return Ok(());
+                    } else if packet.id() == 0x00 {
+                        let json = server_conn.read_packet().as_proxy()?.read_string().as_proxy()?;
+
+                        self.record_event(ProxyEventRecord::Status { addr, server_address: server_address.clone() });
+
+                        // garbage from server is replaced by empty status, listeners can still fill it
+                        let mut status = StatusResponse::from_json(&json).unwrap_or_default();
+                        if let Some(count) = &config.player_count {
+                            status.set_online_players(self.network_online(count, &server) as i32);
+                            if let Some(max) = count.max_players {
+                                status.set_max_players(max);
+                            }
+                        }
+
+                        if let Some(motd) = self.next_motd(&config, &server_address) {
+                            let context = PlaceholderContext::new()
+                                .domain(&server_address)
+                                .server(&server.name)
+                                .max(status.players.as_ref().map_or(0, |o| o.max));
+                            status.set_motd(self.render(&motd, &context));
+                        }
+
+                        let mut event = StatusEvent::builder()
+                            .addr(addr)
+                            .status(status)
+                            .server_address(server_address.clone())
+                            .server_port(server_port)
+                            .protocol_version(protocol_version)
+                            .build();
+                        self.trigger_event(&mut event)?;
+                        let json = event.status().to_json();
+
+                        client_conn.write_packet(&Packet::build(0x00, |o| o.write_string(&json)).as_proxy()?).as_proxy()?;
+                    } else {
+                        client_conn.write_packet(&server_conn.read_packet().as_proxy()?).as_proxy()?;
+                    }
                 }
-            }
-        } else if next_state == 2 {
-            self.players.write().unwrap().push(Player::read(
-                &self.config,
-                protocol_version, 
-                server_address, 
-                server_port, 
-                server, 
+            })().with_server(&server.name));
+        } else if login {
+            self.stats.add_connection();
+            let player = match Player::read(
+                self,
+                Handshake { protocol_version, raw_protocol, raw_address, address, port: server_port, next_state, deadline }, 
+                server.clone(), 
                 addr,
+                forwarded,
                 client_conn, 
                 server_conn
-            )?);
+            ) {
+                Ok(player) => player,
+                Err(e) if matches!(e.root(), ProxyError::LoginDenied) => {
+                    // bans, whitelist and screeners count towards ip blocks too
+                    self.login_failed(addr.ip(), &e);
+                    return Ok(());
+                }
+                Err(e) => {
+                    let player = match &e {
+                        ProxyError::Player { player, .. } => Some(player.as_str()),
+                        _ => None
+                    };
+                    self.report_error(player, Some(&server.name), None, &e);
+                    return Err(e);
+                }
+            };
+            self.record_event(ProxyEventRecord::Join {
+                player: player.name.clone(),
+                uuid: player.uuid,
+                addr,
+                server: player.server().map(|o| o.name).unwrap_or(server.name)
+            });
+            let mut players = self.players.write();
+            players.retain(|o| o.is_connected());
+            players.push(Arc::new(player));
         }
 
         Ok(())
     }
 
-    pub fn start(self) {
-        let listener = TcpListener::bind(&self.config.host).expect("invalid host");
+    /// Whether player has permission by groups from permissions file, None is allowed for everyone
+    pub fn has_permission(&self, player: &str, permission: Option<&str>) -> bool {
+        let Some(permission) = permission else {
+            return true;
+        };
+        let uuid = self.get_players().iter()
+            .find(|o| o.name == player)
+            .map(|o| o.uuid);
+        self.config().permissions.has(player, uuid, permission)
+    }
+
+    /// Replaces placeholders in text of config, see [`placeholders::render`]
+    pub fn render(&self, text: &str, context: &PlaceholderContext) -> String {
+        placeholders::render(self, text, context)
+    }
+
+    /// Sends chat message to every player in play state that has permission
+    pub fn broadcast(&self, message: impl Into<Component>, permission: Option<&str>) {
+        let message = message.into();
+        info!("[broadcast] {}", message.to_plain());
+        let permissions = self.config().permissions.clone();
+        for player in self.get_players().iter() {
+            if player.state() == ClientState::Play && permission.is_none_or(|o| permissions.has(&player.name, Some(player.uuid), o)) {
+                player.send_message(message.clone()).ignore();
+            }
+        }
+    }
+
+    /// Sends chat message of player formatted by global chat config to players that have it enabled
+    pub fn global_chat(&self, player: &str, message: &str) {
+        let Some(global_chat) = self.config().global_chat.clone() else {
+            return;
+        };
+
+        let players = self.get_players();
+        let server = players.iter()
+            .find(|o| o.name == player)
+            .and_then(|o| o.server())
+            .map(|o| o.name)
+            .unwrap_or_default();
+        let text = global_chat.format(player, &server, message);
+
+        info!("[global] {}: {}", player, message);
+        for player in players.iter() {
+            if player.state() == ClientState::Play && player.is_global_chat() {
+                player.send_message(text.clone()).ignore();
+            }
+        }
+    }
+
+    /// Sends alert message with title from config to every player
+    pub fn alert(&self, message: &str) {
+        let mut alert = self.config().alert.clone();
+        // template is rendered before message is put in, so alert text is sent as typed
+        alert.format = self.render(&alert.format, &PlaceholderContext::new());
+        self.broadcast(alert.format(message), None);
+
+        if let Some(title) = &alert.title {
+            for player in self.connected_players(|o| o.state() == ClientState::Play) {
+                player.send_title(self.render(title, &PlaceholderContext::of(&player)), message, 10, 70, 20).ignore();
+            }
+        }
+    }
+
+    /// Sends message to players with staff chat permission
+    pub fn staff_chat(&self, sender: &str, message: &str) {
+        let staff_chat = self.config().staff_chat.clone();
+        let text = staff_chat.format(sender, message);
+
+        info!("[staff] {}: {}", sender, message);
+        let permissions = self.config().permissions.clone();
+        for player in self.get_players().iter() {
+            if player.state() == ClientState::Play && permissions.has(&player.name, Some(player.uuid), chat::STAFF_CHAT_PERMISSION) {
+                player.send_message(text.clone()).ignore();
+            }
+        }
+    }
+
+    /// Sends private message to player on any server, returns recipient name or None if they are offline.
+    /// Both parties can `/reply` to each other after it, replies to console are only logged
+    pub fn private_message(&self, sender: &CommandSender, recipient: &str, message: &str) -> Option<String> {
+        let players = self.get_players();
+
+        let recipient = if recipient.eq_ignore_ascii_case(CommandSender::Console.name()) {
+            CommandSender::Console.name().to_string()
+        } else {
+            let player = players.iter().find(|o| o.name.eq_ignore_ascii_case(recipient) && o.is_connected())?;
+            player.send_message(format!("§7[{} -> me] §f{}", sender.name(), message)).ignore();
+            player.set_reply_target(sender.name());
+            player.name.clone()
+        };
+
+        if let CommandSender::Player(name) = sender {
+            if let Some(player) = players.iter().find(|o| &o.name == name) {
+                player.set_reply_target(&recipient);
+            }
+        }
+
+        info!("[msg] {} -> {}: {}", sender.name(), recipient, message);
+
+        Some(recipient)
+    }
+
+    /// Sends configured join message and title to player
+    pub fn send_join_message(&self, uuid: Uuid) {
+        let Some(join) = self.config().join_message.clone() else {
+            return;
+        };
+
+        let player = self.get_players().iter().find(|o| o.uuid == uuid).cloned();
+        let Some(player) = player else {
+            return;
+        };
+        let context = PlaceholderContext::of(&player);
+        let format = |text: &str| self.render(text, &context);
+
+        if let Some(message) = &join.message {
+            player.send_message(format(message)).ignore();
+        }
+
+        if join.title.is_some() || join.subtitle.is_some() {
+            player.send_title(
+                format(join.title.as_deref().unwrap_or_default()),
+                format(join.subtitle.as_deref().unwrap_or_default()),
+                join.fade_in,
+                join.stay,
+                join.fade_out
+            ).ignore();
+        }
+    }
+
+    /// Sets sidebar of every player that has no own sidebar, None shows sidebar from config again
+    pub fn set_sidebar(&self, sidebar: Option<Sidebar>) {
+        *self.sidebar.write() = sidebar;
+        self.update_sidebars();
+    }
 
-        info!("meexprox started on {}", &self.config.host);
+    pub fn sidebar(&self) -> Option<Sidebar> {
+        self.sidebar.read().clone()
+    }
+
+    /// Shows sidebar of player, global one or one from config, rendered with placeholders of player
+    pub fn update_sidebar(&self, player: &Player) {
+        if player.state() != ClientState::Play {
+            return;
+        }
+
+        let config = self.config();
+        let sidebar = player.sidebar()
+            .or_else(|| self.sidebar())
+            .or_else(|| config.sidebar.as_ref().filter(|o| !o.lines.is_empty()).map(|o| {
+                let context = PlaceholderContext::of(player);
+                Sidebar::new(
+                    self.render(&o.title, &context),
+                    o.lines.iter().map(|line| Component::text(self.render(line, &context))).collect()
+                )
+            }));
+        // proxy sidebar waits until backend removes its own
+        let conflict = config.sidebar.as_ref().map(|o| o.conflict).unwrap_or_default();
+        let sidebar = sidebar.filter(|_| conflict == SidebarConflict::Proxy || player.backend_sidebar().is_none());
+
+        player.show_sidebar(sidebar.as_ref()).ignore();
+    }
+
+    pub fn update_sidebar_of(&self, uuid: Uuid) {
+        let player = self.get_players().iter().find(|o| o.uuid == uuid).cloned();
+        if let Some(player) = player {
+            self.update_sidebar(&player);
+        }
+    }
+
+    pub fn update_sidebars(&self) {
+        for player in self.connected_players(|_| true) {
+            self.update_sidebar(&player);
+        }
+    }
+
+    /// Sends latency measured by proxy of every player to players on the same server
+    pub fn update_tab_latency(&self) {
+        let players = self.connected_players(|o| o.state() == ClientState::Play);
+        let mut latencies: HashMap<String, Vec<(Uuid, Duration)>> = HashMap::new();
+
+        for player in players.iter() {
+            if let (Some(server), Some(latency)) = (player.server(), player.latency()) {
+                latencies.entry(server.name).or_default().push((player.uuid, latency));
+            }
+        }
+
+        for player in players.iter() {
+            if let Some(latencies) = player.server().and_then(|o| latencies.get(&o.name)) {
+                player.send_latencies(latencies).ignore();
+            }
+        }
+    }
+
+    /// Players connected to server group (or whole proxy) used in status of server
+    fn network_online(&self, count: &PlayerCount, server: &ServerInfo) -> usize {
+        match count.group_of(&server.name) {
+            Some(group) => group.iter().map(|o| self.online_count_for(o)).sum(),
+            None => self.online_count()
+        }
+    }
+
+    /// Next motd from rotation for domain, if rotation is enabled
+    fn next_motd(&self, config: &ProxyConfig, domain: &str) -> Option<String> {
+        let rotation = config.motd.as_ref()?;
+        let mut counters = self.motd_counters.lock();
+        let counter = counters.entry(domain.to_string()).or_insert(0);
+        let motd = rotation.pick(domain, *counter);
+        *counter = counter.wrapping_add(1);
+        motd
+    }
+
+    /// Sorts members of server group by routing strategy, the preferred one first
+    fn order_candidates(&self, strategy: RoutingStrategy, candidates: &mut [ServerInfo], client_ip: IpAddr) {
+        let location = match strategy {
+            RoutingStrategy::Parallel => return,
+            RoutingStrategy::Nearest => self.geoip.as_ref().and_then(|o| o.locate(client_ip)),
+            RoutingStrategy::LowestRtt => None
+        };
+
+        match location {
+            Some(location) => candidates.sort_by(|a, b| {
+                let distance = |server: &ServerInfo| server.location
+                    .map(|o| o.distance(&location))
+                    .unwrap_or(f64::INFINITY);
+                distance(a).total_cmp(&distance(b))
+            }),
+            // unreachable and not yet checked servers go last
+            None => candidates.sort_by_key(|o| self.server_health(&o.name)
+                .and_then(|o| o.latency)
+                .unwrap_or(Duration::MAX))
+        }
+    }
+
+    /// Connects to server, logins are retried as set in `connect_retry` and sent to fallback server at last
+    fn connect_backend(
+        &self,
+        config: &ProxyConfig,
+        server: ServerInfo,
+        login: bool,
+        client_ip: IpAddr
+    ) -> Result<(TcpStream, ServerInfo), ProxyError> {
+        let retry = config.connect_retry.as_ref().filter(|_| login);
+        let attempts = retry.map(|o| o.attempts).unwrap_or(1).max(1);
+        let mut delay = retry.map(|o| o.delay).unwrap_or_default();
+
+        let mut candidates: Vec<ServerInfo> = std::iter::once(server.clone())
+            .chain(server.try_servers.iter().filter_map(|o| config.get_server_by_name(o)))
+            .collect();
+        self.order_candidates(server.strategy, &mut candidates, client_ip);
+
+        if let Some(pool) = &config.connection_pool {
+            for candidate in &candidates {
+                if let Some(server_conn) = self.pool.take(&candidate.host, pool.max_idle) {
+                    return Ok((server_conn, candidate.clone()));
+                }
+            }
+        }
+
+        let mut error = None;
+        for attempt in 1..=attempts {
+            let connected = match server.strategy {
+                RoutingStrategy::Parallel => connect_first(&candidates, config.try_stagger),
+                _ => connect_ordered(&candidates)
+            };
+            match connected {
+                Ok(connected) => return Ok(connected),
+                Err(e) if attempt < attempts => {
+                    warn!("server {} connect error (attempt {}/{}): {}", server.name, attempt, attempts, e);
+                    thread::sleep(delay);
+                    delay *= 2;
+                }
+                Err(e) => error = Some(e)
+            }
+        }
+
+        let fallback = retry
+            .and_then(|o| o.fallback.as_ref())
+            .and_then(|o| config.get_server_by_name(o))
+            .filter(|o| o.name != server.name);
+
+        if let Some(fallback) = fallback {
+            info!("server {} is unreachable, falling back to {}", server.name, fallback.name);
+            let server_conn = fallback.connect()
+                .map_err(|source| ProxyError::ServerConnect { server: fallback.name.clone(), source })?;
+            return Ok((server_conn, fallback));
+        }
+
+        Err(ProxyError::ServerConnect {
+            server: server.name,
+            source: error.unwrap_or_else(|| io::ErrorKind::NotConnected.into())
+        })
+    }
+
+    /// Status built by proxy with placeholders of motd and version rendered
+    fn render_status(&self, status: &StatusInfo, context: PlaceholderContext, protocol_version: i32) -> StatusResponse {
+        let context = context.max(status.max_players as i32);
+        let mut response = status.to_status(protocol_version);
+        response.set_motd(self.render(&status.motd, &context));
+        if let Some(version) = &mut response.version {
+            version.name = self.render(&version.name, &context);
+        }
+        response
+    }
+
+    fn serve_status(
+        &self,
+        mut client_conn: MCConnTcp,
+        addr: SocketAddr,
+        status: StatusResponse,
+        server_address: String,
+        server_port: u16,
+        protocol_version: u16
+    ) -> Result<(), ProxyError> {
+        loop {
+            let mut packet = client_conn.read_packet().as_proxy()?;
+            match packet.id() {
+                0x00 => {
+                    self.record_event(ProxyEventRecord::Status { addr, server_address: server_address.clone() });
+
+                    let mut event = StatusEvent::builder()
+                        .addr(addr)
+                        .status(status.clone())
+                        .server_address(server_address.clone())
+                        .server_port(server_port)
+                        .protocol_version(protocol_version)
+                        .build();
+                    self.trigger_event(&mut event)?;
+                    let json = event.status().to_json();
+
+                    client_conn.write_packet(&Packet::build(0x00, |o| o.write_string(&json)).as_proxy()?).as_proxy()?;
+                }
+                0x01 => {
+                    let payload = packet.read_long().as_proxy()?;
+                    client_conn.write_packet(&Packet::build(0x01, |o| o.write_long(payload)).as_proxy()?).as_proxy()?;
+                    return Ok(());
+                }
+                id => {
+                    return Err(ProxyError::HandshakePacket(id));
+                }
+            }
+        }
+    }
+
+    /// Disconnects every player and notifies subscribers that proxy is stopping
+    pub fn shutdown(&self) {
+        for player in self.get_players().iter() {
+            player.close();
+        }
+        self.plugins.disable_all(self);
+        self.record_event(ProxyEventRecord::ProxyStop);
+
+        // subscribers stop once their channels close, notifiers send records left in them first
+        self.record_subscribers.lock().clear();
+        let deadline = Instant::now() + NOTIFIER_FLUSH_TIMEOUT;
+        for notifier in self.notifiers.lock().drain(..) {
+            while !notifier.is_finished() && Instant::now() < deadline {
+                thread::sleep(Duration::from_millis(50));
+            }
+        }
+
+        #[cfg(feature = "otel")]
+        if let Some(telemetry) = self.get_service::<super::otel::Telemetry>() {
+            telemetry.shutdown();
+        }
+    }
+
+    /// Enables plugins and starts background services, connections are accepted by listen
+    pub fn init(mut self) -> Arc<MeexProx> {
+        self.enable_plugins();
 
         let self_arc = Arc::new(self);
 
+        if let Some(discord) = self_arc.config().discord.clone() {
+            let notifier = discord::start(self_arc.clone(), discord);
+            self_arc.notifiers.lock().push(notifier);
+        }
+
+        let webhooks = webhook::start(self_arc.clone(), self_arc.config().webhooks.clone());
+        self_arc.notifiers.lock().extend(webhooks);
+        broadcast::start(self_arc.clone());
+
+        if let Some(storage) = self_arc.storage.clone() {
+            storage::start(self_arc.clone(), storage);
+        }
+
+        if let Some(timeout) = self_arc.config().startup_report {
+            self_arc.report_servers(timeout);
+        }
+
+        if let Some(interval) = self_arc.config().health_check_interval {
+            let self_arc = self_arc.clone();
+            thread::spawn(move || {
+                loop {
+                    self_arc.check_servers_health();
+                    thread::sleep(interval);
+                }
+            });
+        }
+
+        pool::start(self_arc.clone());
+
+        // placeholders of sidebars change without any event, so they are rendered again every interval
+        {
+            let self_arc = self_arc.clone();
+            thread::spawn(move || {
+                loop {
+                    let interval = self_arc.config().sidebar.as_ref().map(|o| o.interval).unwrap_or(Duration::from_secs(5));
+                    thread::sleep(interval);
+                    self_arc.update_sidebars();
+                }
+            });
+        }
+
+        // interval is read from config every time, so reload can change, enable or disable it
+        {
+            let self_arc = self_arc.clone();
+            thread::spawn(move || {
+                loop {
+                    let interval = self_arc.config().tab_latency_interval;
+                    thread::sleep(interval.unwrap_or(Duration::from_secs(1)));
+                    if interval.is_some() && self_arc.config().tab_latency_interval.is_some() {
+                        self_arc.update_tab_latency();
+                    }
+                }
+            });
+        }
+
+        // totals for throughput of stats command
+        {
+            let self_arc = self_arc.clone();
+            thread::spawn(move || {
+                loop {
+                    self_arc.stats().sample();
+                    thread::sleep(Duration::from_secs(1));
+                }
+            });
+        }
+
+        if let Some(interval) = self_arc.config().status_summary_interval {
+            let self_arc = self_arc.clone();
+            thread::spawn(move || {
+                let mut last = self_arc.stats().status_requests();
+                loop {
+                    thread::sleep(interval);
+                    let total = self_arc.stats().status_requests();
+                    if self_arc.config().quiet_status && total > last {
+                        info!("{} status requests in last {} seconds", total - last, interval.as_secs());
+                    }
+                    last = total;
+                }
+            });
+        }
+
+        if let Some(admin) = self_arc.config().admin.clone() {
+            admin::start(self_arc.clone(), admin);
+        }
+
+        if let Some(tunnel) = self_arc.config().tunnel.clone() {
+            tunnel::start(self_arc.clone(), tunnel);
+        }
+
+        #[cfg(feature = "grpc")]
+        if let Some(grpc) = self_arc.config().grpc.clone() {
+            super::grpc::start(self_arc.clone(), grpc);
+        }
+
+        self_arc
+    }
+
+    /// Accepts connections on config host, blocks current thread until every listener is removed
+    pub fn listen(self: &Arc<Self>) {
+        let config = self.config();
+        let (listener, stop) = self.bind_listener(ListenerInfo {
+            host: config.host.clone(),
+            server: None,
+            domain: None
+        }).expect("invalid host");
+
+        for extra in &config.listeners {
+            if let Err(e) = self.add_listener(extra.clone()) {
+                error!("{}", e);
+            }
+        }
+
+        info!("meexprox started on {}", &config.host);
+
+        self.record_event(ProxyEventRecord::ProxyStart { host: config.host.clone() });
+
+        self.accept_loop(listener, stop);
+
+        while !self.listening.read().is_empty() {
+            thread::sleep(Duration::from_secs(1));
+        }
+    }
+
+    /// Accepts connections of listener on `accept_threads` threads, returns when the last one stops
+    fn accept_loop(self: &Arc<Self>, listener: TcpListener, stop: Arc<AtomicBool>) {
+        for _ in 1..self.workers.config().accept_threads {
+            match listener.try_clone() {
+                Ok(listener) => {
+                    let self_arc = self.clone();
+                    let stop = stop.clone();
+                    self.workers.spawn_acceptor(move || self_arc.accept_incoming(listener, stop));
+                }
+                Err(e) => error!("listener clone error: {}", e)
+            }
+        }
+        self.accept_incoming(listener, stop);
+    }
+
+    fn accept_incoming(self: &Arc<Self>, listener: TcpListener, stop: Arc<AtomicBool>) {
         for client in listener.incoming() {
-            if let Ok(client) = client {
-                let self_arc = self_arc.clone();
-                thread::spawn(move || {
-                    match self_arc.accept_client(client) {
-                        Ok(_) => {}
-                        Err(e) => {
-                            error!("connection error: {:?}", e);
-                            
-                        }
-                    };
-                });
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+            let Ok(client) = client else {
+                continue;
+            };
+            match client.peer_addr() {
+                Ok(addr) => self.serve_client(client, addr),
+                Err(e) => error!("connection error: {}", ProxyError::PeerAddr(e))
+            }
+        }
+    }
+
+    /// Serves client connected from address on worker, errors are logged and counted as failed logins
+    pub(crate) fn serve_client(self: &Arc<Self>, client: TcpStream, addr: SocketAddr) {
+        let self_arc = self.clone();
+        self.workers.execute(move || {
+            if let Err(e) = self_arc.accept_client_from(client, addr) {
+                error!("connection {} error: {}", addr, e);
+                self_arc.login_failed(addr.ip(), &e);
             }
+        });
+    }
+
+    pub fn start(self) {
+        self.init().listen();
+    }
+}
+
+/// Connects to candidates one by one, returns the first that accepted connection
+fn connect_ordered(candidates: &[ServerInfo]) -> Result<(TcpStream, ServerInfo), io::Error> {
+    let mut error = io::Error::new(io::ErrorKind::NotFound, "no servers to connect");
+    for server in candidates {
+        match server.connect() {
+            Ok(server_conn) => return Ok((server_conn, server.clone())),
+            Err(e) => error = e
         }
     }
-}
\ No newline at end of file
+    Err(error)
+}
+
+/// Connects to candidates one after another with stagger, without waiting for previous ones,
+/// first established connection is returned and attempts not started yet are cancelled
+fn connect_first(candidates: &[ServerInfo], stagger: Duration) -> Result<(TcpStream, ServerInfo), io::Error> {
+    if let [server] = candidates {
+        return server.connect().map(|o| (o, server.clone()));
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let done = Arc::new(AtomicBool::new(false));
+
+    for (i, server) in candidates.iter().cloned().enumerate() {
+        let sender = sender.clone();
+        let done = done.clone();
+        thread::spawn(move || {
+            thread::sleep(stagger * i as u32);
+            if done.load(Ordering::Relaxed) {
+                return;
+            }
+            sender.send(server.connect().map(|o| (o, server))).ignore();
+        });
+    }
+    drop(sender);
+
+    let mut error = io::Error::new(io::ErrorKind::NotFound, "no servers to connect");
+    for result in receiver {
+        match result {
+            Ok(connected) => {
+                done.store(true, Ordering::Relaxed);
+                return Ok(connected);
+            }
+            Err(e) => error = e
+        }
+    }
+
+    Err(error)
+}
@@ -0,0 +1,290 @@
+use std::{fs, path::Path};
+
+use regex::Regex;
+use serde_yml::{Mapping, Value};
+
+/// Meexprox config converted from config of other proxy, with options that could not be converted
+pub struct Migration {
+    pub config: Mapping,
+    pub warnings: Vec<String>
+}
+
+impl Migration {
+    /// Default meexprox config, converted options replace its values
+    fn new() -> Result<Migration, String> {
+        let config = serde_yml::from_str::<Value>(include_str!("../../config.yml"))
+            .map_err(|e| e.to_string())?
+            .as_mapping()
+            .cloned()
+            .ok_or("default config is not a mapping")?;
+
+        Ok(Migration { config, warnings: Vec::new() })
+    }
+
+    fn set(&mut self, path: &[&str], value: impl Into<Value>) {
+        let (key, parents) = path.split_last().expect("empty config path");
+        let mut map = &mut self.config;
+        for parent in parents {
+            map = map.entry(Value::from(*parent))
+                .or_insert_with(|| Value::Mapping(Mapping::new()))
+                .as_mapping_mut()
+                .expect("config path goes through not a mapping");
+        }
+        map.insert(Value::from(*key), value.into());
+    }
+
+    fn warn(&mut self, warning: impl Into<String>) {
+        self.warnings.push(warning.into());
+    }
+
+    pub fn to_yml(&self, source: &str) -> Result<String, String> {
+        let config = serde_yml::to_string(&self.config).map_err(|e| e.to_string())?;
+        Ok(format!("# migrated from {} config, option descriptions are in default config.yml of meexprox\n{}", source, config))
+    }
+}
+
+/// `&` color codes of bungeecord and velocity configs replaced with `§`
+fn colors(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match chars.peek() {
+            Some(next) if c == '&' && next.is_ascii_alphanumeric() => result.push('§'),
+            _ => result.push(c)
+        }
+    }
+    result
+}
+
+fn server_entry(host: &str, domains: Vec<String>, forwarding: Mapping) -> Value {
+    let mut server = Mapping::new();
+    server.insert("host".into(), host.into());
+    server.insert("domains".into(), Value::Sequence(domains.into_iter().map(Value::from).collect()));
+    server.insert("forwarding".into(), Value::Mapping(forwarding));
+    Value::Mapping(server)
+}
+
+fn forwarding_entry(kind: Option<&str>, secret: Option<&str>) -> Mapping {
+    let mut forwarding = Mapping::new();
+    forwarding.insert("enabled".into(), kind.is_some().into());
+    if let Some(kind) = kind {
+        forwarding.insert("type".into(), kind.into());
+    }
+    if let Some(secret) = secret {
+        forwarding.insert("secret".into(), secret.into());
+    }
+    forwarding
+}
+
+/// Converts BungeeCord `config.yml`: servers, forced hosts, listeners, player limits and ip forwarding
+pub fn from_bungeecord(data: &str) -> Result<Migration, String> {
+    let data = serde_yml::from_str::<Value>(data).map_err(|e| e.to_string())?;
+    let mut migration = Migration::new()?;
+
+    let listeners = data.get("listeners")
+        .and_then(|o| o.as_sequence())
+        .cloned()
+        .unwrap_or_default();
+    let Some((main, extra)) = listeners.split_first() else {
+        return Err("no listeners in bungeecord config".to_string());
+    };
+
+    let ip_forward = data.get("ip_forward").and_then(|o| o.as_bool()).unwrap_or(false);
+    let forwarding = forwarding_entry(ip_forward.then_some("bungeecord"), None);
+
+    let priorities: Vec<String> = main.get("priorities")
+        .and_then(|o| o.as_sequence())
+        .map(|o| o.iter().filter_map(|o| o.as_str()).map(|o| o.to_string()).collect())
+        .unwrap_or_default();
+
+    let forced_hosts: Vec<(String, String)> = listeners.iter()
+        .filter_map(|o| o.get("forced_hosts")?.as_mapping())
+        .flat_map(|o| o.iter())
+        .filter_map(|(k, v)| Some((k.as_str()?.to_string(), v.as_str()?.to_string())))
+        .collect();
+
+    let mut servers = Mapping::new();
+    for (name, server) in data.get("servers").and_then(|o| o.as_mapping()).ok_or("no servers in bungeecord config")? {
+        let name = name.as_str().ok_or("server name is not a string")?;
+        let address = server.get("address").and_then(|o| o.as_str())
+            .ok_or(format!("server {} has no address", name))?;
+
+        let mut domains: Vec<String> = forced_hosts.iter()
+            .filter(|o| o.1 == name)
+            .map(|o| o.0.clone())
+            .collect();
+        if priorities.first().map(|o| o.as_str()) == Some(name) {
+            domains.insert(0, "_".to_string());
+        }
+
+        if server.get("restricted").and_then(|o| o.as_bool()) == Some(true) {
+            migration.warn(format!("server {} is restricted, meexprox has no server permissions", name));
+        }
+
+        servers.insert(name.into(), server_entry(address, domains, forwarding.clone()));
+    }
+    migration.set(&["servers"], Value::Mapping(servers));
+
+    if let Some(fallback) = priorities.get(1) {
+        migration.set(&["connect_retry", "enabled"], true);
+        migration.set(&["connect_retry", "attempts"], 1);
+        migration.set(&["connect_retry", "fallback"], fallback.as_str());
+    }
+    if priorities.len() > 2 {
+        migration.warn("only first two priorities are used, the second one as connect_retry fallback");
+    }
+
+    if let Some(host) = main.get("host").and_then(|o| o.as_str()) {
+        migration.set(&["host"], host);
+    }
+
+    if let Some(motd) = main.get("motd").and_then(|o| o.as_str()) {
+        migration.set(&["motd", "enabled"], true);
+        migration.set(&["motd", "domains"], Value::Mapping(Mapping::from_iter([
+            ("_".into(), Value::Sequence(vec![colors(motd).into()]))
+        ])));
+    }
+
+    if let Some(max_players) = main.get("max_players").and_then(|o| o.as_i64()) {
+        migration.set(&["player_count", "enabled"], true);
+        migration.set(&["player_count", "max_players"], max_players);
+    }
+
+    if data.get("player_limit").and_then(|o| o.as_i64()).is_some_and(|o| o > 0) {
+        migration.warn("player_limit is not enforced by meexprox, max_players is only shown in server list");
+    }
+
+    let mut extra_listeners = Vec::new();
+    for listener in extra {
+        let Some(host) = listener.get("host").and_then(|o| o.as_str()) else {
+            continue;
+        };
+        let mut entry = Mapping::new();
+        entry.insert("host".into(), host.into());
+        match listener.get("priorities").and_then(|o| o.as_sequence()).and_then(|o| o.first()?.as_str()) {
+            Some(server) => {
+                entry.insert("server".into(), server.into());
+            }
+            None => migration.warn(format!("listener {} has no priorities, it routes by domain", host))
+        }
+        extra_listeners.push(Value::Mapping(entry));
+    }
+    migration.set(&["listeners"], Value::Sequence(extra_listeners));
+
+    if main.get("proxy_protocol").and_then(|o| o.as_bool()) == Some(true) {
+        migration.warn("proxy_protocol is not supported");
+    }
+
+    Ok(migration)
+}
+
+/// Velocity options without meexprox equivalent, reported when set to value other than default
+const VELOCITY_UNSUPPORTED: &[(&str, &str)] = &[
+    ("online-mode", "true"),
+    ("force-key-authentication", "true"),
+    ("prevent-client-proxy-connections", "false"),
+    ("announce-forge", "false"),
+    ("kick-existing-players", "false"),
+    ("ping-passthrough", "\"DISABLED\""),
+    ("enable-player-address-logging", "true"),
+    ("advanced.haproxy-protocol", "false"),
+    ("advanced.tcp-fast-open", "false"),
+    ("advanced.bungee-plugin-message-channel", "true"),
+    ("query.enabled", "false")
+];
+
+/// Converts Velocity `velocity.toml`: servers, try order, forced hosts, forwarding mode and secret,
+/// secret file is looked up in dir of config
+pub fn from_velocity(data: &str, dir: &Path) -> Result<Migration, String> {
+    let data = toml::from_str::<toml::Table>(data).map_err(|e| e.to_string())?;
+    let mut migration = Migration::new()?;
+
+    let text = |key: &str| data.get(key).and_then(|o| o.as_str()).map(|o| o.to_string());
+
+    let secret = match text("forwarding-secret-file") {
+        Some(file) => fs::read_to_string(dir.join(&file))
+            .map(|o| Some(o.trim().to_string()))
+            .map_err(|e| format!("forwarding secret file {} read error: {}", file, e))?,
+        None => text("forwarding-secret")
+    };
+    let mode = text("player-info-forwarding-mode").unwrap_or("NONE".to_string()).to_uppercase();
+    let forwarding = match mode.as_str() {
+        "MODERN" => forwarding_entry(Some("velocity"), Some(secret.as_deref().ok_or("modern forwarding without secret")?)),
+        "BUNGEEGUARD" => forwarding_entry(Some("bungeecord"), Some(secret.as_deref().ok_or("bungeeguard forwarding without secret")?)),
+        "LEGACY" => forwarding_entry(Some("bungeecord"), None),
+        _ => forwarding_entry(None, None)
+    };
+
+    let servers_table = data.get("servers").and_then(|o| o.as_table()).ok_or("no servers in velocity config")?;
+    let try_order: Vec<String> = servers_table.get("try")
+        .and_then(|o| o.as_array())
+        .map(|o| o.iter().filter_map(|o| o.as_str()).map(|o| o.to_string()).collect())
+        .unwrap_or_default();
+
+    let mut forced_hosts: Vec<(String, String)> = Vec::new();
+    for (host, servers) in data.get("forced-hosts").and_then(|o| o.as_table()).into_iter().flatten() {
+        let servers: Vec<&str> = servers.as_array().into_iter().flatten().filter_map(|o| o.as_str()).collect();
+        if let Some(server) = servers.first() {
+            forced_hosts.push((host.clone(), server.to_string()));
+        }
+        if servers.len() > 1 {
+            migration.warn(format!("forced host {} goes to {} only, fallback servers of forced hosts are not supported", host, servers[0]));
+        }
+    }
+
+    let mut servers = Mapping::new();
+    for (name, address) in servers_table.iter().filter(|o| o.0 != "try") {
+        let address = address.as_str().ok_or(format!("server {} address is not a string", name))?;
+
+        let mut domains: Vec<String> = forced_hosts.iter()
+            .filter(|o| &o.1 == name)
+            .map(|o| o.0.clone())
+            .collect();
+        if try_order.first() == Some(name) {
+            domains.insert(0, "_".to_string());
+        }
+
+        servers.insert(name.as_str().into(), server_entry(address, domains, forwarding.clone()));
+    }
+    migration.set(&["servers"], Value::Mapping(servers));
+
+    if let Some(fallback) = try_order.get(1) {
+        migration.set(&["connect_retry", "enabled"], true);
+        migration.set(&["connect_retry", "attempts"], 1);
+        migration.set(&["connect_retry", "fallback"], fallback.as_str());
+    }
+    if try_order.len() > 2 {
+        migration.warn("only first two servers of try are used, the second one as connect_retry fallback");
+    }
+
+    if let Some(bind) = text("bind") {
+        migration.set(&["host"], bind);
+    }
+
+    if let Some(motd) = text("motd") {
+        // minimessage tags have no legacy equivalent
+        let plain = Regex::new("<[^>]*>").map(|o| o.replace_all(&motd, "").to_string()).unwrap_or(motd);
+        migration.set(&["motd", "enabled"], true);
+        migration.set(&["motd", "domains"], Value::Mapping(Mapping::from_iter([
+            ("_".into(), Value::Sequence(vec![colors(&plain).into()]))
+        ])));
+        migration.warn("motd is converted without minimessage formatting");
+    }
+
+    if let Some(max_players) = data.get("show-max-players").and_then(|o| o.as_integer()) {
+        migration.set(&["player_count", "enabled"], true);
+        migration.set(&["player_count", "max_players"], max_players);
+    }
+
+    for (key, default) in VELOCITY_UNSUPPORTED {
+        let (table, name) = match key.split_once('.') {
+            Some((table, name)) => (data.get(table).and_then(|o| o.as_table()), name),
+            None => (Some(&data), *key)
+        };
+        if let Some(value) = table.and_then(|o| o.get(name)).map(|o| o.to_string()).filter(|o| o != default) {
+            migration.warn(format!("{} = {} has no meexprox equivalent", key, value));
+        }
+    }
+
+    Ok(migration)
+}
@@ -3,5 +3,52 @@ pub mod error;
 pub mod event;
 pub mod meexprox;
 pub mod connection;
+pub mod sanitizer;
+pub mod status;
+pub mod stats;
+pub mod health;
+pub mod admin;
+pub mod record;
+pub mod websocket;
+pub mod discord;
+pub mod webhook;
+pub mod broadcast;
+pub mod chat;
+pub mod plugin;
+pub mod command;
+pub mod commands;
+pub mod permissions;
+pub mod motd;
+pub mod protocol;
+pub mod storage;
+pub mod component;
+pub mod pool;
+pub mod forwarding;
+pub mod geoip;
+pub mod migrate;
+pub mod screener;
+pub mod ipblock;
+pub mod split;
+pub mod workers;
+pub mod builder;
+pub mod online;
+pub mod packets;
+pub mod transfer;
+pub mod dump;
+pub mod tunnel;
+pub mod placeholders;
+pub mod sidebar;
+pub mod query;
+pub mod throttle;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+#[cfg(feature = "sqlx")]
+pub mod sql;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "dashboard")]
+pub mod dashboard;
+#[cfg(feature = "otel")]
+pub mod otel;
 
 pub use meexprox::*;
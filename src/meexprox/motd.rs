@@ -0,0 +1,76 @@
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_yml::Mapping;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MotdStrategy {
+    Random,
+    Sequential,
+    /// Day (UTC) is split into equal parts, one for every motd
+    TimeOfDay
+}
+
+/// Lists of motds by domain, `_` is used for domains not listed
+#[derive(Clone, Debug)]
+pub struct MotdRotation {
+    pub strategy: MotdStrategy,
+    pub domains: HashMap<String, Vec<String>>
+}
+
+impl MotdRotation {
+    pub fn from_data(data: &Mapping) -> Option<MotdRotation> {
+        if !data.get("enabled")?.as_bool()? {
+            return None;
+        }
+
+        let strategy = match data.get("strategy").and_then(|o| o.as_str()).unwrap_or("random") {
+            "random" => MotdStrategy::Random,
+            "sequential" => MotdStrategy::Sequential,
+            "time_of_day" => MotdStrategy::TimeOfDay,
+            _ => return None
+        };
+
+        let domains = data.get("domains")?.as_mapping()?
+            .iter()
+            .filter_map(|(k, v)| Some((
+                k.as_str()?.to_string(),
+                v.as_sequence()?
+                    .iter()
+                    .filter_map(|o| o.as_str())
+                    .map(|o| o.to_string())
+                    .collect::<Vec<String>>()
+            )))
+            .filter(|o| !o.1.is_empty())
+            .collect();
+
+        Some(MotdRotation { strategy, domains })
+    }
+
+    pub fn motds(&self, domain: &str) -> Option<&Vec<String>> {
+        self.domains.get(domain).or(self.domains.get("_"))
+    }
+
+    /// Picks motd for domain, counter is the number of previous picks used by sequential strategy
+    pub fn pick(&self, domain: &str, counter: usize) -> Option<String> {
+        let motds = self.motds(domain)?;
+
+        let index = match self.strategy {
+            MotdStrategy::Random => {
+                let mut bytes = [0; 4];
+                SystemRandom::new().fill(&mut bytes).ok()?;
+                u32::from_le_bytes(bytes) as usize
+            }
+            MotdStrategy::Sequential => counter,
+            MotdStrategy::TimeOfDay => {
+                let seconds = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs() % 86400;
+                seconds as usize * motds.len() / 86400
+            }
+        };
+
+        motds.get(index % motds.len()).cloned()
+    }
+}
@@ -0,0 +1,91 @@
+use std::{
+    collections::{HashMap, HashSet},
+    net::SocketAddr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use parking_lot::Mutex;
+use uuid::Uuid;
+
+/// Days since unix epoch, in utc
+fn today() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|o| o.as_secs() / 86400).unwrap_or_default()
+}
+
+#[derive(Debug, Default)]
+struct Online {
+    /// Server of every relayed connection, by client address
+    players: HashMap<SocketAddr, String>,
+    servers: HashMap<String, usize>,
+    day: u64,
+    uuids_today: HashSet<Uuid>
+}
+
+/// Players online by server, updated when relay of player starts, switches server and stops,
+/// so counts don't need the player list
+#[derive(Debug, Default)]
+pub struct OnlineCounts {
+    online: Mutex<Online>
+}
+
+impl OnlineCounts {
+    pub fn new() -> OnlineCounts {
+        OnlineCounts::default()
+    }
+
+    pub(crate) fn joined(&self, addr: SocketAddr, uuid: Uuid, server: &str) {
+        let mut online = self.online.lock();
+        if let Some(old) = online.players.insert(addr, server.to_string()) {
+            online.remove_from(&old);
+        }
+        *online.servers.entry(server.to_string()).or_default() += 1;
+
+        let day = today();
+        if online.day != day {
+            online.day = day;
+            online.uuids_today.clear();
+        }
+        online.uuids_today.insert(uuid);
+    }
+
+    pub(crate) fn switched(&self, addr: SocketAddr, server: &str) {
+        let mut online = self.online.lock();
+        let Some(old) = online.players.get_mut(&addr).map(|o| std::mem::replace(o, server.to_string())) else {
+            return;
+        };
+        online.remove_from(&old);
+        *online.servers.entry(server.to_string()).or_default() += 1;
+    }
+
+    pub(crate) fn left(&self, addr: SocketAddr) {
+        let mut online = self.online.lock();
+        if let Some(server) = online.players.remove(&addr) {
+            online.remove_from(&server);
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.online.lock().players.len()
+    }
+
+    pub fn server(&self, server: &str) -> usize {
+        self.online.lock().servers.get(server).copied().unwrap_or_default()
+    }
+
+    /// Different players that joined since midnight utc
+    pub fn unique_today(&self) -> usize {
+        let online = self.online.lock();
+        if online.day == today() { online.uuids_today.len() } else { 0 }
+    }
+}
+
+impl Online {
+    fn remove_from(&mut self, server: &str) {
+        if let Some(count) = self.servers.get_mut(server) {
+            *count -= 1;
+            if *count == 0 {
+                self.servers.remove(server);
+            }
+        }
+    }
+}
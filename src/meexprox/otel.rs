@@ -0,0 +1,125 @@
+use std::{env, sync::Arc};
+
+use log::error;
+use opentelemetry::{metrics::MeterProvider as _, trace::TracerProvider as _, KeyValue};
+use opentelemetry_otlp::{MetricExporter, SpanExporter};
+use opentelemetry_sdk::{metrics::SdkMeterProvider, trace::{SdkTracerProvider, Tracer}, Resource};
+
+use super::{stats::ProxyStats, MeexProx};
+
+/// Name, description and serverbound and clientbound values of traffic counter
+type DirectionCounter = (&'static str, &'static str, fn(&ProxyStats) -> (u64, u64));
+
+/// OTLP exporters of traces and metrics, configured by standard `OTEL_*` environment variables
+pub struct Telemetry {
+    tracer_provider: SdkTracerProvider,
+    meter_provider: SdkMeterProvider
+}
+
+impl Telemetry {
+    /// Enabled when `OTEL_EXPORTER_OTLP_ENDPOINT` or `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT` is set
+    /// and `OTEL_SDK_DISABLED` is not `true`
+    pub fn from_env() -> Option<Telemetry> {
+        let configured = env::var_os("OTEL_EXPORTER_OTLP_ENDPOINT").is_some()
+            || env::var_os("OTEL_EXPORTER_OTLP_TRACES_ENDPOINT").is_some();
+        let disabled = env::var("OTEL_SDK_DISABLED").is_ok_and(|o| o.eq_ignore_ascii_case("true"));
+        if !configured || disabled {
+            return None;
+        }
+
+        match Self::build() {
+            Ok(telemetry) => Some(telemetry),
+            Err(e) => {
+                error!("otlp exporter error: {}", e);
+                None
+            }
+        }
+    }
+
+    fn build() -> Result<Telemetry, String> {
+        let mut resource = Resource::builder();
+        if env::var_os("OTEL_SERVICE_NAME").is_none() {
+            resource = resource.with_service_name("meexprox");
+        }
+        let resource = resource.build();
+
+        let span_exporter = SpanExporter::builder()
+            .with_http()
+            .build()
+            .map_err(|e| e.to_string())?;
+        let metric_exporter = MetricExporter::builder()
+            .with_http()
+            .build()
+            .map_err(|e| e.to_string())?;
+
+        Ok(Telemetry {
+            tracer_provider: SdkTracerProvider::builder()
+                .with_resource(resource.clone())
+                .with_batch_exporter(span_exporter)
+                .build(),
+            meter_provider: SdkMeterProvider::builder()
+                .with_resource(resource)
+                .with_periodic_exporter(metric_exporter)
+                .build()
+        })
+    }
+
+    /// Tracer for `tracing_opentelemetry` layer, connection spans are exported with it
+    pub fn tracer(&self) -> Tracer {
+        self.tracer_provider.tracer("meexprox")
+    }
+
+    /// Exports players online and traffic counters of proxy
+    pub fn register_metrics(&self, meexprox: &Arc<MeexProx>) {
+        let meter = self.meter_provider.meter("meexprox");
+
+        let proxy = Arc::downgrade(meexprox);
+        meter.u64_observable_gauge("meexprox.players")
+            .with_description("Players online")
+            .with_callback(move |o| {
+                if let Some(meexprox) = proxy.upgrade() {
+                    o.observe(meexprox.online_count() as u64, &[]);
+                }
+            })
+            .build();
+
+        let proxy = Arc::downgrade(meexprox);
+        meter.u64_observable_counter("meexprox.connections")
+            .with_description("Accepted connections")
+            .with_callback(move |o| {
+                if let Some(meexprox) = proxy.upgrade() {
+                    o.observe(meexprox.stats().connections(), &[]);
+                }
+            })
+            .build();
+
+        let counters: [DirectionCounter; 2] = [
+            ("meexprox.packets", "Relayed packets", |o| (o.packets_serverbound(), o.packets_clientbound())),
+            ("meexprox.bytes", "Relayed bytes", |o| (o.bytes_serverbound(), o.bytes_clientbound()))
+        ];
+
+        for (name, description, values) in counters {
+            let proxy = Arc::downgrade(meexprox);
+            meter.u64_observable_counter(name)
+                .with_description(description)
+                .with_callback(move |o| {
+                    if let Some(meexprox) = proxy.upgrade() {
+                        let (serverbound, clientbound) = values(meexprox.stats());
+                        o.observe(serverbound, &[KeyValue::new("direction", "serverbound")]);
+                        o.observe(clientbound, &[KeyValue::new("direction", "clientbound")]);
+                    }
+                })
+                .build();
+        }
+    }
+
+    /// Exports remaining spans and metrics
+    pub fn shutdown(&self) {
+        if let Err(e) = self.tracer_provider.shutdown() {
+            error!("otlp traces shutdown error: {}", e);
+        }
+        if let Err(e) = self.meter_provider.shutdown() {
+            error!("otlp metrics shutdown error: {}", e);
+        }
+    }
+}
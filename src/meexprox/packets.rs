@@ -0,0 +1,255 @@
+use std::{any::{self, Any, TypeId}, panic::{self, AssertUnwindSafe}, sync::Arc};
+
+use log::error;
+use parking_lot::RwLock;
+use rust_mc_proto::{DataBufferReader, DataBufferWriter, Packet, ProtocolError};
+use uuid::Uuid;
+
+use super::{connection::ClientState, protocol::ProtocolPackets, stats::PacketDirection, MeexProx};
+
+/// Packet with decoded fields, decoded from relayed packet only when handler of it is registered
+pub trait TypedPacket: Sized + 'static {
+    const STATE: ClientState;
+    const DIRECTION: PacketDirection;
+
+    /// Id of packet in protocol version, None if version is not supported
+    fn id(protocol_version: u16) -> Option<u8>;
+
+    fn decode(packet: &mut Packet, protocol_version: u16) -> Result<Self, ProtocolError>;
+}
+
+/// Serverbound login start, supported since 1.20.2
+#[derive(Clone, Debug)]
+pub struct LoginStart {
+    pub name: String,
+    pub uuid: Uuid
+}
+
+impl TypedPacket for LoginStart {
+    const STATE: ClientState = ClientState::Login;
+    const DIRECTION: PacketDirection = PacketDirection::Serverbound;
+
+    fn id(protocol_version: u16) -> Option<u8> {
+        ProtocolPackets::get(protocol_version).map(|_| 0x00)
+    }
+
+    fn decode(packet: &mut Packet, _protocol_version: u16) -> Result<LoginStart, ProtocolError> {
+        Ok(LoginStart { name: packet.read_string()?, uuid: packet.read_uuid()? })
+    }
+}
+
+/// Serverbound chat message, signature fields are not decoded
+#[derive(Clone, Debug)]
+pub struct ChatMessage {
+    pub message: String
+}
+
+impl TypedPacket for ChatMessage {
+    const STATE: ClientState = ClientState::Play;
+    const DIRECTION: PacketDirection = PacketDirection::Serverbound;
+
+    fn id(protocol_version: u16) -> Option<u8> {
+        ProtocolPackets::get(protocol_version).map(|o| o.chat_message)
+    }
+
+    fn decode(packet: &mut Packet, _protocol_version: u16) -> Result<ChatMessage, ProtocolError> {
+        Ok(ChatMessage { message: packet.read_string()? })
+    }
+}
+
+/// Serverbound unsigned chat command, without slash
+#[derive(Clone, Debug)]
+pub struct ChatCommand {
+    pub command: String
+}
+
+impl TypedPacket for ChatCommand {
+    const STATE: ClientState = ClientState::Play;
+    const DIRECTION: PacketDirection = PacketDirection::Serverbound;
+
+    fn id(protocol_version: u16) -> Option<u8> {
+        ProtocolPackets::get(protocol_version).map(|o| o.chat_command)
+    }
+
+    fn decode(packet: &mut Packet, _protocol_version: u16) -> Result<ChatCommand, ProtocolError> {
+        Ok(ChatCommand { command: packet.read_string()? })
+    }
+}
+
+/// Clientbound keep alive in play state
+#[derive(Clone, Debug)]
+pub struct KeepAlive {
+    pub id: i64
+}
+
+impl TypedPacket for KeepAlive {
+    const STATE: ClientState = ClientState::Play;
+    const DIRECTION: PacketDirection = PacketDirection::Clientbound;
+
+    fn id(protocol_version: u16) -> Option<u8> {
+        ProtocolPackets::get(protocol_version).map(|o| o.keep_alive)
+    }
+
+    fn decode(packet: &mut Packet, _protocol_version: u16) -> Result<KeepAlive, ProtocolError> {
+        Ok(KeepAlive { id: packet.read_long()? })
+    }
+}
+
+/// Chat shown to client, set in its chat settings
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChatVisibility {
+    Full,
+    /// Only command feedback and system messages
+    System,
+    Hidden
+}
+
+impl ChatVisibility {
+    pub fn from_id(id: i32) -> Option<ChatVisibility> {
+        match id {
+            0 => Some(ChatVisibility::Full),
+            1 => Some(ChatVisibility::System),
+            2 => Some(ChatVisibility::Hidden),
+            _ => None
+        }
+    }
+
+    pub fn id(&self) -> i32 {
+        match self {
+            ChatVisibility::Full => 0,
+            ChatVisibility::System => 1,
+            ChatVisibility::Hidden => 2
+        }
+    }
+}
+
+/// Serverbound client information, sent in configuration and again when client changes settings
+#[derive(Clone, Debug)]
+pub struct ClientSettings {
+    /// Like `en_us`
+    pub locale: String,
+    /// Chunks
+    pub view_distance: i8,
+    pub chat_visibility: ChatVisibility,
+    pub chat_colors: bool,
+    pub skin_parts: u8,
+    /// 0 for left, 1 for right
+    pub main_hand: i32,
+    pub text_filtering: bool,
+    pub server_listings: bool
+}
+
+impl ClientSettings {
+    pub fn decode(packet: &mut Packet) -> Result<ClientSettings, ProtocolError> {
+        Ok(ClientSettings {
+            locale: packet.read_string()?,
+            view_distance: packet.read_byte()? as i8,
+            chat_visibility: ChatVisibility::from_id(packet.read_i32_varint()?).unwrap_or(ChatVisibility::Full),
+            chat_colors: packet.read_boolean()?,
+            skin_parts: packet.read_byte()?,
+            main_hand: packet.read_i32_varint()?,
+            text_filtering: packet.read_boolean()?,
+            server_listings: packet.read_boolean()?
+        })
+    }
+
+    pub fn write(&self, packet: &mut Packet) -> Result<(), ProtocolError> {
+        packet.write_string(&self.locale)?;
+        packet.write_byte(self.view_distance as u8)?;
+        packet.write_i32_varint(self.chat_visibility.id())?;
+        packet.write_boolean(self.chat_colors)?;
+        packet.write_byte(self.skin_parts)?;
+        packet.write_i32_varint(self.main_hand)?;
+        packet.write_boolean(self.text_filtering)?;
+        packet.write_boolean(self.server_listings)
+    }
+}
+
+/// Player whose packet is handled
+pub struct PacketContext<'a> {
+    pub meexprox: &'a Arc<MeexProx>,
+    pub name: &'a str,
+    pub uuid: Uuid,
+    pub protocol_version: u16
+}
+
+/// Returns whether packet is relayed, packet is dropped if any handler returns false
+type Handler<T> = Box<dyn Fn(&PacketContext, &T) -> bool + Send + Sync>;
+
+/// Handlers of one packet type, decoded once for all of them
+struct PacketEntry {
+    type_id: TypeId,
+    state: ClientState,
+    direction: PacketDirection,
+    id: fn(u16) -> Option<u8>,
+    dispatch: Dispatch,
+    /// `Handler<T>`, downcasted by dispatch
+    handlers: Vec<Arc<dyn Any + Send + Sync>>
+}
+
+type Dispatch = fn(&PacketContext, &Packet, &[Arc<dyn Any + Send + Sync>]) -> bool;
+
+fn dispatch<T: TypedPacket>(context: &PacketContext, packet: &Packet, handlers: &[Arc<dyn Any + Send + Sync>]) -> bool {
+    let Ok(decoded) = T::decode(&mut packet.clone(), context.protocol_version) else {
+        return true;
+    };
+
+    // every handler is called, even after one dropped packet
+    let mut relayed = true;
+    for handler in handlers.iter().filter_map(|o| o.downcast_ref::<Handler<T>>()) {
+        // panic in one handler must not stop the others and the relay loop calling them
+        match panic::catch_unwind(AssertUnwindSafe(|| handler(context, &decoded))) {
+            Ok(result) => relayed &= result,
+            Err(payload) => {
+                let message = payload.downcast_ref::<&str>().map(|o| o.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                error!("packet handler panicked handling {} of player {}: {}", any::type_name::<T>(), context.name, message);
+            }
+        }
+    }
+    relayed
+}
+
+/// Typed packet handlers registered by plugins
+#[derive(Default)]
+pub struct PacketHandlers {
+    entries: RwLock<Vec<PacketEntry>>
+}
+
+impl PacketHandlers {
+    pub fn new() -> PacketHandlers {
+        PacketHandlers::default()
+    }
+
+    pub fn register<T: TypedPacket>(&self, handler: impl Fn(&PacketContext, &T) -> bool + Send + Sync + 'static) {
+        let handler: Handler<T> = Box::new(handler);
+        let mut entries = self.entries.write();
+        match entries.iter_mut().find(|o| o.type_id == TypeId::of::<T>()) {
+            Some(entry) => entry.handlers.push(Arc::new(handler)),
+            None => entries.push(PacketEntry {
+                type_id: TypeId::of::<T>(),
+                state: T::STATE,
+                direction: T::DIRECTION,
+                id: T::id,
+                dispatch: dispatch::<T>,
+                handlers: vec![Arc::new(handler)]
+            })
+        }
+    }
+
+    /// Calls handlers of packet, nothing is decoded if packet has no handlers. Returns whether packet is relayed
+    pub fn handle(&self, context: &PacketContext, state: ClientState, direction: PacketDirection, packet: &Packet) -> bool {
+        // handlers are called without lock, so they can register other handlers
+        let matching: Vec<(Dispatch, Vec<Arc<dyn Any + Send + Sync>>)> = self.entries.read().iter()
+            .filter(|o| o.state == state && o.direction == direction && (o.id)(context.protocol_version) == Some(packet.id()))
+            .map(|o| (o.dispatch, o.handlers.clone()))
+            .collect();
+
+        let mut relayed = true;
+        for (dispatch, handlers) in matching {
+            relayed &= dispatch(context, packet, &handlers);
+        }
+        relayed
+    }
+}
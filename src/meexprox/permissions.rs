@@ -0,0 +1,127 @@
+use std::{collections::{HashMap, HashSet}, fs, path::Path};
+
+use serde_yml::{Mapping, Value};
+use uuid::Uuid;
+
+/// Named set of permission nodes, nodes of inherited groups are included
+#[derive(Clone, Debug, Default)]
+pub struct PermissionGroup {
+    pub inherits: Vec<String>,
+    /// Nodes like `meexprox.command.kick`, `meexprox.command.*` or `*`, denied if prefixed with `-`
+    pub permissions: Vec<String>
+}
+
+impl PermissionGroup {
+    pub fn from_data(data: &Mapping) -> PermissionGroup {
+        PermissionGroup {
+            inherits: string_list(data.get("inherits")),
+            permissions: string_list(data.get("permissions"))
+        }
+    }
+}
+
+/// Groups and their players loaded from `permissions.yml`
+#[derive(Clone, Debug, Default)]
+pub struct Permissions {
+    pub groups: HashMap<String, PermissionGroup>,
+    /// Groups of players that are not listed in `players`
+    pub default_groups: Vec<String>,
+    /// Groups by lowercase player name or uuid
+    pub players: HashMap<String, Vec<String>>
+}
+
+/// Sequence of strings or single string
+fn string_list(data: Option<&Value>) -> Vec<String> {
+    match data {
+        Some(Value::String(value)) => vec![value.clone()],
+        Some(Value::Sequence(values)) => values.iter()
+            .filter_map(|o| o.as_str())
+            .map(|o| o.to_string())
+            .collect(),
+        _ => Vec::new()
+    }
+}
+
+/// Whether node grants permission, `*` at the end matches any rest
+fn node_matches(node: &str, permission: &str) -> bool {
+    match node.strip_suffix('*') {
+        Some(prefix) => permission.starts_with(prefix),
+        None => node == permission
+    }
+}
+
+impl Permissions {
+    pub fn load_yml(data: String) -> Option<Permissions> {
+        let data = serde_yml::from_str::<Value>(&data).ok()?;
+        let data = data.as_mapping()?;
+
+        let groups = match data.get("groups").filter(|o| !o.is_null()) {
+            Some(groups) => groups.as_mapping()?
+                .iter()
+                .filter_map(|(k, v)| Some((k.as_str()?.to_string(), PermissionGroup::from_data(v.as_mapping()?))))
+                .collect(),
+            None => HashMap::new()
+        };
+
+        let players = match data.get("players").filter(|o| !o.is_null()) {
+            Some(players) => players.as_mapping()?
+                .iter()
+                .filter_map(|(k, v)| Some((k.as_str()?.to_lowercase(), string_list(Some(v)))))
+                .collect(),
+            None => HashMap::new()
+        };
+
+        Some(Permissions {
+            groups,
+            default_groups: string_list(data.get("default")),
+            players
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Option<Permissions> {
+        Self::load_yml(fs::read_to_string(path).ok()?)
+    }
+
+    /// Groups of player with inherited ones, each listed once
+    pub fn player_groups(&self, name: &str, uuid: Option<Uuid>) -> Vec<String> {
+        let direct = uuid.and_then(|o| self.players.get(&o.to_string()))
+            .or_else(|| self.players.get(&name.to_lowercase()))
+            .unwrap_or(&self.default_groups);
+
+        let mut groups = Vec::new();
+        let mut visited = HashSet::new();
+        let mut queue: Vec<&String> = direct.iter().rev().collect();
+
+        while let Some(group) = queue.pop() {
+            if !visited.insert(group) {
+                continue;
+            }
+            groups.push(group.clone());
+            if let Some(info) = self.groups.get(group) {
+                queue.extend(info.inherits.iter().rev());
+            }
+        }
+
+        groups
+    }
+
+    /// Whether player has permission, denied nodes win over granted ones
+    pub fn has(&self, name: &str, uuid: Option<Uuid>, permission: &str) -> bool {
+        let mut granted = false;
+
+        for group in self.player_groups(name, uuid) {
+            let Some(group) = self.groups.get(&group) else {
+                continue;
+            };
+            for node in &group.permissions {
+                match node.strip_prefix('-') {
+                    Some(node) if node_matches(node, permission) => return false,
+                    Some(_) => {}
+                    None => granted |= node_matches(node, permission)
+                }
+            }
+        }
+
+        granted
+    }
+}
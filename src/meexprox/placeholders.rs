@@ -0,0 +1,147 @@
+use std::{collections::HashMap, sync::Arc};
+
+use parking_lot::RwLock;
+use uuid::Uuid;
+
+use super::{connection::Player, MeexProx};
+
+/// Values of placeholders known where text is rendered, placeholders without value are left in text
+#[derive(Clone, Debug, Default)]
+pub struct PlaceholderContext {
+    pub player: Option<String>,
+    pub uuid: Option<Uuid>,
+    pub server: Option<String>,
+    /// Server address typed by client
+    pub domain: Option<String>,
+    /// Max players of rendered status, `player_count.max_players` is used if None
+    pub max: Option<i32>
+}
+
+impl PlaceholderContext {
+    pub fn new() -> PlaceholderContext {
+        PlaceholderContext::default()
+    }
+
+    /// Context of player with their name, current server and domain
+    pub fn of(player: &Player) -> PlaceholderContext {
+        PlaceholderContext {
+            player: Some(player.name.clone()),
+            uuid: Some(player.uuid),
+            server: player.server().map(|o| o.name),
+            domain: Some(player.virtual_host().to_string()),
+            max: None
+        }
+    }
+
+    pub fn player(mut self, player: impl Into<String>) -> PlaceholderContext {
+        self.player = Some(player.into());
+        self
+    }
+
+    pub fn uuid(mut self, uuid: Uuid) -> PlaceholderContext {
+        self.uuid = Some(uuid);
+        self
+    }
+
+    pub fn server(mut self, server: impl Into<String>) -> PlaceholderContext {
+        self.server = Some(server.into());
+        self
+    }
+
+    pub fn domain(mut self, domain: impl Into<String>) -> PlaceholderContext {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    pub fn max(mut self, max: i32) -> PlaceholderContext {
+        self.max = Some(max);
+        self
+    }
+}
+
+/// Value of custom placeholder registered by plugin, like rank or party name of player
+pub trait PlaceholderProvider: Send + Sync {
+    /// Called every time text with placeholder is rendered, placeholder is kept as is when None is returned
+    fn resolve(&self, context: &PlaceholderContext) -> Option<String>;
+}
+
+impl<F: Fn(&PlaceholderContext) -> Option<String> + Send + Sync> PlaceholderProvider for F {
+    fn resolve(&self, context: &PlaceholderContext) -> Option<String> {
+        self(context)
+    }
+}
+
+/// Custom placeholders by name, built-in ones can't be replaced
+#[derive(Default)]
+pub struct PlaceholderProviders {
+    providers: RwLock<HashMap<String, Arc<dyn PlaceholderProvider>>>
+}
+
+impl PlaceholderProviders {
+    pub fn new() -> PlaceholderProviders {
+        PlaceholderProviders::default()
+    }
+
+    pub fn register(&self, name: &str, provider: impl PlaceholderProvider + 'static) {
+        self.providers.write().insert(name.to_string(), Arc::new(provider));
+    }
+
+    /// Returns whether placeholder was registered
+    pub fn unregister(&self, name: &str) -> bool {
+        self.providers.write().remove(name).is_some()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.providers.read().keys().cloned().collect()
+    }
+
+    fn get(&self, name: &str) -> Option<Arc<dyn PlaceholderProvider>> {
+        self.providers.read().get(name).cloned()
+    }
+}
+
+fn resolve(meexprox: &MeexProx, name: &str, context: &PlaceholderContext) -> Option<String> {
+    match name {
+        "online" => Some(meexprox.online_count().to_string()),
+        "max" => context.max
+            .or_else(|| meexprox.config().player_count.as_ref().and_then(|o| o.max_players))
+            .map(|o| o.to_string()),
+        "player" => context.player.clone(),
+        "server" => context.server.clone(),
+        "domain" => context.domain.clone(),
+        "proxy_version" => Some(env!("CARGO_PKG_VERSION").to_string()),
+        // lock is not held while provider runs, so it can render text too
+        _ => meexprox.placeholders().get(name)?.resolve(context)
+    }
+}
+
+/// Replaces `{online}`, `{max}`, `{player}`, `{server}`, `{domain}`, `{proxy_version}` and registered placeholders
+/// in text, unknown placeholders and ones without value in context are kept as is
+pub fn render(meexprox: &MeexProx, text: &str, context: &PlaceholderContext) -> String {
+    let mut rendered = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let value = rest[1..].find('}')
+            .map(|end| &rest[1..end + 1])
+            .filter(|name| !name.is_empty() && name.chars().all(|o| o.is_ascii_alphanumeric() || o == '_'))
+            .and_then(|name| Some((name.len() + 2, resolve(meexprox, name, context)?)));
+
+        match value {
+            Some((len, value)) => {
+                rendered.push_str(&value);
+                rest = &rest[len..];
+            }
+            None => {
+                rendered.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    rendered.push_str(rest);
+    rendered
+}
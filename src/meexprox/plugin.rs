@@ -0,0 +1,126 @@
+use std::{
+    ops::Range,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use log::{error, info};
+use parking_lot::Mutex;
+
+use super::{error::ProxyError, MeexProx};
+
+pub trait Plugin: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Names of plugins that have to be enabled before this one
+    fn depends(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Called once before proxy starts, event listeners and services are registered here
+    fn on_enable(&mut self, meexprox: &mut MeexProx) -> Result<(), ProxyError>;
+
+    fn on_disable(&mut self, _meexprox: &MeexProx) {}
+}
+
+struct LoadedPlugin {
+    name: String,
+    depends: Vec<String>,
+    plugin: Mutex<Box<dyn Plugin>>,
+    /// Indexes of event listeners added in on_enable
+    listeners: Range<usize>,
+    enabled: AtomicBool
+}
+
+#[derive(Default)]
+pub struct PluginManager {
+    pending: Vec<Box<dyn Plugin>>,
+    loaded: Vec<LoadedPlugin>
+}
+
+impl PluginManager {
+    pub fn new() -> PluginManager {
+        PluginManager::default()
+    }
+
+    pub(crate) fn add(&mut self, plugin: Box<dyn Plugin>) {
+        self.pending.push(plugin);
+    }
+
+    /// Takes added plugins sorted so that dependencies come first,
+    /// plugins with missing or circular dependencies are dropped
+    pub(crate) fn take_pending(&mut self) -> Vec<Box<dyn Plugin>> {
+        let mut pending = std::mem::take(&mut self.pending);
+        let mut ordered: Vec<Box<dyn Plugin>> = Vec::new();
+
+        while let Some(index) = pending.iter().position(|plugin| {
+            plugin.depends().iter().all(|dep| ordered.iter().any(|o| o.name() == dep))
+        }) {
+            ordered.push(pending.remove(index));
+        }
+
+        for plugin in pending {
+            error!("plugin {} has missing or circular dependencies", plugin.name());
+        }
+
+        ordered
+    }
+
+    pub(crate) fn push_loaded(&mut self, plugin: Box<dyn Plugin>, listeners: Range<usize>) {
+        self.loaded.push(LoadedPlugin {
+            name: plugin.name().to_string(),
+            depends: plugin.depends(),
+            plugin: Mutex::new(plugin),
+            listeners,
+            enabled: AtomicBool::new(true)
+        });
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.loaded.iter().any(|o| o.name == name && o.enabled.load(Ordering::Relaxed))
+    }
+
+    /// Names of loaded plugins with their enabled state, in load order
+    pub fn list(&self) -> Vec<(String, bool)> {
+        self.loaded.iter()
+            .map(|o| (o.name.clone(), o.enabled.load(Ordering::Relaxed)))
+            .collect()
+    }
+
+    /// Whether event listener with this index belongs to disabled plugin
+    pub(crate) fn is_listener_disabled(&self, index: usize) -> bool {
+        self.loaded.iter().any(|o| o.listeners.contains(&index) && !o.enabled.load(Ordering::Relaxed))
+    }
+
+    /// Name of plugin that added event listener with this index
+    pub fn listener_plugin(&self, index: usize) -> Option<&str> {
+        self.loaded.iter()
+            .find(|o| o.listeners.contains(&index))
+            .map(|o| o.name.as_str())
+    }
+
+    /// Disables plugin and every plugin depending on it, returns false if it was not enabled
+    pub(crate) fn disable(&self, meexprox: &MeexProx, name: &str) -> bool {
+        let Some(loaded) = self.loaded.iter().find(|o| o.name == name) else {
+            return false;
+        };
+
+        if !loaded.enabled.swap(false, Ordering::Relaxed) {
+            return false;
+        }
+
+        for dependent in self.loaded.iter().filter(|o| o.depends.iter().any(|o| o == name)) {
+            self.disable(meexprox, &dependent.name);
+        }
+
+        loaded.plugin.lock().on_disable(meexprox);
+        info!("plugin {} disabled", name);
+
+        true
+    }
+
+    pub(crate) fn disable_all(&self, meexprox: &MeexProx) {
+        for loaded in self.loaded.iter().rev() {
+            self.disable(meexprox, &loaded.name);
+        }
+    }
+}
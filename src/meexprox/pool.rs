@@ -0,0 +1,134 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    net::TcpStream,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use log::debug;
+use parking_lot::Mutex;
+use serde_yml::Mapping;
+
+use super::MeexProx;
+
+/// Settings of warm backend connections
+#[derive(Clone, Debug)]
+pub struct PoolConfig {
+    /// Idle connections kept for every server
+    pub size: usize,
+    /// Connections older than this are dropped, backend closes them if nothing is sent
+    pub max_idle: Duration,
+    /// Server names to keep connections for, all servers if empty
+    pub servers: Vec<String>
+}
+
+impl PoolConfig {
+    pub fn from_data(data: &Mapping) -> Option<PoolConfig> {
+        if !data.get("enabled")?.as_bool()? {
+            return None;
+        }
+
+        Some(PoolConfig {
+            size: data.get("size")
+                .and_then(|o| o.as_u64())
+                .unwrap_or(2) as usize,
+            max_idle: Duration::from_secs(data.get("max_idle")
+                .and_then(|o| o.as_u64())
+                .unwrap_or(10)),
+            servers: data.get("servers")
+                .and_then(|o| o.as_sequence())
+                .map(|o| o.iter()
+                    .filter_map(|o| o.as_str())
+                    .map(|o| o.to_string())
+                    .collect())
+                .unwrap_or_default()
+        })
+    }
+}
+
+/// Tcp connections opened in advance, before handshake, by backend host
+#[derive(Default)]
+pub struct ConnectionPool {
+    connections: Mutex<HashMap<String, VecDeque<(TcpStream, Instant)>>>
+}
+
+impl ConnectionPool {
+    pub fn new() -> ConnectionPool {
+        ConnectionPool::default()
+    }
+
+    /// Takes fresh connection to host, if there is one
+    pub fn take(&self, host: &str, max_idle: Duration) -> Option<TcpStream> {
+        let mut connections = self.connections.lock();
+        let queue = connections.get_mut(host)?;
+
+        while let Some((stream, opened)) = queue.pop_front() {
+            if opened.elapsed() < max_idle {
+                return Some(stream);
+            }
+        }
+
+        None
+    }
+
+    /// Drops expired connections and opens new ones up to pool size
+    fn refill(&self, config: &PoolConfig, hosts: &[String]) {
+        {
+            let mut connections = self.connections.lock();
+            connections.retain(|host, _| hosts.contains(host));
+            for queue in connections.values_mut() {
+                queue.retain(|o| o.1.elapsed() < config.max_idle);
+            }
+        }
+
+        for host in hosts {
+            let missing = config.size.saturating_sub(
+                self.connections.lock().get(host).map(|o| o.len()).unwrap_or(0)
+            );
+
+            for _ in 0..missing {
+                match TcpStream::connect(host) {
+                    Ok(stream) => {
+                        self.connections.lock()
+                            .entry(host.clone())
+                            .or_default()
+                            .push_back((stream, Instant::now()));
+                    }
+                    Err(e) => {
+                        debug!("pool connection to {} error: {}", host, e);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    fn clear(&self) {
+        self.connections.lock().clear();
+    }
+}
+
+/// Keeps pool filled in new thread, pool is cleared while disabled in config
+pub fn start(meexprox: Arc<MeexProx>) {
+    thread::spawn(move || {
+        loop {
+            let config = meexprox.config();
+
+            match &config.connection_pool {
+                Some(pool) => {
+                    let hosts: Vec<String> = config.servers.iter()
+                        .filter(|o| pool.servers.is_empty() || pool.servers.iter().any(|name| o.has_name(name)))
+                        // tunneled servers are not connected by host
+                        .filter(|o| o.tunnel.is_none())
+                        .map(|o| o.host.clone())
+                        .collect();
+                    meexprox.pool().refill(pool, &hosts);
+                }
+                None => meexprox.pool().clear()
+            }
+
+            thread::sleep(Duration::from_secs(1));
+        }
+    });
+}
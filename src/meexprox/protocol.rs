@@ -0,0 +1,440 @@
+use std::{fmt, net::IpAddr, time::Instant};
+
+use rust_mc_proto::{DataBufferWriter, Packet, ProtocolError};
+use serde_json::Value;
+use uuid::Uuid;
+
+use super::config::AddressExtra;
+
+/// Packet ids that differ between supported protocol versions
+#[derive(Clone, Copy, Debug)]
+pub struct ProtocolPackets {
+    /// Serverbound in configuration state, switches to play
+    pub finish_configuration_ack: u8,
+    /// Serverbound in play state, switches to configuration
+    pub configuration_ack: u8,
+    pub chat_command: u8,
+    pub signed_chat_command: Option<u8>,
+    pub chat_message: u8,
+    pub command_suggestions_request: u8,
+    /// Clientbound in play state
+    pub system_chat: u8,
+    pub command_suggestions: u8,
+    pub disconnect: u8,
+    /// Clientbound in configuration state
+    pub configuration_disconnect: u8,
+    pub set_subtitle_text: u8,
+    pub set_title_text: u8,
+    pub set_title_times: u8,
+    pub keep_alive: u8,
+    pub player_info_update: u8,
+    /// Clientbound in play state, first packet after configuration
+    pub login: u8,
+    pub clientbound_plugin_message: u8,
+    /// Serverbound in play state
+    pub keep_alive_response: u8,
+    pub plugin_message: u8,
+    pub client_information: u8,
+    /// Serverbound in configuration state
+    pub configuration_plugin_message: u8,
+    pub configuration_client_information: u8,
+    /// Clientbound in play state, since 1.20.5
+    pub store_cookie: Option<u8>,
+    pub transfer: Option<u8>,
+    /// Clientbound in configuration state, since 1.20.5
+    pub configuration_store_cookie: Option<u8>,
+    pub configuration_transfer: Option<u8>,
+    /// Clientbound in play state, scoreboard
+    pub display_objective: Option<u8>,
+    pub update_objectives: Option<u8>,
+    pub update_score: Option<u8>,
+    /// Since 1.20.3, scores are removed by update score before
+    pub reset_score: Option<u8>
+}
+
+impl ProtocolPackets {
+    pub fn get(protocol_version: u16) -> Option<ProtocolPackets> {
+        Some(match protocol_version {
+            764 => ProtocolPackets {
+                finish_configuration_ack: 0x02,
+                configuration_ack: 0x0B,
+                chat_command: 0x04,
+                signed_chat_command: None,
+                chat_message: 0x05,
+                command_suggestions_request: 0x0A,
+                system_chat: 0x67,
+                command_suggestions: 0x10,
+                disconnect: 0x1B,
+                configuration_disconnect: 0x01,
+                set_subtitle_text: 0x61,
+                set_title_text: 0x63,
+                set_title_times: 0x64,
+                keep_alive: 0x24,
+                player_info_update: 0x3C,
+                login: 0x29,
+                clientbound_plugin_message: 0x18,
+                keep_alive_response: 0x14,
+                plugin_message: 0x0F,
+                client_information: 0x09,
+                configuration_plugin_message: 0x01,
+                configuration_client_information: 0x00,
+                store_cookie: None,
+                transfer: None,
+                configuration_store_cookie: None,
+                configuration_transfer: None,
+                display_objective: Some(0x55),
+                update_objectives: Some(0x5C),
+                update_score: Some(0x5F),
+                reset_score: None
+            },
+            765 => ProtocolPackets {
+                finish_configuration_ack: 0x02,
+                configuration_ack: 0x0B,
+                chat_command: 0x04,
+                signed_chat_command: None,
+                chat_message: 0x05,
+                command_suggestions_request: 0x0A,
+                system_chat: 0x69,
+                command_suggestions: 0x10,
+                disconnect: 0x1B,
+                configuration_disconnect: 0x01,
+                set_subtitle_text: 0x63,
+                set_title_text: 0x65,
+                set_title_times: 0x66,
+                keep_alive: 0x24,
+                player_info_update: 0x3C,
+                login: 0x29,
+                clientbound_plugin_message: 0x18,
+                keep_alive_response: 0x15,
+                plugin_message: 0x10,
+                client_information: 0x09,
+                configuration_plugin_message: 0x01,
+                configuration_client_information: 0x00,
+                store_cookie: None,
+                transfer: None,
+                configuration_store_cookie: None,
+                configuration_transfer: None,
+                display_objective: Some(0x57),
+                update_objectives: Some(0x5E),
+                update_score: Some(0x61),
+                reset_score: Some(0x42)
+            },
+            766..=767 => ProtocolPackets {
+                finish_configuration_ack: 0x03,
+                configuration_ack: 0x0C,
+                chat_command: 0x04,
+                signed_chat_command: Some(0x05),
+                chat_message: 0x06,
+                command_suggestions_request: 0x0B,
+                system_chat: 0x6C,
+                command_suggestions: 0x10,
+                disconnect: 0x1D,
+                configuration_disconnect: 0x01,
+                set_subtitle_text: 0x65,
+                set_title_text: 0x67,
+                set_title_times: 0x68,
+                keep_alive: 0x26,
+                player_info_update: 0x3E,
+                login: 0x2B,
+                clientbound_plugin_message: 0x19,
+                keep_alive_response: 0x18,
+                plugin_message: 0x12,
+                client_information: 0x0A,
+                configuration_plugin_message: 0x02,
+                configuration_client_information: 0x00,
+                store_cookie: Some(0x6B),
+                transfer: Some(0x73),
+                configuration_store_cookie: Some(0x0A),
+                configuration_transfer: Some(0x0B),
+                display_objective: Some(0x59),
+                update_objectives: Some(0x60),
+                update_score: Some(0x63),
+                reset_score: Some(0x44)
+            },
+            _ => return None
+        })
+    }
+}
+
+/// Next state of handshake sent by clients transferred from another server, since 1.20.5
+pub const TRANSFER_INTENT: u8 = 3;
+
+/// Protocol version of tolerated clients with version number that doesn't fit, like snapshots
+pub const UNKNOWN_PROTOCOL: u16 = u16::MAX;
+
+/// Set in protocol version of snapshots and pre-releases since 1.16.4, lower bits are their number
+pub const SNAPSHOT_PROTOCOL_BIT: i32 = 0x40000000;
+
+/// Login handshake of client
+#[derive(Clone, Debug)]
+pub struct Handshake {
+    /// [`UNKNOWN_PROTOCOL`] when version sent by client doesn't fit
+    pub protocol_version: u16,
+    /// Protocol version exactly as sent by client, it's also sent to backends
+    pub raw_protocol: i32,
+    /// Server address exactly as sent by client, with extra data
+    pub raw_address: String,
+    /// Address used for routing, host is replaced with domain of listener
+    /// and untrusted forwarding fields are stripped
+    pub address: HandshakeAddress,
+    pub port: u16,
+    /// 2 for login, [`TRANSFER_INTENT`] for login after transfer
+    pub next_state: u8,
+    /// Time login start and forwarding responses have to arrive by, None without `handshake_timeout`
+    pub deadline: Option<Instant>
+}
+
+impl Handshake {
+    /// Host typed by client, without extra data and trailing dot
+    pub fn virtual_host(&self) -> &str {
+        self.raw_address.split('\0').next().unwrap_or_default().trim_end_matches('.')
+    }
+
+    /// Extra data sent by client after first null character
+    pub fn raw_extra(&self) -> Option<&str> {
+        self.raw_address.split_once('\0').map(|o| o.1)
+    }
+
+    pub fn is_transfer(&self) -> bool {
+        self.next_state == TRANSFER_INTENT
+    }
+}
+
+/// Server address from handshake split into hostname and extra data appended by
+/// forge clients (`\0FML2\0`) or upstream proxies (bungeecord `\0ip\0uuid\0properties`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HandshakeAddress {
+    /// Hostname used for routing, without trailing dot
+    pub host: String,
+    /// Everything after first null character
+    pub extra: Option<String>
+}
+
+impl HandshakeAddress {
+    pub fn parse(address: &str) -> HandshakeAddress {
+        let (host, extra) = match address.split_once('\0') {
+            Some((host, extra)) => (host, Some(extra.to_string())),
+            None => (address, None)
+        };
+
+        HandshakeAddress {
+            host: host.trim_end_matches('.').to_string(),
+            extra
+        }
+    }
+
+    /// Null separated fields of extra data
+    pub fn extra_fields(&self) -> Vec<&str> {
+        self.extra.as_deref()
+            .map(|o| o.split('\0').filter(|o| !o.is_empty()).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn is_forge(&self) -> bool {
+        self.extra_fields().iter().any(|o| o.starts_with("FML"))
+    }
+
+    /// Whether field of extra data looks like bungeecord forwarding identity (ip, uuid or properties)
+    fn is_forwarding_field(field: &str) -> bool {
+        field.parse::<IpAddr>().is_ok()
+            || Uuid::try_parse(field).is_ok()
+            || field.starts_with('[') && serde_json::from_str::<Vec<Value>>(field).is_ok()
+    }
+
+    pub fn has_forwarding_fields(&self) -> bool {
+        self.extra_fields().into_iter().any(Self::is_forwarding_field)
+    }
+
+    /// Removes forwarding identity fields, other fields (forge markers) stay
+    pub fn strip_forwarding_fields(&mut self) {
+        let rest: Vec<&str> = self.extra_fields().into_iter().filter(|o| !Self::is_forwarding_field(o)).collect();
+        self.extra = if rest.is_empty() { None } else { Some(rest.join("\0")) };
+    }
+
+    /// Address sent to backend with extra data handled by policy of server
+    pub fn forward(&self, policy: &AddressExtra) -> String {
+        match (policy, &self.extra) {
+            (AddressExtra::Preserve, Some(extra)) => format!("{}\0{}", self.host, extra),
+            (AddressExtra::Rewrite(extra), _) => format!("{}\0{}", self.host, extra),
+            _ => self.host.clone()
+        }
+    }
+}
+
+/// Release versions by protocol version, newest first, generated from `proto/versions.txt`
+static VERSIONS: &[(u16, &[&str])] = include!(concat!(env!("OUT_DIR"), "/versions.rs"));
+
+/// Release versions sharing one protocol version
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VersionRange {
+    pub protocol: u16,
+    /// Oldest first, never empty
+    pub names: &'static [&'static str]
+}
+
+impl VersionRange {
+    pub fn get(protocol_version: u16) -> Option<VersionRange> {
+        VERSIONS.iter()
+            .find(|o| o.0 == protocol_version)
+            .map(|o| VersionRange { protocol: o.0, names: o.1 })
+    }
+
+    pub fn first(&self) -> &'static str {
+        self.names[0]
+    }
+
+    pub fn last(&self) -> &'static str {
+        self.names[self.names.len() - 1]
+    }
+}
+
+impl fmt::Display for VersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.names.len() == 1 {
+            write!(f, "{}", self.first())
+        } else {
+            write!(f, "{}-{}", self.first(), self.last())
+        }
+    }
+}
+
+/// Name of protocol version like `1.20.3-1.20.4`, the number itself if version is unknown
+pub fn version_name(protocol_version: u16) -> String {
+    match VersionRange::get(protocol_version) {
+        Some(range) => range.to_string(),
+        None => format!("protocol {}", protocol_version)
+    }
+}
+
+/// Name of protocol version sent by client, snapshots are named by their number
+pub fn raw_version_name(protocol_version: i32) -> String {
+    match u16::try_from(protocol_version) {
+        Ok(protocol_version) => version_name(protocol_version),
+        Err(_) if protocol_version & SNAPSHOT_PROTOCOL_BIT != 0 => {
+            format!("snapshot protocol {}", protocol_version & !SNAPSHOT_PROTOCOL_BIT)
+        }
+        Err(_) => format!("protocol {}", protocol_version)
+    }
+}
+
+/// Whether protocol version sent by client is in version table
+pub fn is_known_protocol(protocol_version: i32) -> bool {
+    u16::try_from(protocol_version).ok().and_then(VersionRange::get).is_some()
+}
+
+/// Id of clientbound disconnect packet in play state
+pub fn play_disconnect(protocol_version: u16) -> Option<u8> {
+    if let Some(packets) = ProtocolPackets::get(protocol_version) {
+        return Some(packets.disconnect);
+    }
+
+    Some(match protocol_version {
+        340 => 0x1A,         // 1.12.2
+        393..=404 => 0x1B,   // 1.13 - 1.13.2
+        477..=498 => 0x1A,   // 1.14 - 1.14.4
+        573..=578 => 0x1B,   // 1.15 - 1.15.2
+        735..=736 => 0x1A,   // 1.16 - 1.16.1
+        751..=754 => 0x19,   // 1.16.2 - 1.16.5
+        755..=758 => 0x1A,   // 1.17 - 1.18.2
+        759 => 0x17,         // 1.19
+        760 => 0x19,         // 1.19.1 - 1.19.2
+        761 => 0x17,         // 1.19.3
+        762..=763 => 0x1A,   // 1.19.4 - 1.20.1
+        _ => return None
+    })
+}
+
+/// Writes text component as json string before 1.20.3 and as network nbt since
+pub fn write_component(packet: &mut Packet, protocol_version: u16, component: &Value) -> Result<(), ProtocolError> {
+    if protocol_version < 765 {
+        return packet.write_string(&component.to_string());
+    }
+
+    let component = match component {
+        Value::Null => Value::String(String::new()),
+        o => o.clone()
+    };
+
+    packet.write_byte(nbt_tag(&component))?;
+    write_nbt(packet, &component)
+}
+
+fn nbt_tag(value: &Value) -> u8 {
+    match value {
+        Value::Bool(_) => 1,
+        Value::Number(o) if o.as_i64().is_some_and(|o| i32::try_from(o).is_ok()) => 3,
+        Value::Number(_) => 6,
+        Value::String(_) | Value::Null => 8,
+        Value::Array(_) => 9,
+        Value::Object(_) => 10
+    }
+}
+
+fn write_nbt_string(packet: &mut Packet, text: &str) -> Result<(), ProtocolError> {
+    // modified utf-8: null and supplementary characters are encoded as in java
+    let mut bytes = Vec::with_capacity(text.len());
+    for char in text.chars() {
+        let mut units = [0; 2];
+        for unit in char.encode_utf16(&mut units).iter().map(|o| *o as u32) {
+            match unit {
+                0x01..=0x7F => bytes.push(unit as u8),
+                0x00 | 0x80..=0x7FF => {
+                    bytes.push(0xC0 | (unit >> 6) as u8);
+                    bytes.push(0x80 | (unit & 0x3F) as u8);
+                }
+                _ => {
+                    bytes.push(0xE0 | (unit >> 12) as u8);
+                    bytes.push(0x80 | ((unit >> 6) & 0x3F) as u8);
+                    bytes.push(0x80 | (unit & 0x3F) as u8);
+                }
+            }
+        }
+    }
+
+    packet.write_unsigned_short(bytes.len().min(u16::MAX as usize) as u16)?;
+    packet.write_bytes(&bytes[..bytes.len().min(u16::MAX as usize)])
+}
+
+fn write_nbt(packet: &mut Packet, value: &Value) -> Result<(), ProtocolError> {
+    match value {
+        Value::Bool(o) => packet.write_byte(*o as u8),
+        Value::Number(o) => match o.as_i64().and_then(|o| i32::try_from(o).ok()) {
+            Some(o) => packet.write_int(o),
+            None => packet.write_double(o.as_f64().unwrap_or_default())
+        },
+        Value::String(o) => write_nbt_string(packet, o),
+        Value::Null => write_nbt_string(packet, ""),
+        Value::Array(items) => {
+            let tag = items.first().map(nbt_tag).unwrap_or(0);
+
+            if items.iter().all(|o| nbt_tag(o) == tag) {
+                packet.write_byte(tag)?;
+                packet.write_int(items.len() as i32)?;
+                for item in items {
+                    write_nbt(packet, item)?;
+                }
+            } else {
+                // nbt lists have one type, so mixed components are wrapped into compounds
+                packet.write_byte(10)?;
+                packet.write_int(items.len() as i32)?;
+                for item in items {
+                    match item {
+                        Value::Object(_) => write_nbt(packet, item)?,
+                        Value::String(o) => write_nbt(packet, &serde_json::json!({ "text": o }))?,
+                        o => write_nbt(packet, &serde_json::json!({ "text": o.to_string() }))?
+                    }
+                }
+            }
+
+            Ok(())
+        }
+        Value::Object(map) => {
+            for (key, value) in map.iter().filter(|o| !o.1.is_null()) {
+                packet.write_byte(nbt_tag(value))?;
+                write_nbt_string(packet, key)?;
+                write_nbt(packet, value)?;
+            }
+            packet.write_byte(0)
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use rust_mc_proto::{DataBufferReader, DataBufferWriter, Packet, ProtocolError};
+use serde_json::{json, Map, Value};
+
+use super::MeexProx;
+
+/// Plugin message channel of backend queries, requests come in clientbound and answers go serverbound
+pub const QUERY_CHANNEL: &str = "meexprox:query";
+
+/// Json request if packet is plugin message on query channel
+pub fn read_query(mut packet: Packet) -> Option<Value> {
+    if packet.read_string().ok()? != QUERY_CHANNEL {
+        return None;
+    }
+    let len = packet.buffer().len() - packet.buffer().get_rpos();
+    let data = packet.read_bytes(len).ok()?;
+    // malformed queries are answered with error, so backend is not left waiting
+    Some(serde_json::from_slice(&data).unwrap_or(Value::Null))
+}
+
+fn result(meexprox: &MeexProx, request: &Value) -> Result<Value, &'static str> {
+    let string = |key: &str| request.get(key).and_then(|o| o.as_str()).ok_or("missing field");
+
+    match request.get("type").and_then(|o| o.as_str()).ok_or("missing type")? {
+        "player_server" => {
+            let name = string("player")?;
+            let server = meexprox.get_players().iter()
+                .find(|o| o.name.eq_ignore_ascii_case(name) && o.is_connected())
+                .map(|o| o.server().map(|o| o.name));
+            Ok(json!({ "online": server.is_some(), "server": server.flatten() }))
+        }
+        "server_counts" => {
+            let servers: Map<String, Value> = meexprox.config().servers.iter()
+                .map(|o| (o.name.clone(), meexprox.online_count_for(&o.name).into()))
+                .collect();
+            Ok(json!({ "total": meexprox.online_count(), "servers": servers }))
+        }
+        "maintenance" => {
+            let message = meexprox.config().maintenance.clone();
+            Ok(json!({ "enabled": message.is_some(), "message": message }))
+        }
+        _ => Err("unknown type")
+    }
+}
+
+/// Answer with id of request and fields of result, or with error
+pub fn answer(meexprox: &MeexProx, request: &Value) -> Value {
+    let mut answer = match result(meexprox, request) {
+        Ok(Value::Object(fields)) => fields,
+        Ok(_) => Map::new(),
+        Err(e) => Map::from_iter([("error".to_string(), Value::from(e))])
+    };
+    answer.insert("id".to_string(), request.get("id").cloned().unwrap_or(Value::Null));
+    answer.insert("ok".to_string(), Value::from(!answer.contains_key("error")));
+    Value::Object(answer)
+}
+
+/// Serverbound plugin message with answer
+pub fn answer_packet(id: u8, answer: &Value) -> Result<Packet, ProtocolError> {
+    Packet::build(id, |p| {
+        p.write_string(QUERY_CHANNEL)?;
+        p.write_bytes(answer.to_string().as_bytes())
+    })
+}
@@ -0,0 +1,130 @@
+use std::{any::TypeId, net::{IpAddr, SocketAddr}};
+
+use make_event::EventRecord;
+use serde::Serialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+use super::event::{self, IpBlockEvent, PlayerKickEvent, ProxyErrorEvent, StatusEvent};
+
+/// Owned copy of notable proxy event, sent to subscribers outside of listener dispatch
+#[derive(Clone, Debug, Serialize, EventRecord)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ProxyEventRecord {
+    Join {
+        player: String,
+        uuid: Uuid,
+        addr: SocketAddr,
+        server: String
+    },
+    Quit {
+        player: String,
+        uuid: Uuid
+    },
+    Switch {
+        player: String,
+        uuid: Uuid,
+        from: Option<String>,
+        to: String
+    },
+    #[event(PlayerKickEvent)]
+    Kick {
+        player: String,
+        uuid: Uuid,
+        reason: String
+    },
+    #[event(StatusEvent)]
+    Status {
+        addr: SocketAddr,
+        server_address: String
+    },
+    ServerUp {
+        server: String
+    },
+    ServerDown {
+        server: String
+    },
+    #[event(ProxyErrorEvent)]
+    Error {
+        player: Option<String>,
+        server: Option<String>,
+        kind: String,
+        error: String
+    },
+    #[event(IpBlockEvent)]
+    IpBlock {
+        ip: IpAddr,
+        failures: usize,
+        /// Seconds
+        duration: u64
+    },
+    ProxyStart {
+        host: String
+    },
+    ProxyStop
+}
+
+impl ProxyEventRecord {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Replaces `{field}` placeholders in template with record fields
+    pub fn format(&self, template: &str) -> String {
+        let mut text = template.to_string();
+
+        if let Ok(Value::Object(fields)) = serde_json::to_value(self) {
+            for (key, value) in fields {
+                let value = match value {
+                    Value::String(o) => o,
+                    Value::Null => String::new(),
+                    o => o.to_string()
+                };
+                text = text.replace(&format!("{{{}}}", key), &value);
+            }
+        }
+
+        text
+    }
+}
+
+/// Record with unix time it was made at, kept in event history
+#[derive(Clone, Debug, Serialize)]
+pub struct TimedRecord {
+    pub time: i64,
+    #[serde(flatten)]
+    pub record: ProxyEventRecord
+}
+
+/// Record types picked by name, names are record types (`kick`) or names of registered events
+/// the records are copied from (`player_kick`)
+#[derive(Clone, Debug)]
+pub struct EventFilter {
+    names: Vec<String>,
+    events: Vec<TypeId>
+}
+
+impl EventFilter {
+    pub fn new<S: AsRef<str>>(names: impl IntoIterator<Item = S>) -> EventFilter {
+        let names: Vec<String> = names.into_iter().map(|o| o.as_ref().trim().to_string()).collect();
+        EventFilter {
+            events: names.iter().filter_map(|o| event::event_type_id(o)).collect(),
+            names
+        }
+    }
+
+    /// Record type or registered event name
+    pub fn is_known(name: &str) -> bool {
+        ProxyEventRecord::NAMES.contains(&name) || event::event_type_id(name).is_some()
+    }
+
+    /// Names in filter that are neither record types nor registered events
+    pub fn unknown(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(|o| o.as_str()).filter(|o| !Self::is_known(o))
+    }
+
+    pub fn accepts(&self, record: &ProxyEventRecord) -> bool {
+        self.names.iter().any(|o| o == record.name())
+            || record.event_type_id().is_some_and(|o| self.events.contains(&o))
+    }
+}
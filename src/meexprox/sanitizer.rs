@@ -0,0 +1,179 @@
+use rust_mc_proto::{DataBufferReader, Packet, ProtocolError};
+use serde_yml::Mapping;
+
+use super::error::{AsProxyResult, ProxyError};
+
+/// Limits applied to relayed packets to block well-known crash exploits
+#[derive(Clone, Copy, Debug)]
+pub struct Sanitizer {
+    pub max_packet_size: usize,
+    pub max_nbt_depth: usize,
+    pub max_book_pages: usize,
+    pub max_book_page_length: usize,
+    pub max_item_data_length: usize
+}
+
+impl Default for Sanitizer {
+    fn default() -> Self {
+        Sanitizer {
+            max_packet_size: 2097152,
+            max_nbt_depth: 32,
+            max_book_pages: 100,
+            max_book_page_length: 1024,
+            max_item_data_length: 65536
+        }
+    }
+}
+
+impl Sanitizer {
+    pub fn from_data(data: &Mapping) -> Option<Sanitizer> {
+        if !data.get("enabled")?.as_bool()? {
+            return None;
+        }
+
+        let mut sanitizer = Sanitizer::default();
+        let limit = |key: &str, default: usize| -> usize {
+            data.get(key)
+                .and_then(|o| o.as_u64())
+                .map(|o| o as usize)
+                .unwrap_or(default)
+        };
+
+        sanitizer.max_packet_size = limit("max_packet_size", sanitizer.max_packet_size);
+        sanitizer.max_nbt_depth = limit("max_nbt_depth", sanitizer.max_nbt_depth);
+        sanitizer.max_book_pages = limit("max_book_pages", sanitizer.max_book_pages);
+        sanitizer.max_book_page_length = limit("max_book_page_length", sanitizer.max_book_page_length);
+        sanitizer.max_item_data_length = limit("max_item_data_length", sanitizer.max_item_data_length);
+
+        Some(sanitizer)
+    }
+
+    /// Checks packet sent by client to backend server
+    pub fn check_serverbound(&self, protocol_version: u16, packet: &Packet) -> Result<(), ProxyError> {
+        self.check_size(packet)?;
+
+        let (edit_book, creative_slot) = match protocol_version {
+            764 => (0x10, 0x2E),
+            765 => (0x11, 0x2F),
+            766..=767 => (0x14, 0x32),
+            _ => return Ok(())
+        };
+
+        let mut packet = packet.clone();
+
+        let result = if packet.id() == edit_book {
+            self.check_book(&mut packet)
+        } else if packet.id() == creative_slot {
+            self.check_creative_slot(protocol_version, &mut packet)
+        } else {
+            Ok(())
+        };
+
+        // packets that cannot be parsed are treated the same as exceeded limits
+        result.map_err(|_| ProxyError::ExploitPacket(packet.id()))
+    }
+
+    /// Checks packet sent by backend server to client
+    pub fn check_clientbound(&self, _protocol_version: u16, packet: &Packet) -> Result<(), ProxyError> {
+        self.check_size(packet)
+    }
+
+    fn check_size(&self, packet: &Packet) -> Result<(), ProxyError> {
+        if packet.buffer().len() > self.max_packet_size {
+            Err(ProxyError::ExploitPacket(packet.id()))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn check_book(&self, packet: &mut Packet) -> Result<(), ProxyError> {
+        packet.read_usize_varint().as_proxy()?; // slot
+
+        let pages = packet.read_usize_varint().as_proxy()?;
+        if pages > self.max_book_pages {
+            return Err(ProxyError::ExploitPacket(packet.id()));
+        }
+
+        for _ in 0..pages {
+            if packet.read_string().as_proxy()?.chars().count() > self.max_book_page_length {
+                return Err(ProxyError::ExploitPacket(packet.id()));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_creative_slot(&self, protocol_version: u16, packet: &mut Packet) -> Result<(), ProxyError> {
+        packet.read_short().as_proxy()?; // slot index
+
+        if packet.buffer().len() - packet.buffer().get_rpos() > self.max_item_data_length {
+            return Err(ProxyError::ExploitPacket(packet.id()));
+        }
+
+        if protocol_version >= 766 {
+            return Ok(()); // item components cannot be walked without the registries
+        }
+
+        if !packet.read_boolean().as_proxy()? {
+            return Ok(());
+        }
+
+        packet.read_usize_varint().as_proxy()?; // item id
+        packet.read_byte().as_proxy()?; // count
+
+        let tag = packet.read_byte().as_proxy()?;
+        self.check_nbt(packet, tag, 0).as_proxy()
+    }
+
+    fn check_nbt(&self, packet: &mut Packet, tag: u8, depth: usize) -> Result<(), ProtocolError> {
+        if depth > self.max_nbt_depth {
+            return Err(ProtocolError::DataRanOutError);
+        }
+
+        match tag {
+            0 => {}
+            1 => { packet.read_bytes(1)?; }
+            2 => { packet.read_bytes(2)?; }
+            3 | 5 => { packet.read_bytes(4)?; }
+            4 | 6 => { packet.read_bytes(8)?; }
+            7 => {
+                let len = packet.read_int()?.max(0) as usize;
+                packet.read_bytes(len)?;
+            }
+            8 => {
+                let len = packet.read_unsigned_short()? as usize;
+                packet.read_bytes(len)?;
+            }
+            9 => {
+                let item = packet.read_byte()?;
+                let len = packet.read_int()?.max(0);
+                if item == 0 && len > 0 {
+                    return Err(ProtocolError::DataRanOutError);
+                }
+                for _ in 0..len {
+                    self.check_nbt(packet, item, depth + 1)?;
+                }
+            }
+            10 => {
+                loop {
+                    let item = packet.read_byte()?;
+                    if item == 0 { break }
+                    let len = packet.read_unsigned_short()? as usize;
+                    packet.read_bytes(len)?; // name
+                    self.check_nbt(packet, item, depth + 1)?;
+                }
+            }
+            11 => {
+                let len = packet.read_int()?.max(0) as usize;
+                packet.read_bytes(len * 4)?;
+            }
+            12 => {
+                let len = packet.read_int()?.max(0) as usize;
+                packet.read_bytes(len * 8)?;
+            }
+            _ => return Err(ProtocolError::DataRanOutError)
+        }
+
+        Ok(())
+    }
+}
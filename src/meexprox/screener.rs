@@ -0,0 +1,144 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    time::{Duration, Instant},
+};
+
+use log::warn;
+use parking_lot::Mutex;
+use serde_json::Value;
+use serde_yml::Mapping;
+
+/// Checks client ip at login before player connects to any server
+pub trait ConnectionScreener: Send + Sync {
+    /// Called on connection thread of player, so it can block on network requests.
+    /// Returns kick message if connection has to be denied
+    fn screen(&self, ip: IpAddr, name: &str) -> Option<String>;
+}
+
+/// Ips that are never sent to reputation api
+fn is_local(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_loopback() || ip.is_private() || ip.is_link_local(),
+        IpAddr::V6(ip) => ip.is_loopback()
+    }
+}
+
+/// Yaml scalar as string, to compare it with json value
+fn scalar(value: &serde_yml::Value) -> Option<String> {
+    match value {
+        serde_yml::Value::String(o) => Some(o.clone()),
+        serde_yml::Value::Bool(o) => Some(o.to_string()),
+        serde_yml::Value::Number(o) => Some(o.to_string()),
+        _ => None
+    }
+}
+
+/// Settings of built-in http reputation api screener
+#[derive(Clone, Debug)]
+pub struct ScreenerConfig {
+    /// Request url, `{ip}` is replaced
+    pub url: String,
+    /// Json pointer to field of response, like `/{ip}/proxy`, `{ip}` is replaced
+    pub field: String,
+    /// Values of field that deny connection, compared as strings
+    pub deny: Vec<String>,
+    pub message: String,
+    pub cache_time: Duration,
+    pub timeout: Duration,
+    /// Connections are allowed when api can't be reached
+    pub allow_on_error: bool
+}
+
+impl ScreenerConfig {
+    pub fn from_data(data: &Mapping) -> Option<ScreenerConfig> {
+        if !data.get("enabled")?.as_bool()? {
+            return None;
+        }
+
+        Some(ScreenerConfig {
+            url: data.get("url")?.as_str()?.to_string(),
+            field: data.get("field")?.as_str()?.to_string(),
+            deny: match data.get("deny") {
+                Some(serde_yml::Value::Sequence(values)) => values.iter().filter_map(scalar).collect(),
+                Some(value) => vec![scalar(value)?],
+                None => vec!["true".to_string()]
+            },
+            message: data.get("message")
+                .and_then(|o| o.as_str())
+                .unwrap_or("VPN and proxy connections are not allowed")
+                .to_string(),
+            cache_time: Duration::from_secs(data.get("cache_time").and_then(|o| o.as_u64()).unwrap_or(3600)),
+            timeout: Duration::from_millis(data.get("timeout").and_then(|o| o.as_u64()).unwrap_or(3000)),
+            allow_on_error: data.get("allow_on_error").and_then(|o| o.as_bool()).unwrap_or(true)
+        })
+    }
+}
+
+/// Built-in screener asking http reputation api whether ip is vpn or datacenter, answers are cached
+pub struct HttpScreener {
+    config: ScreenerConfig,
+    cache: Mutex<HashMap<IpAddr, (Instant, bool)>>
+}
+
+impl HttpScreener {
+    pub fn new(config: ScreenerConfig) -> HttpScreener {
+        HttpScreener { config, cache: Mutex::new(HashMap::new()) }
+    }
+
+    /// Whether api reports ip as denied
+    fn lookup(&self, ip: IpAddr) -> Result<bool, String> {
+        let ip = ip.to_string();
+        let url = self.config.url.replace("{ip}", &ip);
+
+        let body = ureq::get(&url)
+            .config()
+            .timeout_global(Some(self.config.timeout))
+            .build()
+            .call()
+            .map_err(|e| e.to_string())?
+            .body_mut()
+            .read_to_string()
+            .map_err(|e| e.to_string())?;
+
+        let json: Value = serde_json::from_str(&body).map_err(|e| e.to_string())?;
+        let value = match json.pointer(&self.config.field.replace("{ip}", &ip)) {
+            Some(Value::String(value)) => value.clone(),
+            Some(value) => value.to_string(),
+            None => return Ok(false)
+        };
+
+        Ok(self.config.deny.iter().any(|o| o.eq_ignore_ascii_case(&value)))
+    }
+}
+
+impl ConnectionScreener for HttpScreener {
+    fn screen(&self, ip: IpAddr, name: &str) -> Option<String> {
+        if is_local(ip) {
+            return None;
+        }
+
+        let cached = self.cache.lock()
+            .get(&ip)
+            .filter(|o| o.0.elapsed() < self.config.cache_time)
+            .map(|o| o.1);
+
+        let denied = match cached {
+            Some(denied) => denied,
+            None => match self.lookup(ip) {
+                Ok(denied) => {
+                    let mut cache = self.cache.lock();
+                    cache.retain(|_, o| o.0.elapsed() < self.config.cache_time);
+                    cache.insert(ip, (Instant::now(), denied));
+                    denied
+                }
+                Err(e) => {
+                    warn!("screener lookup of {} ({}) error: {}", ip, name, e);
+                    !self.config.allow_on_error
+                }
+            }
+        };
+
+        denied.then(|| self.config.message.clone())
+    }
+}
@@ -0,0 +1,247 @@
+use std::time::Duration;
+
+use rust_mc_proto::{DataBufferReader, DataBufferWriter, Packet, ProtocolError};
+use serde_yml::Mapping;
+
+use super::{component::Component, protocol::ProtocolPackets};
+
+/// Lines shown by client in sidebar
+pub const MAX_LINES: usize = 15;
+/// Objective of proxy sidebar, backends are not expected to use it
+pub const OBJECTIVE: &str = "meexprox_sidebar";
+/// Display slot of sidebar
+pub const SIDEBAR_SLOT: i32 = 1;
+
+/// Sidebar scoreboard shown by proxy, lines are from top to bottom
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Sidebar {
+    pub title: Component,
+    pub lines: Vec<Component>
+}
+
+impl Sidebar {
+    pub fn new(title: impl Into<Component>, lines: Vec<Component>) -> Sidebar {
+        Sidebar { title: title.into(), lines }
+    }
+}
+
+/// Who keeps sidebar slot when backend displays own objective there
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SidebarConflict {
+    /// Backend sidebar is hidden while proxy shows one, and displayed again after
+    #[default]
+    Proxy,
+    /// Proxy sidebar is hidden while backend shows one
+    Backend
+}
+
+/// Sidebar of every player rendered from config with placeholders
+#[derive(Clone, Debug)]
+pub struct SidebarConfig {
+    pub title: String,
+    /// Sidebar is not shown from config when empty, but conflict and interval still apply to api sidebars
+    pub lines: Vec<String>,
+    pub interval: Duration,
+    pub conflict: SidebarConflict
+}
+
+impl SidebarConfig {
+    pub fn from_data(data: &Mapping) -> Option<SidebarConfig> {
+        if !data.get("enabled")?.as_bool()? {
+            return None;
+        }
+
+        Some(SidebarConfig {
+            title: data.get("title")
+                .and_then(|o| o.as_str())
+                .unwrap_or_default()
+                .to_string(),
+            lines: data.get("lines")
+                .and_then(|o| o.as_sequence())
+                .map(|o| o.iter()
+                    .filter_map(|o| o.as_str())
+                    .take(MAX_LINES)
+                    .map(|o| o.to_string())
+                    .collect())
+                .unwrap_or_default(),
+            interval: Duration::from_secs(data.get("interval").and_then(|o| o.as_u64()).unwrap_or(5).max(1)),
+            conflict: match data.get("conflict").and_then(|o| o.as_str()).unwrap_or("proxy") {
+                "proxy" => SidebarConflict::Proxy,
+                "backend" => SidebarConflict::Backend,
+                _ => return None
+            }
+        })
+    }
+}
+
+/// Sidebar line as score entry
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Entry {
+    name: String,
+    /// Shown instead of entry name since 1.20.3
+    display: Option<Component>,
+    score: i32
+}
+
+/// Entries of lines, scores go down so lines keep their order. Before 1.20.3 the line itself is
+/// the entry name, made unique by invisible color code at its end
+fn entries(sidebar: &Sidebar, protocol_version: u16) -> Vec<Entry> {
+    let count = sidebar.lines.len().min(MAX_LINES);
+    sidebar.lines.iter()
+        .take(MAX_LINES)
+        .enumerate()
+        .map(|(i, line)| {
+            let score = (count - i) as i32;
+            if protocol_version < 765 {
+                let text: String = line.to_plain().chars().take(36).collect();
+                Entry { name: format!("{}§{:x}§r", text, i), display: None, score }
+            } else {
+                Entry { name: format!("§{:x}", i), display: Some(line.clone()), score }
+            }
+        })
+        .collect()
+}
+
+/// Sidebar of player, shared with clientbound relay that watches backend objectives
+#[derive(Debug, Default)]
+pub struct SidebarState {
+    /// Set for this player by api, replaces global sidebar
+    pub own: Option<Sidebar>,
+    /// Shown on client, None while proxy objective is not created
+    shown: Option<Sidebar>,
+    /// Objective backend displayed in sidebar slot
+    backend: Option<String>
+}
+
+impl SidebarState {
+    pub fn is_shown(&self) -> bool {
+        self.shown.is_some()
+    }
+
+    /// Objective backend displayed in sidebar slot
+    pub fn backend(&self) -> Option<&str> {
+        self.backend.as_deref()
+    }
+
+    /// Client scoreboard is cleared on every play login, so after server switch too
+    pub fn reset(&mut self) {
+        self.shown = None;
+        self.backend = None;
+    }
+
+    /// Handles objective displayed by backend, returns whether packet is relayed to client
+    pub fn backend_displayed(&mut self, objective: String, conflict: SidebarConflict) -> bool {
+        self.backend = (!objective.is_empty()).then_some(objective);
+        conflict == SidebarConflict::Backend || self.shown.is_none()
+    }
+
+    /// Forgets objective removed by backend
+    pub fn backend_removed(&mut self, objective: &str) {
+        if self.backend.as_deref() == Some(objective) {
+            self.backend = None;
+        }
+    }
+
+    /// Packets changing shown sidebar to new one, only changed lines are sent
+    pub fn update(&mut self, sidebar: Option<&Sidebar>, packets: &ProtocolPackets, protocol_version: u16) -> Result<Vec<Packet>, ProtocolError> {
+        let (Some(display_objective), Some(update_objectives), Some(update_score)) =
+                (packets.display_objective, packets.update_objectives, packets.update_score) else {
+            return Ok(Vec::new());
+        };
+        if self.shown.as_ref() == sidebar {
+            return Ok(Vec::new());
+        }
+
+        let mut built = Vec::new();
+        let Some(sidebar) = sidebar else {
+            built.push(Packet::build(update_objectives, |p| {
+                p.write_string(OBJECTIVE)?;
+                p.write_byte(1)
+            })?);
+            // sidebar of backend is shown again
+            if let Some(backend) = &self.backend {
+                built.push(Packet::build(display_objective, |p| {
+                    p.write_i32_varint(SIDEBAR_SLOT)?;
+                    p.write_string(backend)
+                })?);
+            }
+            self.shown = None;
+            return Ok(built);
+        };
+
+        let old = self.shown.as_ref().map(|o| entries(o, protocol_version)).unwrap_or_default();
+        let new = entries(sidebar, protocol_version);
+
+        if self.shown.as_ref().is_none_or(|o| o.title != sidebar.title) {
+            let mode = if self.shown.is_none() { 0 } else { 2 };
+            built.push(Packet::build(update_objectives, |p| {
+                p.write_string(OBJECTIVE)?;
+                p.write_byte(mode)?;
+                sidebar.title.write(p, protocol_version)?;
+                p.write_i32_varint(0)?; // integer
+                if protocol_version >= 765 {
+                    // numbers are hidden
+                    p.write_boolean(true)?;
+                    p.write_i32_varint(0)?;
+                }
+                Ok(())
+            })?);
+        }
+
+        for entry in old.iter().filter(|o| !new.iter().any(|n| n.name == o.name)) {
+            built.push(match packets.reset_score {
+                Some(reset_score) => Packet::build(reset_score, |p| {
+                    p.write_string(&entry.name)?;
+                    p.write_boolean(true)?;
+                    p.write_string(OBJECTIVE)
+                })?,
+                None => Packet::build(update_score, |p| {
+                    p.write_string(&entry.name)?;
+                    p.write_i32_varint(1)?; // remove
+                    p.write_string(OBJECTIVE)
+                })?
+            });
+        }
+
+        for entry in new.iter().filter(|o| !old.contains(o)) {
+            built.push(Packet::build(update_score, |p| {
+                p.write_string(&entry.name)?;
+                if protocol_version < 765 {
+                    p.write_i32_varint(0)?; // update
+                    p.write_string(OBJECTIVE)?;
+                    return p.write_i32_varint(entry.score);
+                }
+                p.write_string(OBJECTIVE)?;
+                p.write_i32_varint(entry.score)?;
+                p.write_boolean(entry.display.is_some())?;
+                if let Some(display) = &entry.display {
+                    display.write(p, protocol_version)?;
+                }
+                p.write_boolean(false) // number format of objective
+            })?);
+        }
+
+        if self.shown.is_none() {
+            built.push(Packet::build(display_objective, |p| {
+                p.write_i32_varint(SIDEBAR_SLOT)?;
+                p.write_string(OBJECTIVE)
+            })?);
+        }
+
+        self.shown = Some(sidebar.clone());
+        Ok(built)
+    }
+}
+
+/// Slot and objective of display objective packet
+pub fn read_display_objective(packet: &Packet) -> Result<(i32, String), ProtocolError> {
+    let mut packet = packet.clone();
+    Ok((packet.read_i32_varint()?, packet.read_string()?))
+}
+
+/// Objective removed by update objectives packet
+pub fn read_removed_objective(packet: &Packet) -> Result<Option<String>, ProtocolError> {
+    let mut packet = packet.clone();
+    let objective = packet.read_string()?;
+    Ok((packet.read_byte()? == 1).then_some(objective))
+}
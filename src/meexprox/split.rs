@@ -0,0 +1,237 @@
+use std::{
+    io::{Cursor, IoSlice, Write},
+    net::TcpStream,
+    sync::{Arc, OnceLock},
+    thread,
+    time::Duration,
+};
+
+use parking_lot::{Condvar, Mutex};
+use rust_mc_proto::{MinecraftConnection, MCConnTcp, Packet, ProtocolError};
+use serde_yml::Mapping;
+
+use super::error::{AsProxyResult, ProxyError};
+
+/// Coalescing of queued packets into single vectored write
+#[derive(Clone, Copy, Debug)]
+pub struct WriteBatching {
+    /// Longest time packet waits in queue
+    pub interval: Duration,
+    /// Queue is written at once when it has this many bytes
+    pub max_size: usize
+}
+
+impl WriteBatching {
+    pub fn from_data(data: &Mapping) -> Option<WriteBatching> {
+        if !data.get("enabled")?.as_bool()? {
+            return None;
+        }
+
+        Some(WriteBatching {
+            interval: Duration::from_millis(data.get("interval").and_then(|o| o.as_u64()).unwrap_or(2)),
+            max_size: data.get("max_size").and_then(|o| o.as_u64()).unwrap_or(32768) as usize
+        })
+    }
+}
+
+/// Bytes of batch written at once, priority packets get in between these writes
+const PRIORITY_CHUNK: usize = 16384;
+
+/// Encoded packets waiting for batched write
+#[derive(Default)]
+struct Batch {
+    packets: Vec<Vec<u8>>,
+    size: usize
+}
+
+/// Writes every buffer with as few syscalls as stream allows
+fn write_all_vectored(stream: &mut TcpStream, buffers: &[Vec<u8>]) -> Result<(), ProtocolError> {
+    let mut slices: Vec<IoSlice> = buffers.iter().map(|o| IoSlice::new(o)).collect();
+    let mut slices = &mut slices[..];
+    while !slices.is_empty() {
+        match stream.write_vectored(slices) {
+            Ok(0) => return Err(ProtocolError::ConnectionClosedError),
+            Ok(written) => IoSlice::advance_slices(&mut slices, written),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(_) => return Err(ProtocolError::WriteError)
+        }
+    }
+    Ok(())
+}
+
+/// Connection split into read and write halves with own locks,
+/// so a blocked write never stalls reads and the other way around
+pub struct SplitConn {
+    reader: Mutex<MCConnTcp>,
+    writer: Mutex<MCConnTcp>,
+    /// Used for closing and compression only, never locked during io
+    control: Mutex<MCConnTcp>,
+    batching: OnceLock<WriteBatching>,
+    batch: Mutex<Batch>,
+    /// Encoded packets of proxy written ahead of batch
+    priority: Mutex<Vec<Vec<u8>>>,
+    queued: Condvar
+}
+
+impl SplitConn {
+    pub fn new(mut conn: MCConnTcp) -> Result<SplitConn, ProxyError> {
+        Ok(SplitConn {
+            reader: Mutex::new(conn.try_clone().as_proxy()?),
+            writer: Mutex::new(conn.try_clone().as_proxy()?),
+            control: Mutex::new(conn),
+            batching: OnceLock::new(),
+            batch: Mutex::new(Batch::default()),
+            priority: Mutex::new(Vec::new()),
+            queued: Condvar::new()
+        })
+    }
+
+    /// Replaces every half with new connection, old one has to be closed before
+    pub fn replace(&self, mut conn: MCConnTcp) -> Result<(), ProxyError> {
+        let reader = conn.try_clone().as_proxy()?;
+        let writer = conn.try_clone().as_proxy()?;
+        *self.writer.lock() = writer;
+        *self.reader.lock() = reader;
+        *self.control.lock() = conn;
+        Ok(())
+    }
+
+    /// Own read half for relay thread, reading from it doesn't lock anything
+    pub fn try_clone_reader(&self) -> Result<MCConnTcp, ProxyError> {
+        self.reader.lock().try_clone().as_proxy()
+    }
+
+    pub fn read_packet(&self) -> Result<Packet, ProxyError> {
+        self.reader.lock().read_packet().as_proxy()
+    }
+
+    /// Writes packet right away, queued packets are written before it
+    pub fn write_packet(&self, packet: &Packet) -> Result<(), ProxyError> {
+        let mut writer = self.writer.lock();
+        if self.batching.get().is_some() {
+            let batch = std::mem::take(&mut *self.batch.lock());
+            self.write_batch(&mut writer, &batch.packets)?;
+        }
+        writer.write_packet(packet).as_proxy()
+    }
+
+    /// Writes packet ahead of queued packets, for proxy packets that don't depend on relayed ones like
+    /// keep alives, titles and kicks. While batch is written, packet gets in between its chunks
+    pub fn write_priority_packet(&self, packet: &Packet) -> Result<(), ProxyError> {
+        if self.batching.get().is_none() {
+            return self.write_packet(packet);
+        }
+
+        self.priority.lock().push(self.encode(packet)?);
+        let mut writer = self.writer.lock();
+        self.write_priority(&mut writer)
+    }
+
+    fn write_priority(&self, writer: &mut MCConnTcp) -> Result<(), ProxyError> {
+        let priority = std::mem::take(&mut *self.priority.lock());
+        if priority.is_empty() {
+            return Ok(());
+        }
+        write_all_vectored(writer.get_mut(), &priority).as_proxy()
+    }
+
+    /// Writes batched packets in chunks, with priority packets written before every chunk
+    fn write_batch(&self, writer: &mut MCConnTcp, mut packets: &[Vec<u8>]) -> Result<(), ProxyError> {
+        loop {
+            self.write_priority(writer)?;
+            if packets.is_empty() {
+                return Ok(());
+            }
+
+            let mut size = 0;
+            let count = packets.iter()
+                .take_while(|o| {
+                    size += o.len();
+                    size <= PRIORITY_CHUNK
+                })
+                .count()
+                .max(1);
+            write_all_vectored(writer.get_mut(), &packets[..count]).as_proxy()?;
+            packets = &packets[count..];
+        }
+    }
+
+    /// Packet encoded with current compression
+    fn encode(&self, packet: &Packet) -> Result<Vec<u8>, ProxyError> {
+        let mut encoded = MinecraftConnection::new(Cursor::new(Vec::new()));
+        encoded.set_compression(self.compression());
+        encoded.write_packet(packet).as_proxy()?;
+        Ok(std::mem::take(encoded.get_mut().get_mut()))
+    }
+
+    /// Starts thread writing queued packets every interval, only first call has effect
+    pub fn start_batching(self: &Arc<Self>, batching: WriteBatching) {
+        if self.batching.set(batching).is_err() {
+            return;
+        }
+
+        let conn = Arc::downgrade(self);
+        thread::spawn(move || {
+            while let Some(conn) = conn.upgrade() {
+                {
+                    let mut batch = conn.batch.lock();
+                    if batch.packets.is_empty() {
+                        conn.queued.wait_for(&mut batch, Duration::from_secs(1));
+                    }
+                }
+                if !conn.is_alive() {
+                    break;
+                }
+                thread::sleep(batching.interval);
+                if conn.flush().is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    /// Adds packet to batch written by batching thread, packet is written right away when batching is not started
+    pub fn queue_packet(&self, packet: &Packet) -> Result<(), ProxyError> {
+        let Some(batching) = self.batching.get() else {
+            return self.write_packet(packet);
+        };
+
+        let encoded = self.encode(packet)?;
+
+        let mut batch = self.batch.lock();
+        batch.size += encoded.len();
+        batch.packets.push(encoded);
+        if batch.size >= batching.max_size {
+            drop(batch);
+            return self.flush();
+        }
+        self.queued.notify_one();
+        Ok(())
+    }
+
+    /// Writes queued packets in vectored writes, priority packets first
+    pub fn flush(&self) -> Result<(), ProxyError> {
+        let mut writer = self.writer.lock();
+        let batch = std::mem::take(&mut *self.batch.lock());
+        self.write_batch(&mut writer, &batch.packets)
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.control.lock().is_alive()
+    }
+
+    /// Closes socket, blocked reads and writes of both halves fail
+    pub fn close(&self) {
+        self.control.lock().close();
+    }
+
+    pub fn compression(&self) -> Option<usize> {
+        self.control.lock().compression()
+    }
+
+    pub fn set_compression(&self, threshold: Option<usize>) {
+        self.control.lock().set_compression(threshold);
+        self.reader.lock().set_compression(threshold);
+        self.writer.lock().set_compression(threshold);
+    }
+}
@@ -0,0 +1,398 @@
+use sqlx::{
+    any::{install_default_drivers, AnyPoolOptions, AnyRow},
+    AnyPool, Row,
+};
+use tokio::runtime::Runtime;
+use uuid::Uuid;
+
+use super::storage::{self, Ban, PlayerRecord, Storage, StorageError};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Dialect {
+    Postgres,
+    Mysql
+}
+
+/// Schema changes applied in order, every migration is a list of statements
+const POSTGRES_MIGRATIONS: &[&[&str]] = &[
+    &[
+        "CREATE TABLE IF NOT EXISTS bans (
+            name VARCHAR(64) NOT NULL,
+            uuid VARCHAR(36),
+            reason TEXT NOT NULL,
+            created BIGINT NOT NULL,
+            expires BIGINT
+        )",
+        "CREATE INDEX IF NOT EXISTS bans_name ON bans (LOWER(name))",
+        "CREATE TABLE IF NOT EXISTS whitelist (name VARCHAR(64) PRIMARY KEY)",
+        "CREATE TABLE IF NOT EXISTS players (
+            uuid VARCHAR(36) PRIMARY KEY,
+            name VARCHAR(64) NOT NULL,
+            first_seen BIGINT NOT NULL,
+            last_seen BIGINT NOT NULL,
+            play_time BIGINT NOT NULL DEFAULT 0
+        )",
+        "CREATE INDEX IF NOT EXISTS players_name ON players (LOWER(name))",
+    ],
+    &[
+        "CREATE TABLE IF NOT EXISTS cookies (
+            uuid VARCHAR(36) NOT NULL,
+            cookie VARCHAR(255) NOT NULL,
+            data BYTEA NOT NULL,
+            PRIMARY KEY (uuid, cookie)
+        )",
+    ],
+    &[
+        "CREATE TABLE IF NOT EXISTS server_time (
+            uuid VARCHAR(36) NOT NULL,
+            server VARCHAR(255) NOT NULL,
+            time BIGINT NOT NULL DEFAULT 0,
+            PRIMARY KEY (uuid, server)
+        )",
+    ],
+    &[
+        "CREATE UNIQUE INDEX IF NOT EXISTS whitelist_lower_name ON whitelist (LOWER(name))",
+    ],
+];
+
+const MYSQL_MIGRATIONS: &[&[&str]] = &[
+    &[
+        "CREATE TABLE IF NOT EXISTS bans (
+            name VARCHAR(64) NOT NULL,
+            uuid VARCHAR(36),
+            reason TEXT NOT NULL,
+            created BIGINT NOT NULL,
+            expires BIGINT,
+            INDEX bans_name (name)
+        )",
+        "CREATE TABLE IF NOT EXISTS whitelist (name VARCHAR(64) PRIMARY KEY)",
+        "CREATE TABLE IF NOT EXISTS players (
+            uuid VARCHAR(36) PRIMARY KEY,
+            name VARCHAR(64) NOT NULL,
+            first_seen BIGINT NOT NULL,
+            last_seen BIGINT NOT NULL,
+            play_time BIGINT NOT NULL DEFAULT 0,
+            INDEX players_name (name)
+        )",
+    ],
+    &[
+        "CREATE TABLE IF NOT EXISTS cookies (
+            uuid VARCHAR(36) NOT NULL,
+            cookie VARCHAR(255) NOT NULL,
+            data BLOB NOT NULL,
+            PRIMARY KEY (uuid, cookie)
+        )",
+    ],
+    &[
+        "CREATE TABLE IF NOT EXISTS server_time (
+            uuid VARCHAR(36) NOT NULL,
+            server VARCHAR(255) NOT NULL,
+            time BIGINT NOT NULL DEFAULT 0,
+            PRIMARY KEY (uuid, server)
+        )",
+    ],
+    // names of whitelist are compared ignoring case by default collation, like unique index of postgres does
+    &[],
+];
+
+/// Postgres or MySQL storage, can be shared by several proxies
+pub struct SqlStore {
+    pool: AnyPool,
+    runtime: Runtime,
+    dialect: Dialect
+}
+
+impl SqlStore {
+    pub fn connect(url: &str) -> Result<SqlStore, StorageError> {
+        install_default_drivers();
+
+        let dialect = if url.starts_with("postgres") {
+            Dialect::Postgres
+        } else if url.starts_with("mysql") || url.starts_with("mariadb") {
+            Dialect::Mysql
+        } else {
+            return Err(StorageError("unknown database url scheme".to_string()));
+        };
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .enable_all()
+            .build()
+            .map_err(|e| StorageError(e.to_string()))?;
+
+        let pool = runtime.block_on(AnyPoolOptions::new().max_connections(8).connect(url))?;
+
+        let store = SqlStore { pool, runtime, dialect };
+        store.migrate()?;
+        Ok(store)
+    }
+
+    /// Replaces `?` placeholders with `$1`, `$2`... for postgres
+    fn sql(&self, query: &str) -> String {
+        if self.dialect == Dialect::Mysql {
+            return query.to_string();
+        }
+
+        let mut index = 0;
+        query.chars()
+            .map(|o| if o == '?' {
+                index += 1;
+                format!("${}", index)
+            } else {
+                o.to_string()
+            })
+            .collect()
+    }
+
+    /// Insert that updates row with the same `key` instead of failing, `key` is ignored by mysql
+    /// which updates on any unique key
+    fn upsert(&self, insert: &str, key: &str, update: &str) -> String {
+        self.sql(&match self.dialect {
+            Dialect::Postgres => format!("{} ON CONFLICT ({}) DO UPDATE SET {}", insert, key, update),
+            Dialect::Mysql => format!("{} ON DUPLICATE KEY UPDATE {}", insert, update)
+        })
+    }
+
+    /// Value of column in row that was going to be inserted, inside update of upsert
+    fn inserted(&self, column: &str) -> String {
+        match self.dialect {
+            Dialect::Postgres => format!("EXCLUDED.{}", column),
+            Dialect::Mysql => format!("VALUES({})", column)
+        }
+    }
+
+    /// Applies every migration with its version row in one transaction, so failed migration is retried
+    /// whole on next start. Mysql commits schema statements right away, so migrations are kept idempotent
+    fn migrate(&self) -> Result<(), StorageError> {
+        let migrations = match self.dialect {
+            Dialect::Postgres => POSTGRES_MIGRATIONS,
+            Dialect::Mysql => MYSQL_MIGRATIONS
+        };
+
+        self.runtime.block_on(async {
+            sqlx::query("CREATE TABLE IF NOT EXISTS meexprox_schema (version BIGINT NOT NULL)")
+                .execute(&self.pool).await?;
+
+            let version: i64 = sqlx::query("SELECT MAX(version) FROM meexprox_schema")
+                .fetch_one(&self.pool).await?
+                .try_get::<Option<i64>, _>(0)?
+                .unwrap_or(0);
+
+            for (index, migration) in migrations.iter().enumerate().skip(version as usize) {
+                let mut transaction = self.pool.begin().await?;
+                for statement in migration.iter() {
+                    sqlx::query(statement).execute(&mut *transaction).await?;
+                }
+                sqlx::query(&self.sql("INSERT INTO meexprox_schema (version) VALUES (?)"))
+                    .bind(index as i64 + 1)
+                    .execute(&mut *transaction).await?;
+                transaction.commit().await?;
+            }
+
+            Ok(())
+        })
+    }
+
+    fn read_ban(row: AnyRow) -> Result<Ban, sqlx::Error> {
+        Ok(Ban {
+            name: row.try_get(0)?,
+            uuid: row.try_get::<Option<String>, _>(1)?.and_then(|o| Uuid::parse_str(&o).ok()),
+            reason: row.try_get(2)?,
+            created: row.try_get(3)?,
+            expires: row.try_get(4)?
+        })
+    }
+
+    fn read_player(row: AnyRow) -> Result<PlayerRecord, sqlx::Error> {
+        Ok(PlayerRecord {
+            uuid: Uuid::parse_str(&row.try_get::<String, _>(0)?).unwrap_or_default(),
+            name: row.try_get(1)?,
+            first_seen: row.try_get(2)?,
+            last_seen: row.try_get(3)?,
+            play_time: row.try_get(4)?,
+            server_time: Vec::new()
+        })
+    }
+
+    /// Last seen player matching condition on players table, with its time on servers
+    fn find_player(&self, condition: &str, value: &str) -> Result<Option<PlayerRecord>, StorageError> {
+        self.runtime.block_on(async {
+            let row = sqlx::query(&self.sql(&format!(
+                "SELECT uuid, name, first_seen, last_seen, play_time FROM players
+                WHERE {} ORDER BY last_seen DESC LIMIT 1", condition
+            )))
+                .bind(value)
+                .fetch_optional(&self.pool).await?;
+            let Some(mut record) = row.map(Self::read_player).transpose()? else {
+                return Ok(None);
+            };
+
+            let rows = sqlx::query(&self.sql("SELECT server, time FROM server_time WHERE uuid = ? ORDER BY time DESC"))
+                .bind(record.uuid.to_string())
+                .fetch_all(&self.pool).await?;
+            record.server_time = rows.iter()
+                .map(|o| Ok((o.try_get(0)?, o.try_get(1)?)))
+                .collect::<Result<Vec<(String, i64)>, sqlx::Error>>()?;
+            Ok(Some(record))
+        })
+    }
+}
+
+impl Storage for SqlStore {
+    fn add_ban(&self, ban: &Ban) -> Result<(), StorageError> {
+        self.runtime.block_on(
+            sqlx::query(&self.sql("INSERT INTO bans (name, uuid, reason, created, expires) VALUES (?, ?, ?, ?, ?)"))
+                .bind(&ban.name)
+                .bind(ban.uuid.map(|o| o.to_string()))
+                .bind(&ban.reason)
+                .bind(ban.created)
+                .bind(ban.expires)
+                .execute(&self.pool)
+        )?;
+        Ok(())
+    }
+
+    fn remove_ban(&self, name: &str) -> Result<bool, StorageError> {
+        let result = self.runtime.block_on(
+            sqlx::query(&self.sql("DELETE FROM bans WHERE LOWER(name) = LOWER(?)"))
+                .bind(name)
+                .execute(&self.pool)
+        )?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    fn get_ban(&self, name: &str, uuid: Option<Uuid>) -> Result<Option<Ban>, StorageError> {
+        let row = self.runtime.block_on(
+            sqlx::query(&self.sql(
+                "SELECT name, uuid, reason, created, expires FROM bans
+                WHERE (LOWER(name) = LOWER(?) OR uuid = ?) AND (expires IS NULL OR expires > ?)
+                ORDER BY created DESC LIMIT 1"
+            ))
+                .bind(name)
+                .bind(uuid.map(|o| o.to_string()))
+                .bind(storage::now())
+                .fetch_optional(&self.pool)
+        )?;
+        Ok(row.map(Self::read_ban).transpose()?)
+    }
+
+    fn whitelist_add(&self, name: &str) -> Result<(), StorageError> {
+        let query = self.upsert("INSERT INTO whitelist (name) VALUES (?)", "(LOWER(name))", &format!("name = {}", self.inserted("name")));
+        self.runtime.block_on(
+            sqlx::query(&query)
+                .bind(name)
+                .execute(&self.pool)
+        )?;
+        Ok(())
+    }
+
+    fn whitelist_remove(&self, name: &str) -> Result<bool, StorageError> {
+        let result = self.runtime.block_on(
+            sqlx::query(&self.sql("DELETE FROM whitelist WHERE LOWER(name) = LOWER(?)"))
+                .bind(name)
+                .execute(&self.pool)
+        )?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    fn is_whitelisted(&self, name: &str) -> Result<bool, StorageError> {
+        let row = self.runtime.block_on(
+            sqlx::query(&self.sql("SELECT name FROM whitelist WHERE LOWER(name) = LOWER(?)"))
+                .bind(name)
+                .fetch_optional(&self.pool)
+        )?;
+        Ok(row.is_some())
+    }
+
+    fn whitelist(&self) -> Result<Vec<String>, StorageError> {
+        let rows = self.runtime.block_on(
+            sqlx::query("SELECT name FROM whitelist ORDER BY name").fetch_all(&self.pool)
+        )?;
+        Ok(rows.iter().map(|o| o.try_get(0)).collect::<Result<Vec<String>, sqlx::Error>>()?)
+    }
+
+    fn record_join(&self, uuid: Uuid, name: &str) -> Result<(), StorageError> {
+        let now = storage::now();
+        let query = self.upsert(
+            "INSERT INTO players (uuid, name, first_seen, last_seen) VALUES (?, ?, ?, ?)",
+            "uuid",
+            &format!("name = {}, last_seen = {}", self.inserted("name"), self.inserted("last_seen"))
+        );
+        self.runtime.block_on(
+            sqlx::query(&query)
+                .bind(uuid.to_string())
+                .bind(name)
+                .bind(now)
+                .bind(now)
+                .execute(&self.pool)
+        )?;
+        Ok(())
+    }
+
+    fn record_quit(&self, uuid: Uuid, session: i64) -> Result<(), StorageError> {
+        self.runtime.block_on(
+            sqlx::query(&self.sql("UPDATE players SET last_seen = ?, play_time = play_time + ? WHERE uuid = ?"))
+                .bind(storage::now())
+                .bind(session)
+                .bind(uuid.to_string())
+                .execute(&self.pool)
+        )?;
+        Ok(())
+    }
+
+    fn record_server_time(&self, uuid: Uuid, server: &str, time: i64) -> Result<(), StorageError> {
+        self.runtime.block_on(async {
+            let query = self.upsert(
+                "INSERT INTO server_time (uuid, server, time) VALUES (?, ?, ?)",
+                "uuid, server",
+                &format!("time = server_time.time + {}", self.inserted("time"))
+            );
+            sqlx::query(&query)
+                .bind(uuid.to_string())
+                .bind(server)
+                .bind(time)
+                .execute(&self.pool).await?;
+
+            sqlx::query(&self.sql("UPDATE players SET last_seen = ? WHERE uuid = ?"))
+                .bind(storage::now())
+                .bind(uuid.to_string())
+                .execute(&self.pool).await?;
+
+            Ok(())
+        })
+    }
+
+    fn get_player(&self, name: &str) -> Result<Option<PlayerRecord>, StorageError> {
+        self.find_player("LOWER(name) = LOWER(?)", name)
+    }
+
+    fn get_player_by_uuid(&self, uuid: Uuid) -> Result<Option<PlayerRecord>, StorageError> {
+        self.find_player("uuid = ?", &uuid.to_string())
+    }
+
+    fn set_cookie(&self, uuid: Uuid, key: &str, data: &[u8]) -> Result<(), StorageError> {
+        let query = self.upsert(
+            "INSERT INTO cookies (uuid, cookie, data) VALUES (?, ?, ?)",
+            "uuid, cookie",
+            &format!("data = {}", self.inserted("data"))
+        );
+        self.runtime.block_on(
+            sqlx::query(&query)
+                .bind(uuid.to_string())
+                .bind(key)
+                .bind(data.to_vec())
+                .execute(&self.pool)
+        )?;
+        Ok(())
+    }
+
+    fn get_cookie(&self, uuid: Uuid, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        let row = self.runtime.block_on(
+            sqlx::query(&self.sql("SELECT data FROM cookies WHERE uuid = ? AND cookie = ?"))
+                .bind(uuid.to_string())
+                .bind(key)
+                .fetch_optional(&self.pool)
+        )?;
+        Ok(row.map(|o| o.try_get(0)).transpose()?)
+    }
+}
@@ -0,0 +1,203 @@
+use std::path::Path;
+
+use parking_lot::Mutex;
+use rusqlite::{params, Connection, OptionalExtension};
+use uuid::Uuid;
+
+use super::storage::{self, Ban, PlayerRecord, Storage, StorageError};
+
+/// Schema changes applied in order, `user_version` pragma keeps the number of applied ones
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE bans (
+        name TEXT NOT NULL COLLATE NOCASE,
+        uuid TEXT,
+        reason TEXT NOT NULL,
+        created INTEGER NOT NULL,
+        expires INTEGER
+    );
+    CREATE INDEX bans_name ON bans (name);
+    CREATE TABLE whitelist (
+        name TEXT PRIMARY KEY COLLATE NOCASE
+    );
+    CREATE TABLE players (
+        uuid TEXT PRIMARY KEY,
+        name TEXT NOT NULL COLLATE NOCASE,
+        first_seen INTEGER NOT NULL,
+        last_seen INTEGER NOT NULL,
+        play_time INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX players_name ON players (name);",
+    "CREATE TABLE cookies (
+        uuid TEXT NOT NULL,
+        cookie TEXT NOT NULL,
+        data BLOB NOT NULL,
+        PRIMARY KEY (uuid, cookie)
+    );",
+    "CREATE TABLE server_time (
+        uuid TEXT NOT NULL,
+        server TEXT NOT NULL,
+        time INTEGER NOT NULL DEFAULT 0,
+        PRIMARY KEY (uuid, server)
+    );",
+];
+
+pub struct SqliteStore {
+    conn: Mutex<Connection>
+}
+
+impl SqliteStore {
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<SqliteStore> {
+        let mut conn = Connection::open(path)?;
+        Self::migrate(&mut conn)?;
+        Ok(SqliteStore { conn: Mutex::new(conn) })
+    }
+
+    fn migrate(conn: &mut Connection) -> rusqlite::Result<()> {
+        let version: usize = conn.query_row("PRAGMA user_version", [], |o| o.get(0))?;
+
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            let transaction = conn.transaction()?;
+            transaction.execute_batch(migration)?;
+            transaction.pragma_update(None, "user_version", index + 1)?;
+            transaction.commit()?;
+        }
+
+        Ok(())
+    }
+
+    /// Last seen player matching condition on players table, with its time on servers
+    fn find_player(&self, condition: &str, value: &str) -> Result<Option<PlayerRecord>, StorageError> {
+        let conn = self.conn.lock();
+        let record = conn.query_row(
+            &format!("SELECT uuid, name, first_seen, last_seen, play_time FROM players
+            WHERE {} ORDER BY last_seen DESC LIMIT 1", condition),
+            params![value],
+            |o| Ok(PlayerRecord {
+                uuid: Uuid::parse_str(&o.get::<_, String>(0)?).unwrap_or_default(),
+                name: o.get(1)?,
+                first_seen: o.get(2)?,
+                last_seen: o.get(3)?,
+                play_time: o.get(4)?,
+                server_time: Vec::new()
+            })
+        ).optional()?;
+
+        let Some(mut record) = record else {
+            return Ok(None);
+        };
+        let mut statement = conn.prepare("SELECT server, time FROM server_time WHERE uuid = ?1 ORDER BY time DESC")?;
+        record.server_time = statement.query_map(params![record.uuid.to_string()], |o| Ok((o.get(0)?, o.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<(String, i64)>>>()?;
+        Ok(Some(record))
+    }
+}
+
+impl Storage for SqliteStore {
+    fn add_ban(&self, ban: &Ban) -> Result<(), StorageError> {
+        self.conn.lock().execute(
+            "INSERT INTO bans (name, uuid, reason, created, expires) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![ban.name, ban.uuid.map(|o| o.to_string()), ban.reason, ban.created, ban.expires]
+        )?;
+        Ok(())
+    }
+
+    fn remove_ban(&self, name: &str) -> Result<bool, StorageError> {
+        Ok(self.conn.lock().execute("DELETE FROM bans WHERE name = ?1 COLLATE NOCASE", params![name])? > 0)
+    }
+
+    fn get_ban(&self, name: &str, uuid: Option<Uuid>) -> Result<Option<Ban>, StorageError> {
+        self.conn.lock().query_row(
+            "SELECT name, uuid, reason, created, expires FROM bans
+            WHERE (name = ?1 COLLATE NOCASE OR uuid = ?2) AND (expires IS NULL OR expires > ?3)
+            ORDER BY created DESC LIMIT 1",
+            params![name, uuid.map(|o| o.to_string()), storage::now()],
+            |o| Ok(Ban {
+                name: o.get(0)?,
+                uuid: o.get::<_, Option<String>>(1)?.and_then(|o| Uuid::parse_str(&o).ok()),
+                reason: o.get(2)?,
+                created: o.get(3)?,
+                expires: o.get(4)?
+            })
+        ).optional().map_err(|o| o.into())
+    }
+
+    fn whitelist_add(&self, name: &str) -> Result<(), StorageError> {
+        self.conn.lock().execute("INSERT OR IGNORE INTO whitelist (name) VALUES (?1)", params![name])?;
+        Ok(())
+    }
+
+    fn whitelist_remove(&self, name: &str) -> Result<bool, StorageError> {
+        Ok(self.conn.lock().execute("DELETE FROM whitelist WHERE name = ?1 COLLATE NOCASE", params![name])? > 0)
+    }
+
+    fn is_whitelisted(&self, name: &str) -> Result<bool, StorageError> {
+        self.conn.lock()
+            .query_row("SELECT 1 FROM whitelist WHERE name = ?1 COLLATE NOCASE", params![name], |_| Ok(()))
+            .optional()
+            .map(|o| o.is_some())
+            .map_err(|o| o.into())
+    }
+
+    fn whitelist(&self) -> Result<Vec<String>, StorageError> {
+        let conn = self.conn.lock();
+        let mut statement = conn.prepare("SELECT name FROM whitelist ORDER BY name")?;
+        let names = statement.query_map([], |o| o.get(0))?.collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(names)
+    }
+
+    fn record_join(&self, uuid: Uuid, name: &str) -> Result<(), StorageError> {
+        let now = storage::now();
+        self.conn.lock().execute(
+            "INSERT INTO players (uuid, name, first_seen, last_seen) VALUES (?1, ?2, ?3, ?3)
+            ON CONFLICT (uuid) DO UPDATE SET name = ?2, last_seen = ?3",
+            params![uuid.to_string(), name, now]
+        )?;
+        Ok(())
+    }
+
+    fn record_quit(&self, uuid: Uuid, session: i64) -> Result<(), StorageError> {
+        self.conn.lock().execute(
+            "UPDATE players SET last_seen = ?2, play_time = play_time + ?3 WHERE uuid = ?1",
+            params![uuid.to_string(), storage::now(), session]
+        )?;
+        Ok(())
+    }
+
+    fn record_server_time(&self, uuid: Uuid, server: &str, time: i64) -> Result<(), StorageError> {
+        let conn = self.conn.lock();
+        conn.execute(
+            "INSERT INTO server_time (uuid, server, time) VALUES (?1, ?2, ?3)
+            ON CONFLICT (uuid, server) DO UPDATE SET time = time + ?3",
+            params![uuid.to_string(), server, time]
+        )?;
+        conn.execute(
+            "UPDATE players SET last_seen = ?2 WHERE uuid = ?1",
+            params![uuid.to_string(), storage::now()]
+        )?;
+        Ok(())
+    }
+
+    fn get_player(&self, name: &str) -> Result<Option<PlayerRecord>, StorageError> {
+        self.find_player("name = ?1 COLLATE NOCASE", name)
+    }
+
+    fn get_player_by_uuid(&self, uuid: Uuid) -> Result<Option<PlayerRecord>, StorageError> {
+        self.find_player("uuid = ?1", &uuid.to_string())
+    }
+
+    fn set_cookie(&self, uuid: Uuid, key: &str, data: &[u8]) -> Result<(), StorageError> {
+        self.conn.lock().execute(
+            "INSERT OR REPLACE INTO cookies (uuid, cookie, data) VALUES (?1, ?2, ?3)",
+            params![uuid.to_string(), key, data]
+        )?;
+        Ok(())
+    }
+
+    fn get_cookie(&self, uuid: Uuid, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        self.conn.lock().query_row(
+            "SELECT data FROM cookies WHERE uuid = ?1 AND cookie = ?2",
+            params![uuid.to_string(), key],
+            |o| o.get(0)
+        ).optional().map_err(|o| o.into())
+    }
+}
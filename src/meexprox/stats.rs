@@ -0,0 +1,230 @@
+use std::{collections::{HashMap, VecDeque}, sync::atomic::{AtomicU64, Ordering}, time::{Duration, Instant}};
+
+use parking_lot::Mutex;
+
+use super::connection::ClientState;
+
+const STATES: [ClientState; 3] = [ClientState::Login, ClientState::Configuration, ClientState::Play];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PacketDirection {
+    Serverbound,
+    Clientbound
+}
+
+impl PacketDirection {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PacketDirection::Serverbound => "serverbound",
+            PacketDirection::Clientbound => "clientbound"
+        }
+    }
+}
+
+/// Packets relayed with one id in one state and direction
+#[derive(Clone, Copy, Debug)]
+pub struct PacketCount {
+    pub direction: PacketDirection,
+    pub state: ClientState,
+    pub id: u8,
+    pub count: u64,
+    pub bytes: u64
+}
+
+#[derive(Default, Debug)]
+struct PacketCounter {
+    count: AtomicU64,
+    bytes: AtomicU64
+}
+
+/// Totals sampled every second are kept for this long, longest window of throughput
+const SAMPLES_KEPT: Duration = Duration::from_secs(60);
+
+/// Packets and bytes of both directions per second, averaged over window
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Throughput {
+    pub packets: f64,
+    pub bytes: f64
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Sample {
+    time: Instant,
+    packets: u64,
+    bytes: u64
+}
+
+/// Proxy-wide traffic counters, updated by relay loops
+#[derive(Debug)]
+pub struct ProxyStats {
+    started: Instant,
+    connections: AtomicU64,
+    status_requests: AtomicU64,
+    packets_serverbound: AtomicU64,
+    packets_clientbound: AtomicU64,
+    bytes_serverbound: AtomicU64,
+    bytes_clientbound: AtomicU64,
+    /// Indexed by direction, state and packet id
+    packets: Vec<PacketCounter>,
+    /// Failed connects by server name, of logins and switches
+    connect_failures: Mutex<HashMap<String, u64>>,
+    /// Totals taken by [`ProxyStats::sample`], oldest first
+    samples: Mutex<VecDeque<Sample>>
+}
+
+impl Default for ProxyStats {
+    fn default() -> ProxyStats {
+        ProxyStats {
+            started: Instant::now(),
+            connections: AtomicU64::new(0),
+            status_requests: AtomicU64::new(0),
+            packets_serverbound: AtomicU64::new(0),
+            packets_clientbound: AtomicU64::new(0),
+            bytes_serverbound: AtomicU64::new(0),
+            bytes_clientbound: AtomicU64::new(0),
+            packets: (0..2 * STATES.len() * 256).map(|_| PacketCounter::default()).collect(),
+            connect_failures: Mutex::new(HashMap::new()),
+            samples: Mutex::new(VecDeque::new())
+        }
+    }
+}
+
+impl ProxyStats {
+    pub fn new() -> ProxyStats {
+        ProxyStats::default()
+    }
+
+    /// Time since proxy was created
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    fn packet_counter(&self, direction: PacketDirection, state: ClientState, id: u8) -> &PacketCounter {
+        let direction = match direction {
+            PacketDirection::Serverbound => 0,
+            PacketDirection::Clientbound => 1
+        };
+        let state = STATES.iter().position(|o| *o == state).unwrap_or(0);
+        &self.packets[(direction * STATES.len() + state) * 256 + id as usize]
+    }
+
+    pub fn add_connection(&self) {
+        self.connections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add_status_request(&self) {
+        self.status_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add_serverbound(&self, state: ClientState, id: u8, bytes: usize) {
+        self.packets_serverbound.fetch_add(1, Ordering::Relaxed);
+        self.bytes_serverbound.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.add_packet(PacketDirection::Serverbound, state, id, bytes);
+    }
+
+    pub fn add_clientbound(&self, state: ClientState, id: u8, bytes: usize) {
+        self.packets_clientbound.fetch_add(1, Ordering::Relaxed);
+        self.bytes_clientbound.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.add_packet(PacketDirection::Clientbound, state, id, bytes);
+    }
+
+    fn add_packet(&self, direction: PacketDirection, state: ClientState, id: u8, bytes: usize) {
+        let counter = self.packet_counter(direction, state, id);
+        counter.count.fetch_add(1, Ordering::Relaxed);
+        counter.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    pub fn add_connect_failure(&self, server: &str) {
+        *self.connect_failures.lock().entry(server.to_string()).or_default() += 1;
+    }
+
+    /// Failed connects by server, sorted by server name
+    pub fn connect_failures(&self) -> Vec<(String, u64)> {
+        let mut failures: Vec<(String, u64)> = self.connect_failures.lock().iter().map(|o| (o.0.clone(), *o.1)).collect();
+        failures.sort();
+        failures
+    }
+
+    /// Takes current totals for throughput, called every second by proxy
+    pub fn sample(&self) {
+        let now = Instant::now();
+        let mut samples = self.samples.lock();
+        samples.push_back(Sample {
+            time: now,
+            packets: self.packets_serverbound() + self.packets_clientbound(),
+            bytes: self.bytes_serverbound() + self.bytes_clientbound()
+        });
+        while samples.front().is_some_and(|o| now - o.time > SAMPLES_KEPT) {
+            samples.pop_front();
+        }
+    }
+
+    /// Rolling average over the last window (up to a minute), zero until two samples are taken
+    pub fn throughput(&self, window: Duration) -> Throughput {
+        let samples = self.samples.lock();
+        let Some(last) = samples.back() else {
+            return Throughput::default();
+        };
+        let Some(first) = samples.iter().find(|o| last.time - o.time <= window) else {
+            return Throughput::default();
+        };
+        let elapsed = (last.time - first.time).as_secs_f64();
+        if elapsed <= 0.0 {
+            return Throughput::default();
+        }
+        Throughput {
+            packets: (last.packets - first.packets) as f64 / elapsed,
+            bytes: (last.bytes - first.bytes) as f64 / elapsed
+        }
+    }
+
+    pub fn connections(&self) -> u64 {
+        self.connections.load(Ordering::Relaxed)
+    }
+
+    pub fn status_requests(&self) -> u64 {
+        self.status_requests.load(Ordering::Relaxed)
+    }
+
+    pub fn packets_serverbound(&self) -> u64 {
+        self.packets_serverbound.load(Ordering::Relaxed)
+    }
+
+    pub fn packets_clientbound(&self) -> u64 {
+        self.packets_clientbound.load(Ordering::Relaxed)
+    }
+
+    pub fn bytes_serverbound(&self) -> u64 {
+        self.bytes_serverbound.load(Ordering::Relaxed)
+    }
+
+    pub fn bytes_clientbound(&self) -> u64 {
+        self.bytes_clientbound.load(Ordering::Relaxed)
+    }
+
+    /// Counters of every packet seen at least once, most bytes first
+    pub fn packet_counts(&self) -> Vec<PacketCount> {
+        let mut counts = Vec::new();
+
+        for direction in [PacketDirection::Serverbound, PacketDirection::Clientbound] {
+            for state in STATES {
+                for id in 0..=255 {
+                    let counter = self.packet_counter(direction, state, id);
+                    let count = counter.count.load(Ordering::Relaxed);
+                    if count > 0 {
+                        counts.push(PacketCount {
+                            direction,
+                            state,
+                            id,
+                            count,
+                            bytes: counter.bytes.load(Ordering::Relaxed)
+                        });
+                    }
+                }
+            }
+        }
+
+        counts.sort_by_key(|o| std::cmp::Reverse(o.bytes));
+        counts
+    }
+}
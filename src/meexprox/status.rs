@@ -0,0 +1,123 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::component::Component;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct StatusVersion {
+    pub name: String,
+    /// Some servers and pingers leave it out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<i32>
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StatusPlayerSample {
+    pub name: String,
+    /// Not always valid uuid, servers put anything here to show custom lines in hover
+    pub id: String
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct StatusPlayers {
+    pub max: i32,
+    pub online: i32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sample: Vec<StatusPlayerSample>
+}
+
+/// Server list status, sent as json in response to status request.
+/// Fields the server left out stay out, client shows `???` instead of player count if `players` is None
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct StatusResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<StatusVersion>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub players: Option<StatusPlayers>,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub description: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub favicon: Option<String>,
+    /// Fields not known by proxy (enforcesSecureChat, forgeData, etc.)
+    #[serde(flatten)]
+    pub extra: Map<String, Value>
+}
+
+impl StatusResponse {
+    pub fn new(version: String, protocol: i32, max_players: i32, online_players: i32, motd: &str) -> StatusResponse {
+        let mut status = StatusResponse::default();
+        status.set_version(version, protocol);
+        status.set_max_players(max_players);
+        status.set_online_players(online_players);
+        status.set_motd(motd);
+        status
+    }
+
+    /// Parses status, known fields of unexpected shape are kept in `extra` and sent back unchanged.
+    /// None if json is not an object
+    pub fn from_json(json: &str) -> Option<StatusResponse> {
+        let mut extra: Map<String, Value> = serde_json::from_str(json).ok()?;
+        Some(StatusResponse {
+            version: take_field(&mut extra, "version"),
+            players: take_field(&mut extra, "players"),
+            description: extra.remove("description").unwrap_or_default(),
+            favicon: take_field(&mut extra, "favicon"),
+            extra
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Sets description to text component
+    pub fn set_motd(&mut self, motd: impl Into<Component>) {
+        self.description = motd.into().to_json();
+    }
+
+    pub fn set_description(&mut self, description: Value) {
+        self.description = description;
+    }
+
+    pub fn set_version(&mut self, name: String, protocol: i32) {
+        self.extra.remove("version");
+        self.version = Some(StatusVersion { name, protocol: Some(protocol) });
+    }
+
+    pub fn set_max_players(&mut self, max: i32) {
+        self.players_mut().max = max;
+    }
+
+    pub fn set_online_players(&mut self, online: i32) {
+        self.players_mut().online = online;
+    }
+
+    pub fn set_sample(&mut self, sample: Vec<StatusPlayerSample>) {
+        self.players_mut().sample = sample;
+    }
+
+    /// Sets favicon from base64 encoded png
+    pub fn set_favicon(&mut self, favicon: Option<String>) {
+        self.extra.remove("favicon");
+        self.favicon = favicon.map(|o| {
+            if o.starts_with("data:") {
+                o
+            } else {
+                format!("data:image/png;base64,{}", o)
+            }
+        });
+    }
+
+    /// Players, made empty if server left them out or sent them in unexpected shape
+    fn players_mut(&mut self) -> &mut StatusPlayers {
+        self.extra.remove("players");
+        self.players.get_or_insert_with(StatusPlayers::default)
+    }
+}
+
+/// Takes field out of object if it has expected shape, otherwise leaves it there
+fn take_field<T: DeserializeOwned>(object: &mut Map<String, Value>, key: &str) -> Option<T> {
+    let value = serde_json::from_value(object.get(key)?.clone()).ok()?;
+    object.remove(key);
+    Some(value)
+}
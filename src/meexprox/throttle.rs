@@ -0,0 +1,147 @@
+use std::time::{Duration, Instant};
+
+use serde_yml::Mapping;
+
+/// Limits of relayed traffic in one direction, per second
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RateLimit {
+    /// Unlimited if None
+    pub bytes: Option<u64>,
+    pub packets: Option<u64>
+}
+
+impl RateLimit {
+    pub fn from_data(data: &Mapping) -> RateLimit {
+        let limit = |key: &str| data.get(key).and_then(|o| o.as_u64()).filter(|o| *o > 0);
+        RateLimit {
+            bytes: limit("bytes"),
+            packets: limit("packets")
+        }
+    }
+}
+
+/// Rate limits of every player connection, separate for both directions
+#[derive(Clone, Copy, Debug)]
+pub struct ThrottleConfig {
+    pub clientbound: RateLimit,
+    pub serverbound: RateLimit,
+    /// Traffic above limits allowed at once, as time of traffic at limit
+    pub burst: Duration,
+    /// Clients exceeding serverbound limits are disconnected instead of slowed down
+    pub kick: bool
+}
+
+impl ThrottleConfig {
+    pub fn from_data(data: &Mapping) -> Option<ThrottleConfig> {
+        if !data.get("enabled")?.as_bool()? {
+            return None;
+        }
+
+        let limit = |key: &str| data.get(key)
+            .and_then(|o| o.as_mapping())
+            .map(RateLimit::from_data)
+            .unwrap_or_default();
+
+        Some(ThrottleConfig {
+            clientbound: limit("clientbound"),
+            serverbound: limit("serverbound"),
+            burst: Duration::from_millis(data.get("burst")
+                .and_then(|o| o.as_f64())
+                .map(|o| (o.max(0.0) * 1000.0) as u64)
+                .unwrap_or(2000)),
+            kick: data.get("kick")
+                .and_then(|o| o.as_bool())
+                .unwrap_or(false)
+        })
+    }
+}
+
+/// Token bucket refilled at rate per second, tokens go below zero when taken over limit
+#[derive(Debug)]
+struct Bucket {
+    rate: f64,
+    capacity: f64,
+    tokens: f64,
+    updated: Instant
+}
+
+impl Bucket {
+    fn new(rate: u64, burst: Duration) -> Bucket {
+        let rate = rate as f64;
+        // at least one second of traffic, or limits under one would never let anything through
+        let capacity = rate * burst.as_secs_f64().max(1.0);
+        Bucket { rate, capacity, tokens: capacity, updated: Instant::now() }
+    }
+
+    /// Time to wait until taken amount is within limit
+    fn take(&mut self, amount: f64) -> Duration {
+        let now = Instant::now();
+        self.tokens = (self.tokens + now.duration_since(self.updated).as_secs_f64() * self.rate).min(self.capacity);
+        self.updated = now;
+        self.tokens -= amount;
+
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+/// Throttle of one direction of player connection, shared by relay loops of every backend player is on
+#[derive(Debug)]
+pub struct Throttle {
+    bytes: Option<Bucket>,
+    packets: Option<Bucket>
+}
+
+impl Throttle {
+    pub fn new(limit: &RateLimit, burst: Duration) -> Throttle {
+        Throttle {
+            bytes: limit.bytes.map(|o| Bucket::new(o, burst)),
+            packets: limit.packets.map(|o| Bucket::new(o, burst))
+        }
+    }
+
+    /// Takes packet of size, returns time relay has to wait before sending it, zero when within limits
+    pub fn take(&mut self, size: usize) -> Duration {
+        let bytes = self.bytes.as_mut().map(|o| o.take(size as f64)).unwrap_or_default();
+        let packets = self.packets.as_mut().map(|o| o.take(1.0)).unwrap_or_default();
+        bytes.max(packets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn traffic_within_burst_is_not_delayed() {
+        let mut throttle = Throttle::new(&RateLimit { bytes: Some(1000), packets: None }, Duration::from_secs(2));
+        assert_eq!(throttle.take(1500), Duration::ZERO);
+        assert_eq!(throttle.take(500), Duration::ZERO);
+    }
+
+    #[test]
+    fn traffic_over_limit_waits_for_refill() {
+        let mut throttle = Throttle::new(&RateLimit { bytes: Some(1000), packets: None }, Duration::from_secs(1));
+        assert_eq!(throttle.take(1000), Duration::ZERO);
+        let wait = throttle.take(500);
+        assert!(wait > Duration::from_millis(450) && wait <= Duration::from_millis(500), "{:?}", wait);
+    }
+
+    #[test]
+    fn longest_wait_of_both_limits_is_taken() {
+        let mut throttle = Throttle::new(&RateLimit { bytes: Some(1_000_000), packets: Some(10) }, Duration::ZERO);
+        for _ in 0..10 {
+            assert_eq!(throttle.take(1), Duration::ZERO);
+        }
+        assert!(throttle.take(1) > Duration::from_millis(90));
+    }
+
+    #[test]
+    fn unlimited_throttle_never_waits() {
+        let mut throttle = Throttle::new(&RateLimit::default(), Duration::ZERO);
+        assert_eq!(throttle.take(usize::MAX), Duration::ZERO);
+    }
+}
@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use ring::hmac;
+use serde_yml::Mapping;
+use uuid::Uuid;
+
+use super::storage;
+
+/// Longest cookie payload accepted by clients
+pub const MAX_COOKIE_SIZE: usize = 5120;
+/// Timestamp before payload and hmac after it
+const COOKIE_OVERHEAD: usize = 8 + 32;
+
+/// Signed cookies of players transferred between proxies
+#[derive(Clone, Debug)]
+pub struct TransferConfig {
+    /// Shared by every proxy players are transferred between
+    pub secret: String,
+    /// Key of cookie requested from transferred players
+    pub cookie: String,
+    /// Older cookies are not accepted
+    pub max_age: Duration,
+    /// Transferred players without valid cookie are disconnected
+    pub required: bool,
+    pub message: String
+}
+
+impl TransferConfig {
+    pub fn from_data(data: &Mapping) -> Option<TransferConfig> {
+        if !data.get("enabled")?.as_bool()? {
+            return None;
+        }
+
+        Some(TransferConfig {
+            secret: data.get("secret")?.as_str()?.to_string(),
+            cookie: data.get("cookie").and_then(|o| o.as_str()).unwrap_or("meexprox:transfer").to_string(),
+            max_age: Duration::from_secs(data.get("max_age").and_then(|o| o.as_u64()).unwrap_or(60)),
+            required: data.get("required").and_then(|o| o.as_bool()).unwrap_or(false),
+            message: data.get("message").and_then(|o| o.as_str()).unwrap_or("Invalid transfer").to_string()
+        })
+    }
+}
+
+fn signed_data(key: &str, uuid: Uuid, time: &[u8], payload: &[u8]) -> Vec<u8> {
+    [key.as_bytes(), &[0], uuid.as_bytes(), time, payload].concat()
+}
+
+/// Cookie with payload, signed with key of cookie, uuid of player it is stored on and current time
+pub fn sign(secret: &str, key: &str, uuid: Uuid, payload: &[u8]) -> Option<Vec<u8>> {
+    if payload.len() + COOKIE_OVERHEAD > MAX_COOKIE_SIZE {
+        return None;
+    }
+
+    let time = storage::now().to_be_bytes();
+    let hmac_key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let tag = hmac::sign(&hmac_key, &signed_data(key, uuid, &time, payload));
+    Some([&time, payload, tag.as_ref()].concat())
+}
+
+/// Payload of cookie if it is signed with secret for key and player uuid and not older than max age,
+/// so cookie copied from another player is not accepted
+pub fn verify(secret: &str, key: &str, uuid: Uuid, cookie: &[u8], max_age: Duration) -> Option<Vec<u8>> {
+    if cookie.len() < COOKIE_OVERHEAD {
+        return None;
+    }
+
+    let (time, rest) = cookie.split_at(8);
+    let (payload, tag) = rest.split_at(rest.len() - 32);
+
+    let hmac_key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    hmac::verify(&hmac_key, &signed_data(key, uuid, time, payload), tag).ok()?;
+
+    let age = storage::now() - i64::from_be_bytes(time.try_into().ok()?);
+    (age >= 0 && age as u64 <= max_age.as_secs()).then(|| payload.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "meexprox:transfer";
+    const MAX_AGE: Duration = Duration::from_secs(60);
+
+    /// Cookie signed as if it was stored at time
+    fn cookie_at(time: i64, uuid: Uuid, payload: &[u8]) -> Vec<u8> {
+        let time = time.to_be_bytes();
+        let tag = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, b"secret"), &signed_data(KEY, uuid, &time, payload));
+        [&time, payload, tag.as_ref()].concat()
+    }
+
+    #[test]
+    fn cookie_is_verified_for_its_player_only() {
+        let uuid = Uuid::from_u128(1);
+        let cookie = sign("secret", KEY, uuid, b"payload").unwrap();
+        assert_eq!(verify("secret", KEY, uuid, &cookie, MAX_AGE).as_deref(), Some(&b"payload"[..]));
+
+        assert!(verify("wrong", KEY, uuid, &cookie, MAX_AGE).is_none());
+        assert!(verify("secret", "other:key", uuid, &cookie, MAX_AGE).is_none());
+        assert!(verify("secret", KEY, Uuid::from_u128(2), &cookie, MAX_AGE).is_none());
+    }
+
+    #[test]
+    fn malformed_cookie_is_rejected() {
+        let uuid = Uuid::from_u128(1);
+        let cookie = sign("secret", KEY, uuid, b"payload").unwrap();
+
+        let mut tampered = cookie.clone();
+        tampered[8] ^= 1;
+        assert!(verify("secret", KEY, uuid, &tampered, MAX_AGE).is_none());
+        assert!(verify("secret", KEY, uuid, &cookie[..COOKIE_OVERHEAD - 1], MAX_AGE).is_none());
+        assert!(verify("secret", KEY, uuid, &[], MAX_AGE).is_none());
+    }
+
+    #[test]
+    fn old_and_future_cookies_are_rejected() {
+        let uuid = Uuid::from_u128(1);
+        let now = storage::now();
+
+        assert!(verify("secret", KEY, uuid, &cookie_at(now - 30, uuid, b""), MAX_AGE).is_some());
+        assert!(verify("secret", KEY, uuid, &cookie_at(now - 120, uuid, b""), MAX_AGE).is_none());
+        assert!(verify("secret", KEY, uuid, &cookie_at(now + 120, uuid, b""), MAX_AGE).is_none());
+    }
+
+    #[test]
+    fn too_large_payload_is_not_signed() {
+        assert!(sign("secret", KEY, Uuid::nil(), &[0; MAX_COOKIE_SIZE - COOKIE_OVERHEAD]).is_some());
+        assert!(sign("secret", KEY, Uuid::nil(), &[0; MAX_COOKIE_SIZE - COOKIE_OVERHEAD + 1]).is_none());
+    }
+}
@@ -0,0 +1,303 @@
+use std::{
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use log::{error, info, warn};
+use ring::{hmac, rand::{SecureRandom, SystemRandom}};
+use serde_yml::Mapping;
+
+use super::MeexProx;
+
+/// Random challenge sent by each side, answered with hmac-sha256 of role and challenge under shared secret
+const CHALLENGE_LEN: usize = 32;
+const ANSWER_LEN: usize = 32;
+
+/// Roles are signed with challenge, so answer of one side can't be reflected back as answer of the other
+const LINK_ROLE: &[u8] = b"link";
+const LISTENER_ROLE: &[u8] = b"listener";
+
+/// Time peer has to answer challenge
+const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Zstd compressed link to server that is another meexprox, per server in config
+#[derive(Clone, Debug)]
+pub struct TunnelLink {
+    /// Tunnel listener of the other proxy
+    pub host: String,
+    /// Shared with tunnel listener of the other proxy
+    pub secret: String,
+    /// Zstd level, 1 (fastest) to 22
+    pub level: i32
+}
+
+impl TunnelLink {
+    pub fn from_data(data: &Mapping) -> Option<TunnelLink> {
+        if !data.get("enabled")?.as_bool()? {
+            return None;
+        }
+
+        Some(TunnelLink {
+            host: data.get("host")?.as_str()?.to_string(),
+            secret: data.get("secret")?.as_str()?.to_string(),
+            level: data.get("level").and_then(|o| o.as_i64()).unwrap_or(3).clamp(1, 22) as i32
+        })
+    }
+}
+
+/// Accepts tunnels of other proxies that know secret, decompressed connections are accepted as usual clients
+#[derive(Clone, Debug)]
+pub struct TunnelListener {
+    pub host: String,
+    pub secret: String,
+    /// Zstd level of data sent back
+    pub level: i32
+}
+
+impl TunnelListener {
+    pub fn from_data(data: &Mapping) -> Option<TunnelListener> {
+        if !data.get("enabled")?.as_bool()? {
+            return None;
+        }
+
+        Some(TunnelListener {
+            host: data.get("host")?.as_str()?.to_string(),
+            secret: data.get("secret")?.as_str()?.to_string(),
+            level: data.get("level").and_then(|o| o.as_i64()).unwrap_or(3).clamp(1, 22) as i32
+        })
+    }
+}
+
+fn answer(secret: &str, role: &[u8], challenge: &[u8]) -> hmac::Tag {
+    hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes()), &[role, challenge].concat())
+}
+
+fn challenge() -> io::Result<[u8; CHALLENGE_LEN]> {
+    let mut challenge = [0; CHALLENGE_LEN];
+    SystemRandom::new().fill(&mut challenge).map_err(|_| io::Error::other("random generator error"))?;
+    Ok(challenge)
+}
+
+fn check_answer(stream: &mut TcpStream, secret: &str, role: &[u8], challenge: &[u8]) -> io::Result<bool> {
+    let mut tag = [0; ANSWER_LEN];
+    stream.read_exact(&mut tag)?;
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    Ok(hmac::verify(&key, &[role, challenge].concat(), &tag).is_ok())
+}
+
+/// Answers challenge of tunnel listener and checks its answer to own challenge, so both sides prove they know secret
+fn authenticate(stream: &mut TcpStream, secret: &str) -> io::Result<()> {
+    let mut theirs = [0; CHALLENGE_LEN];
+    stream.read_exact(&mut theirs)?;
+    let ours = challenge()?;
+    stream.write_all(&[&ours, answer(secret, LINK_ROLE, &theirs).as_ref()].concat())?;
+
+    if !check_answer(stream, secret, LISTENER_ROLE, &ours)? {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, "tunnel listener answered with wrong secret"));
+    }
+    Ok(())
+}
+
+/// Whether peer answered challenge with secret, nothing of peer reaches proxy before.
+/// Challenge of peer is answered only after that, so peers without secret learn nothing
+fn verify(stream: &mut TcpStream, secret: &str) -> io::Result<bool> {
+    let ours = challenge()?;
+    stream.write_all(&ours)?;
+
+    let mut theirs = [0; CHALLENGE_LEN];
+    stream.read_exact(&mut theirs)?;
+    if !check_answer(stream, secret, LINK_ROLE, &ours)? {
+        return Ok(false);
+    }
+
+    stream.write_all(answer(secret, LISTENER_ROLE, &theirs).as_ref())?;
+    Ok(true)
+}
+
+/// Connected pair of loopback streams, one end is used as backend connection by proxy.
+/// Other local processes can connect to the listener too, so connections from anywhere but first end are dropped
+fn loopback_pair() -> io::Result<(TcpStream, TcpStream)> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let first = TcpStream::connect(listener.local_addr()?)?;
+    let local = first.local_addr()?;
+    loop {
+        let (second, peer) = listener.accept()?;
+        if peer == local {
+            return Ok((first, second));
+        }
+        warn!("unexpected connection to tunnel loopback from {}", peer);
+    }
+}
+
+/// Copies plain stream into compressed one, flushing after every read so packets are not delayed
+#[cfg(feature = "zstd")]
+fn compress(mut plain: TcpStream, compressed: TcpStream, level: i32) -> io::Result<()> {
+    use std::net::Shutdown;
+
+    let mut encoder = zstd::stream::write::Encoder::new(compressed, level)?;
+    let mut buf = [0; 16384];
+    loop {
+        let len = plain.read(&mut buf)?;
+        if len == 0 {
+            return encoder.finish()?.shutdown(Shutdown::Write);
+        }
+        encoder.write_all(&buf[..len])?;
+        encoder.flush()?;
+    }
+}
+
+#[cfg(feature = "zstd")]
+fn decompress(compressed: TcpStream, mut plain: TcpStream) -> io::Result<()> {
+    use std::net::Shutdown;
+
+    let mut decoder = zstd::stream::read::Decoder::new(compressed)?;
+    let mut buf = [0; 16384];
+    loop {
+        let len = decoder.read(&mut buf)?;
+        if len == 0 {
+            return plain.shutdown(Shutdown::Write);
+        }
+        plain.write_all(&buf[..len])?;
+    }
+}
+
+/// Relays plain stream through compressed one in both directions, both are closed on error of either direction
+#[cfg(feature = "zstd")]
+fn pump(plain: TcpStream, compressed: TcpStream, level: i32) -> io::Result<()> {
+    use std::net::Shutdown;
+
+    let close = {
+        let (plain, compressed) = (plain.try_clone()?, compressed.try_clone()?);
+        move |e: io::Error| {
+            if e.kind() != io::ErrorKind::NotConnected {
+                warn!("tunnel error: {}", e);
+            }
+            plain.shutdown(Shutdown::Both).ok();
+            compressed.shutdown(Shutdown::Both).ok();
+        }
+    };
+    let close = Arc::new(close);
+
+    let (plain_reader, compressed_writer) = (plain.try_clone()?, compressed.try_clone()?);
+    let on_error = close.clone();
+    thread::spawn(move || compress(plain_reader, compressed_writer, level).unwrap_or_else(|e| on_error(e)));
+    thread::spawn(move || decompress(compressed, plain).unwrap_or_else(|e| close(e)));
+    Ok(())
+}
+
+#[cfg(not(feature = "zstd"))]
+fn pump(_plain: TcpStream, _compressed: TcpStream, _level: i32) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "meexprox is built without zstd feature"))
+}
+
+/// Opens tunnel to other proxy, returned stream carries plain minecraft connection
+pub fn connect(link: &TunnelLink) -> io::Result<TcpStream> {
+    let mut compressed = TcpStream::connect(&link.host)?;
+    compressed.set_nodelay(true)?;
+    compressed.set_read_timeout(Some(AUTH_TIMEOUT))?;
+    authenticate(&mut compressed, &link.secret)?;
+    compressed.set_read_timeout(None)?;
+    let (plain, inner) = loopback_pair()?;
+    pump(inner, compressed, link.level)?;
+    Ok(plain)
+}
+
+/// Tunnel of authenticated peer as client connection of proxy
+fn accept(meexprox: &Arc<MeexProx>, mut compressed: TcpStream, tunnel: &TunnelListener) -> io::Result<()> {
+    let addr = compressed.peer_addr()?;
+    compressed.set_nodelay(true)?;
+    compressed.set_read_timeout(Some(AUTH_TIMEOUT))?;
+    if !verify(&mut compressed, &tunnel.secret)? {
+        return Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("{} answered with wrong secret", addr)));
+    }
+    compressed.set_read_timeout(None)?;
+
+    let (plain, inner) = loopback_pair()?;
+    pump(inner, compressed, tunnel.level)?;
+    // client is accepted with address of peer, so ip blocks and trusted forwarding apply to it
+    meexprox.serve_client(plain, addr);
+    Ok(())
+}
+
+/// Accepts tunnels in background, every tunnel is served as a client connection from its peer
+pub fn start(meexprox: Arc<MeexProx>, tunnel: TunnelListener) {
+    let listener = match TcpListener::bind(&tunnel.host) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("tunnel listener {} bind error: {}", tunnel.host, e);
+            return;
+        }
+    };
+    info!("tunnel listener started on {}", tunnel.host);
+
+    thread::spawn(move || {
+        for compressed in listener.incoming().flatten() {
+            let (meexprox, tunnel) = (meexprox.clone(), tunnel.clone());
+            // slow peers don't hold other tunnels while answering challenge
+            thread::spawn(move || {
+                if let Err(e) = accept(&meexprox, compressed, &tunnel) {
+                    warn!("tunnel connection error: {}", e);
+                }
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let link = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        (link, listener.accept().unwrap().0)
+    }
+
+    #[test]
+    fn both_sides_with_secret_are_verified() {
+        let (mut link, mut listener) = pair();
+        let linked = thread::spawn(move || authenticate(&mut link, "secret"));
+        assert!(verify(&mut listener, "secret").unwrap());
+        linked.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn link_with_wrong_secret_is_rejected() {
+        let (mut link, mut listener) = pair();
+        let linked = thread::spawn(move || authenticate(&mut link, "wrong"));
+        assert!(!verify(&mut listener, "secret").unwrap());
+        // listener never answers, so link learns nothing about secret
+        drop(listener);
+        assert!(linked.join().unwrap().is_err());
+    }
+
+    #[test]
+    fn reflected_answer_of_link_is_rejected() {
+        let (mut link, mut fake) = pair();
+        let linked = thread::spawn(move || authenticate(&mut link, "secret"));
+        fake.write_all(&challenge().unwrap()).unwrap();
+        let mut theirs = [0; CHALLENGE_LEN + ANSWER_LEN];
+        fake.read_exact(&mut theirs).unwrap();
+
+        // challenge of first link is sent to second one, which answers it with secret
+        let (mut other, mut reflector) = pair();
+        thread::spawn(move || authenticate(&mut other, "secret"));
+        reflector.write_all(&theirs[..CHALLENGE_LEN]).unwrap();
+        let mut reflected = [0; CHALLENGE_LEN + ANSWER_LEN];
+        reflector.read_exact(&mut reflected).unwrap();
+
+        fake.write_all(&reflected[CHALLENGE_LEN..]).unwrap();
+        let error = linked.join().unwrap().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+    }
+
+    #[test]
+    fn peer_closing_before_answer_is_error() {
+        let (link, mut listener) = pair();
+        drop(link);
+        assert!(verify(&mut listener, "secret").is_err());
+    }
+}
@@ -0,0 +1,94 @@
+use std::{sync::Arc, thread::{self, JoinHandle}, time::Duration};
+
+use log::warn;
+use ring::hmac;
+use serde_yml::Mapping;
+
+use super::{record::{EventFilter, ProxyEventRecord}, MeexProx};
+
+const MAX_ATTEMPTS: u32 = 5;
+
+#[derive(Clone, Debug)]
+pub struct Webhook {
+    pub url: String,
+    /// Record types to send, every record is sent if None
+    pub events: Option<EventFilter>,
+    pub secret: Option<String>
+}
+
+impl Webhook {
+    pub fn from_data(data: &Mapping) -> Option<Webhook> {
+        let webhook = Webhook {
+            url: data.get("url")?.as_str()?.to_string(),
+            events: data.get("events")
+                .and_then(|o| o.as_sequence())
+                .map(|o| EventFilter::new(o.iter().filter_map(|o| o.as_str()))),
+            secret: data.get("secret")
+                .and_then(|o| o.as_str())
+                .map(|o| o.to_string())
+        };
+
+        for name in webhook.events.iter().flat_map(|o| o.unknown()) {
+            warn!("webhook {} filters unknown event type {}", webhook.url, name);
+        }
+
+        Some(webhook)
+    }
+
+    pub fn accepts(&self, record: &ProxyEventRecord) -> bool {
+        self.events.as_ref().is_none_or(|o| o.accepts(record))
+    }
+
+    /// Hex encoded hmac-sha256 of body
+    pub fn sign(secret: &str, body: &str) -> String {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+        hmac::sign(&key, body.as_bytes())
+            .as_ref()
+            .iter()
+            .map(|o| format!("{:02x}", o))
+            .collect()
+    }
+
+    /// Posts body, retrying with exponential backoff until it succeeds or attempts run out
+    fn send(&self, body: &str) {
+        let mut delay = Duration::from_secs(1);
+
+        for attempt in 1..=MAX_ATTEMPTS {
+            let mut request = ureq::post(&self.url)
+                .header("Content-Type", "application/json");
+
+            if let Some(secret) = &self.secret {
+                request = request.header("X-Meexprox-Signature", format!("sha256={}", Self::sign(secret, body)));
+            }
+
+            match request.send(body) {
+                Ok(_) => return,
+                Err(e) => {
+                    warn!("webhook {} error (attempt {}/{}): {}", self.url, attempt, MAX_ATTEMPTS, e);
+                }
+            }
+
+            if attempt < MAX_ATTEMPTS {
+                thread::sleep(delay);
+                delay *= 2;
+            }
+        }
+    }
+}
+
+/// Starts one thread per webhook, so slow endpoint does not delay the others
+pub fn start(meexprox: Arc<MeexProx>, webhooks: Vec<Webhook>) -> Vec<JoinHandle<()>> {
+    webhooks.into_iter()
+        .map(|webhook| {
+            let records = meexprox.subscribe();
+
+            thread::spawn(move || {
+                for record in records {
+                    if webhook.accepts(&record) {
+                        webhook.send(&record.to_json());
+                    }
+                }
+            })
+        })
+        .collect()
+}
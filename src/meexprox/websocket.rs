@@ -0,0 +1,81 @@
+use std::{
+    io::{self, Read, Write},
+    net::{Shutdown, TcpStream},
+    sync::Arc,
+    thread,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ring::digest;
+
+use super::{admin::HttpRequest, record::EventFilter, MeexProx};
+
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC11B65";
+
+pub fn is_upgrade(request: &HttpRequest) -> bool {
+    request.headers.get("upgrade")
+        .map(|o| o.eq_ignore_ascii_case("websocket"))
+        .unwrap_or(false)
+}
+
+/// Completes websocket handshake and streams event records as json text frames until client leaves
+pub fn serve_events(meexprox: &Arc<MeexProx>, request: &HttpRequest, mut stream: TcpStream) -> io::Result<()> {
+    let Some(key) = request.headers.get("sec-websocket-key") else {
+        return Err(io::ErrorKind::InvalidData.into());
+    };
+
+    let accept = STANDARD.encode(digest::digest(
+        &digest::SHA1_FOR_LEGACY_USE_ONLY,
+        format!("{}{}", key, ACCEPT_GUID).as_bytes()
+    ));
+
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept
+    )?;
+
+    stream.set_read_timeout(None)?;
+
+    let records = meexprox.subscribe();
+
+    // comma separated record types or event names, every record is sent if not set
+    let events = request.query.get("events").map(|o| EventFilter::new(o.split(',')));
+
+    // client frames are only read to notice close, proxy never expects data from subscribers
+    let mut reader = stream.try_clone()?;
+    thread::spawn(move || {
+        let mut buf = [0; 1024];
+        while let Ok(1..) = reader.read(&mut buf) {
+            if buf[0] & 0x0F == 0x08 { break }
+        }
+        reader.shutdown(Shutdown::Both).ok();
+    });
+
+    for record in records {
+        if events.as_ref().is_some_and(|o| !o.accepts(&record)) {
+            continue;
+        }
+        write_text_frame(&mut stream, &record.to_json())?;
+    }
+
+    Ok(())
+}
+
+fn write_text_frame(stream: &mut TcpStream, text: &str) -> io::Result<()> {
+    let payload = text.as_bytes();
+    let mut frame = vec![0x81];
+
+    if payload.len() < 126 {
+        frame.push(payload.len() as u8);
+    } else if payload.len() <= u16::MAX as usize {
+        frame.push(126);
+        frame.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+    } else {
+        frame.push(127);
+        frame.extend_from_slice(&(payload.len() as u64).to_be_bytes());
+    }
+
+    frame.extend_from_slice(payload);
+    stream.write_all(&frame)
+}
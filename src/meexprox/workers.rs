@@ -0,0 +1,154 @@
+use std::{
+    io,
+    sync::{atomic::{AtomicUsize, Ordering}, mpsc::{self, Sender}, Arc},
+    thread,
+};
+
+use core_affinity::CoreId;
+use log::warn;
+use parking_lot::Mutex;
+use serde_yml::Mapping;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Thread settings of proxy, applied on start only. Relays block on reads of their connection,
+/// so they are not pooled and their count is not bounded, every player has one per direction
+#[derive(Clone, Debug)]
+pub struct WorkerConfig {
+    /// Threads accepting connections on every listener
+    pub accept_threads: usize,
+    /// Threads handling handshakes, logins and status pings, thread per connection when 0
+    pub connection_workers: usize,
+    /// Stack size of relay threads in bytes, default of std when not set
+    pub relay_stack_size: Option<usize>,
+    /// Cpus that threads are pinned to in turn, not pinned when empty
+    pub cpu_affinity: Vec<usize>
+}
+
+impl Default for WorkerConfig {
+    fn default() -> WorkerConfig {
+        WorkerConfig {
+            accept_threads: 1,
+            connection_workers: 0,
+            relay_stack_size: None,
+            cpu_affinity: Vec::new()
+        }
+    }
+}
+
+impl WorkerConfig {
+    pub fn from_data(data: &Mapping) -> Option<WorkerConfig> {
+        Some(WorkerConfig {
+            accept_threads: data.get("accept_threads").and_then(|o| o.as_u64()).unwrap_or(1).max(1) as usize,
+            connection_workers: data.get("connection_workers").and_then(|o| o.as_u64()).unwrap_or(0) as usize,
+            relay_stack_size: data.get("relay_stack_size")
+                .and_then(|o| o.as_u64())
+                .filter(|o| *o > 0)
+                .map(|o| o as usize * 1024),
+            cpu_affinity: data.get("cpu_affinity")
+                .and_then(|o| o.as_sequence())
+                .map(|o| o.iter().filter_map(|o| o.as_u64()).map(|o| o as usize).collect())
+                .unwrap_or_default()
+        })
+    }
+}
+
+/// Spawns connection and relay threads as set in `workers` config
+pub struct Workers {
+    config: WorkerConfig,
+    next_cpu: AtomicUsize,
+    /// Jobs of connection workers, connections get own threads when not set
+    queue: Option<Mutex<Sender<Job>>>
+}
+
+impl Workers {
+    pub fn new(config: WorkerConfig) -> Workers {
+        let mut workers = Workers {
+            config,
+            next_cpu: AtomicUsize::new(0),
+            queue: None
+        };
+
+        if workers.config.connection_workers > 0 {
+            let (sender, receiver) = mpsc::channel::<Job>();
+            let receiver = Arc::new(Mutex::new(receiver));
+            for i in 0..workers.config.connection_workers {
+                let receiver = receiver.clone();
+                let cpu = workers.next_cpu();
+                thread::Builder::new()
+                    .name(format!("connection-worker-{}", i))
+                    .spawn(move || {
+                        pin(cpu);
+                        loop {
+                            let job = receiver.lock().recv();
+                            match job {
+                                Ok(job) => job(),
+                                Err(_) => break
+                            }
+                        }
+                    })
+                    .expect("connection worker spawn error");
+            }
+            workers.queue = Some(Mutex::new(sender));
+        }
+
+        workers
+    }
+
+    pub fn config(&self) -> &WorkerConfig {
+        &self.config
+    }
+
+    /// Cpu for next spawned thread, cpus of config are used in turn
+    fn next_cpu(&self) -> Option<usize> {
+        let cpus = &self.config.cpu_affinity;
+        (!cpus.is_empty()).then(|| cpus[self.next_cpu.fetch_add(1, Ordering::Relaxed) % cpus.len()])
+    }
+
+    /// Runs handling of new connection on worker, waits in queue when every worker is busy
+    pub fn execute(&self, job: impl FnOnce() + Send + 'static) {
+        if let Some(queue) = &self.queue {
+            if queue.lock().send(Box::new(job)).is_err() {
+                warn!("connection workers are stopped");
+            }
+            return;
+        }
+
+        let cpu = self.next_cpu();
+        thread::spawn(move || {
+            pin(cpu);
+            job();
+        });
+    }
+
+    /// Spawns thread relaying packets of one direction of player, never taken from a pool
+    pub fn spawn_relay(&self, relay: impl FnOnce() + Send + 'static) -> io::Result<()> {
+        let mut builder = thread::Builder::new();
+        if let Some(size) = self.config.relay_stack_size {
+            builder = builder.stack_size(size);
+        }
+
+        let cpu = self.next_cpu();
+        builder.spawn(move || {
+            pin(cpu);
+            relay();
+        }).map(|_| ())
+    }
+
+    /// Spawns thread accepting connections, pinned like the others
+    pub fn spawn_acceptor(&self, accept: impl FnOnce() + Send + 'static) {
+        let cpu = self.next_cpu();
+        thread::spawn(move || {
+            pin(cpu);
+            accept();
+        });
+    }
+}
+
+fn pin(cpu: Option<usize>) {
+    if let Some(id) = cpu {
+        if !core_affinity::set_for_current(CoreId { id }) {
+            warn!("thread can't be pinned to cpu {}", id);
+        }
+    }
+}
@@ -0,0 +1,18 @@
+use std::{fs, path::Path};
+
+use meexprox::dump;
+use serde_yml::Value;
+
+#[test]
+fn shipped_config_has_no_secrets_in_dump() {
+    let text = fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("config.yml")).unwrap();
+    let mut config: Value = serde_yml::from_str(&text).unwrap();
+    dump::redact(&mut config);
+    let redacted = serde_yml::to_string(&config).unwrap();
+
+    for secret in ["discord.com/api/webhooks", "qwerty123456", "postgres://"] {
+        assert!(!redacted.contains(secret), "{} is in dump", secret);
+    }
+    // values that are not secrets stay
+    assert!(redacted.contains("sloganmc.ru:25565"));
+}
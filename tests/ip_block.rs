@@ -0,0 +1,68 @@
+use std::{
+    net::{IpAddr, TcpListener, TcpStream},
+    path::Path,
+    thread,
+    time::Duration,
+};
+
+use meexprox::{config::ProxyConfig, ipblock::IpBlockConfig, MeexProx};
+use rust_mc_proto::{DataBufferWriter, MCConnTcp, Packet};
+use uuid::Uuid;
+
+/// Logs in with name that username rules deny, returns when proxy closes connection
+fn denied_login(meexprox: &std::sync::Arc<MeexProx>, listener: &TcpListener) {
+    let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let (accepted, _) = listener.accept().unwrap();
+    let proxy = meexprox.clone();
+    let serving = thread::spawn(move || proxy.accept_client(accepted));
+
+    let mut client = MCConnTcp::new(client);
+    client.write_packet(&Packet::build(0x00, |p| {
+        p.write_u16_varint(767)?;
+        p.write_string("play.localhost")?;
+        p.write_unsigned_short(25565)?;
+        p.write_u8_varint(2)
+    }).unwrap()).unwrap();
+    client.write_packet(&Packet::build(0x00, |p| {
+        p.write_string("not a valid name")?;
+        p.write_uuid(&Uuid::nil())
+    }).unwrap()).unwrap();
+
+    serving.join().unwrap().unwrap();
+}
+
+#[test]
+fn denied_logins_block_ip() {
+    // backend only has to accept connections, login is denied before anything is sent to it
+    let backend = TcpListener::bind("127.0.0.1:0").unwrap();
+    let backend_addr = backend.local_addr().unwrap().to_string();
+    thread::spawn(move || {
+        let mut connections = Vec::new();
+        for stream in backend.incoming().flatten() {
+            connections.push(stream);
+        }
+    });
+
+    let mut config = ProxyConfig::load(Path::new(env!("CARGO_MANIFEST_DIR")).join("config.yml")).unwrap();
+    for server in &mut config.servers {
+        server.host = backend_addr.clone();
+    }
+    config.connection_pool = None;
+    config.ip_blocks = Some(IpBlockConfig {
+        failures: 3,
+        window: Duration::from_secs(60),
+        duration: Duration::from_secs(60)
+    });
+    let meexprox = MeexProx::new(config).init();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let ip: IpAddr = "127.0.0.1".parse().unwrap();
+
+    for _ in 0..2 {
+        denied_login(&meexprox, &listener);
+    }
+    assert!(!meexprox.ip_blocks().is_blocked(ip));
+
+    denied_login(&meexprox, &listener);
+    assert!(meexprox.ip_blocks().is_blocked(ip));
+}
@@ -0,0 +1,50 @@
+use std::{path::Path, time::Duration};
+
+use meexprox::{
+    config::ProxyConfig,
+    error::ProxyError,
+    event::{event_listener, Event, EventHandlers, EventListener, EventPriority, ListenerOrder, ServerStatusPingEvent},
+    MeexProx,
+};
+
+struct PanickingListener {
+    calls: u32
+}
+
+#[event_listener]
+impl PanickingListener {
+    #[event_handler]
+    fn on_ping(&mut self, event: &mut ServerStatusPingEvent) {
+        self.calls += 1;
+        if self.calls == 1 {
+            panic!("listener panic");
+        }
+        event.cancel();
+    }
+}
+
+fn ping_event() -> ServerStatusPingEvent {
+    ServerStatusPingEvent::new("127.0.0.1:25565".parse().unwrap(), "play".to_string(), 0, Duration::ZERO)
+}
+
+#[test]
+fn proxy_keeps_serving_after_listener_panic() {
+    let config = ProxyConfig::load(Path::new(env!("CARGO_MANIFEST_DIR")).join("config.yml")).unwrap();
+    let mut meexprox = MeexProx::new(config);
+    meexprox.add_event_handlers(PanickingListener { calls: 0 });
+    let meexprox = meexprox.init();
+
+    // panic is caught by dispatcher
+    let mut event = ping_event();
+    meexprox.trigger_event(&mut event).unwrap();
+    assert!(!event.is_cancelled());
+
+    // listener state and proxy locks are still usable after the panic
+    let mut event = ping_event();
+    meexprox.trigger_event(&mut event).unwrap();
+    assert!(event.is_cancelled());
+
+    assert!(meexprox.get_players().is_empty());
+    assert!(!meexprox.listener_timings().is_empty());
+    meexprox.reload_config().unwrap();
+}
@@ -0,0 +1,34 @@
+#![cfg(feature = "sqlite")]
+
+use std::{env, fs, process};
+
+use meexprox::{sqlite::SqliteStore, storage::{self, Ban, Storage}};
+use uuid::Uuid;
+
+#[test]
+fn names_are_matched_ignoring_case() {
+    let path = env::temp_dir().join(format!("meexprox_names_{}.db", process::id()));
+    fs::remove_file(&path).ok();
+    let store = SqliteStore::open(&path).unwrap();
+
+    store.add_ban(&Ban {
+        name: "MeexReay".to_string(),
+        uuid: None,
+        reason: "test".to_string(),
+        created: storage::now(),
+        expires: None
+    }).unwrap();
+    assert!(store.get_ban("meexreay", None).unwrap().is_some());
+    assert!(store.remove_ban("MEEXREAY").unwrap());
+
+    store.whitelist_add("MeexReay").unwrap();
+    assert!(store.is_whitelisted("meexreay").unwrap());
+    assert!(store.whitelist_remove("MEEXREAY").unwrap());
+
+    store.record_join(Uuid::from_u128(1), "MeexReay").unwrap();
+    assert!(store.get_player("meexreay").unwrap().is_some());
+    assert_eq!(store.get_player_by_uuid(Uuid::from_u128(1)).unwrap().unwrap().name, "MeexReay");
+
+    drop(store);
+    fs::remove_file(&path).ok();
+}